@@ -38,6 +38,7 @@ fn main() -> anyhow::Result<()> {
     let ser = Constructor::<OutputsOnlyModifiable>::new(psbt)?
         .output(OutputBuilder::new(output).build())
         .no_more_outputs()
+        .expect("no pending silent payment outputs")
         .psbt()
         .expect("valid lock time combination")
         .serialize();
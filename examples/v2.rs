@@ -18,9 +18,7 @@ use psbt_v2::bitcoin::secp256k1::{self, SECP256K1};
 use psbt_v2::bitcoin::{
     script, Address, Amount, Network, OutPoint, PublicKey, ScriptBuf, Sequence, TxOut, Txid,
 };
-use psbt_v2::v2::{
-    self, Constructor, InputBuilder, Modifiable, Output, OutputBuilder, Psbt, Signer, Updater,
-};
+use psbt_v2::v2::{self, Constructor, InputBuilder, Modifiable, Output, OutputBuilder, Psbt};
 
 pub const DUMMY_UTXO_AMOUNT: Amount = Amount::from_sat(20_000_000);
 pub const SPEND_AMOUNT: Amount = Amount::from_sat(20_000_000);
@@ -87,7 +85,7 @@ fn main() -> anyhow::Result<()> {
     // The updater role.
 
     // We can act as updater.
-    let psbt = Updater::new(psbt)?.set_sequence(Sequence::ENABLE_LOCKTIME_NO_RBF, 1)?.psbt();
+    let psbt = psbt.into_updater()?.set_sequence(Sequence::ENABLE_LOCKTIME_NO_RBF, 1)?.psbt();
 
     // Or we can get Alice and Bob to act as updaters.
     let updated_by_a = alice.update(psbt.clone())?;
@@ -280,7 +278,7 @@ impl Entity {
         let path = DerivationPath::from_str(derivation_path)?;
         let xpriv = self.master.derive_priv(SECP256K1, &path)?;
 
-        let signer = Signer::new(psbt)?;
+        let signer = psbt.into_signer()?;
         match signer.sign(&xpriv, SECP256K1) {
             Ok((psbt, _signing_keys)) => Ok(psbt),
             Err(e) => panic!("signing failed: {:?}", e),
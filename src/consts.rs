@@ -21,6 +21,10 @@ pub(crate) const PSBT_GLOBAL_INPUT_COUNT: u8 = 0x04;
 pub(crate) const PSBT_GLOBAL_OUTPUT_COUNT: u8 = 0x05;
 /// Type: Transaction Modifiable Flags PSBT_GLOBAL_TX_MODIFIABLE = 0x06
 pub(crate) const PSBT_GLOBAL_TX_MODIFIABLE: u8 = 0x06;
+/// Type: Silent Payment Global ECDH Share PSBT_GLOBAL_SP_ECDH_SHARE = 0x07
+pub(crate) const PSBT_GLOBAL_SP_ECDH_SHARE: u8 = 0x07;
+/// Type: Silent Payment Global DLEQ Proof PSBT_GLOBAL_SP_DLEQ = 0x08
+pub(crate) const PSBT_GLOBAL_SP_DLEQ: u8 = 0x08;
 /// Type: Version Number PSBT_GLOBAL_VERSION = 0xFB
 pub(crate) const PSBT_GLOBAL_VERSION: u8 = 0xFB;
 /// Type: Proprietary Use Type PSBT_GLOBAL_PROPRIETARY = 0xFC
@@ -77,6 +81,16 @@ pub(crate) const PSBT_IN_TAP_BIP32_DERIVATION: u8 = 0x16;
 pub(crate) const PSBT_IN_TAP_INTERNAL_KEY: u8 = 0x17;
 /// Type: Taproot Merkle Root PSBT_IN_TAP_MERKLE_ROOT = 0x18
 pub(crate) const PSBT_IN_TAP_MERKLE_ROOT: u8 = 0x18;
+/// Type: MuSig2 Participant Public Keys PSBT_IN_MUSIG2_PARTICIPANT_PUBKEYS = 0x1a
+pub(crate) const PSBT_IN_MUSIG2_PARTICIPANT_PUBKEYS: u8 = 0x1a;
+/// Type: MuSig2 Public Nonce PSBT_IN_MUSIG2_PUB_NONCE = 0x1b
+pub(crate) const PSBT_IN_MUSIG2_PUB_NONCE: u8 = 0x1b;
+/// Type: MuSig2 Participant Partial Signature PSBT_IN_MUSIG2_PARTIAL_SIG = 0x1c
+pub(crate) const PSBT_IN_MUSIG2_PARTIAL_SIG: u8 = 0x1c;
+/// Type: Silent Payment Input ECDH Share PSBT_IN_SP_ECDH_SHARE = 0x1d
+pub(crate) const PSBT_IN_SP_ECDH_SHARE: u8 = 0x1d;
+/// Type: Silent Payment Input DLEQ Proof PSBT_IN_SP_DLEQ = 0x1e
+pub(crate) const PSBT_IN_SP_DLEQ: u8 = 0x1e;
 /// Type: Proprietary Use Type PSBT_IN_PROPRIETARY = 0xFC
 pub(crate) const PSBT_IN_PROPRIETARY: u8 = 0xFC;
 
@@ -96,6 +110,12 @@ pub(crate) const PSBT_OUT_TAP_INTERNAL_KEY: u8 = 0x05;
 pub(crate) const PSBT_OUT_TAP_TREE: u8 = 0x06;
 /// Type: Taproot Key BIP 32 Derivation Path PSBT_OUT_TAP_BIP32_DERIVATION = 0x07
 pub(crate) const PSBT_OUT_TAP_BIP32_DERIVATION: u8 = 0x07;
+/// Type: MuSig2 Participant Public Keys PSBT_OUT_MUSIG2_PARTICIPANT_PUBKEYS = 0x08
+pub(crate) const PSBT_OUT_MUSIG2_PARTICIPANT_PUBKEYS: u8 = 0x08;
+/// Type: Silent Payment v0 Info PSBT_OUT_SP_V0_INFO = 0x09
+pub(crate) const PSBT_OUT_SP_V0_INFO: u8 = 0x09;
+/// Type: Silent Payment v0 Label PSBT_OUT_SP_V0_LABEL = 0x0a
+pub(crate) const PSBT_OUT_SP_V0_LABEL: u8 = 0x0a;
 /// Type: Proprietary Use Type PSBT_IN_PROPRIETARY = 0xFC
 pub(crate) const PSBT_OUT_PROPRIETARY: u8 = 0xFC;
 
@@ -109,6 +129,8 @@ pub(crate) fn psbt_global_key_type_value_to_str(v: u8) -> &'static str {
         PSBT_GLOBAL_INPUT_COUNT => "PSBT_GLOBAL_INPUT_COUNT",
         PSBT_GLOBAL_OUTPUT_COUNT => "PSBT_GLOBAL_OUTPUT_COUNT",
         PSBT_GLOBAL_TX_MODIFIABLE => "PSBT_GLOBAL_TX_MODIFIABLE",
+        PSBT_GLOBAL_SP_ECDH_SHARE => "PSBT_GLOBAL_SP_ECDH_SHARE",
+        PSBT_GLOBAL_SP_DLEQ => "PSBT_GLOBAL_SP_DLEQ",
         PSBT_GLOBAL_VERSION => "PSBT_GLOBAL_VERSION",
         PSBT_GLOBAL_PROPRIETARY => "PSBT_GLOBAL_PROPRIETARY",
         _ => "unknown PSBT_GLOBAL_ key type value",
@@ -143,6 +165,11 @@ pub(crate) fn psbt_in_key_type_value_to_str(v: u8) -> &'static str {
         PSBT_IN_TAP_BIP32_DERIVATION => "PSBT_IN_TAP_BIP32_DERIVATION",
         PSBT_IN_TAP_INTERNAL_KEY => "PSBT_IN_TAP_INTERNAL_KEY",
         PSBT_IN_TAP_MERKLE_ROOT => "PSBT_IN_TAP_MERKLE_ROOT",
+        PSBT_IN_MUSIG2_PARTICIPANT_PUBKEYS => "PSBT_IN_MUSIG2_PARTICIPANT_PUBKEYS",
+        PSBT_IN_MUSIG2_PUB_NONCE => "PSBT_IN_MUSIG2_PUB_NONCE",
+        PSBT_IN_MUSIG2_PARTIAL_SIG => "PSBT_IN_MUSIG2_PARTIAL_SIG",
+        PSBT_IN_SP_ECDH_SHARE => "PSBT_IN_SP_ECDH_SHARE",
+        PSBT_IN_SP_DLEQ => "PSBT_IN_SP_DLEQ",
         PSBT_IN_PROPRIETARY => "PSBT_IN_PROPRIETARY",
         _ => "unknown PSBT_IN_ key type value",
     }
@@ -159,6 +186,9 @@ pub(crate) fn psbt_out_key_type_value_to_str(v: u8) -> &'static str {
         PSBT_OUT_TAP_INTERNAL_KEY => "PSBT_OUT_TAP_INTERNAL_KEY",
         PSBT_OUT_TAP_TREE => "PSBT_OUT_TAP_TREE",
         PSBT_OUT_TAP_BIP32_DERIVATION => "PSBT_OUT_TAP_BIP32_DERIVATION",
+        PSBT_OUT_MUSIG2_PARTICIPANT_PUBKEYS => "PSBT_OUT_MUSIG2_PARTICIPANT_PUBKEYS",
+        PSBT_OUT_SP_V0_INFO => "PSBT_OUT_SP_V0_INFO",
+        PSBT_OUT_SP_V0_LABEL => "PSBT_OUT_SP_V0_LABEL",
         PSBT_OUT_PROPRIETARY => "PSBT_OUT_PROPRIETARY",
         _ => "unknown PSBT_OUT_ key type value",
     }
@@ -3,6 +3,7 @@
 use core::fmt;
 
 use bitcoin::bip32::Xpub;
+use bitcoin::{Amount, FeeRate, ScriptBuf, TxOut, Weight};
 
 /// Error combining two PSBTs, global extended public key has inconsistent key sources.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -63,6 +64,73 @@ impl From<FundingUtxoError> for FeeError {
     fn from(e: FundingUtxoError) -> Self { Self::FundingUtxo(e) }
 }
 
+/// Returns the fee rate of a transaction of `weight` paying `fee`.
+pub(crate) fn fee_rate(fee: Amount, weight: Weight) -> FeeRate {
+    FeeRate::from_sat_per_kwu(fee.to_sat().saturating_mul(1000) / weight.to_wu())
+}
+
+/// Returns the amount left for a change output paying to `script_pubkey` after paying `fee` from
+/// `value`.
+pub(crate) fn change_amount(
+    value: Amount,
+    fee: Amount,
+    script_pubkey: &ScriptBuf,
+) -> Result<Amount, PayFeeError> {
+    let amount = value
+        .checked_sub(fee)
+        .ok_or_else(|| PayFeeError::InsufficientFunds { missing: fee - value })?;
+    let dust = TxOut::minimal_non_dust(script_pubkey.clone()).value;
+    if amount < dust {
+        return Err(PayFeeError::ChangeBelowDust { amount, dust });
+    }
+    Ok(amount)
+}
+
+/// An error paying a fee from the available funds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PayFeeError {
+    /// The available funds do not cover the required fee.
+    InsufficientFunds {
+        /// The amount missing to pay the required fee.
+        missing: Amount,
+    },
+    /// Paying the required fee would leave the change output below the dust limit.
+    ChangeBelowDust {
+        /// The remaining change amount.
+        amount: Amount,
+        /// The minimal non-dust amount for the change output.
+        dust: Amount,
+    },
+    /// Integer overflow in fee calculation.
+    FeeOverflow,
+}
+
+impl fmt::Display for PayFeeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use PayFeeError::*;
+
+        match *self {
+            InsufficientFunds { missing } =>
+                write!(f, "insufficient funds to pay the fee, missing {}", missing),
+            ChangeBelowDust { amount, dust } =>
+                write!(f, "change amount {} is below the dust limit {}", amount, dust),
+            FeeOverflow => f.write_str("integer overflow in fee calculation"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PayFeeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use PayFeeError::*;
+
+        match *self {
+            InsufficientFunds { .. } | ChangeBelowDust { .. } | FeeOverflow => None,
+        }
+    }
+}
+
 /// An error getting the funding transaction for this input.
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[non_exhaustive]
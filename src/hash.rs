@@ -0,0 +1,17 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Hashing helpers shared by the MuSig2 and silent payments code.
+
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+
+/// Computes the BIP-340 tagged hash of the concatenation of `data`.
+pub(crate) fn tagged_hash<'a, I: IntoIterator<Item = &'a [u8]>>(tag: &str, data: I) -> [u8; 32] {
+    let tag = sha256::Hash::hash(tag.as_bytes());
+    let mut engine = sha256::Hash::engine();
+    engine.input(tag.as_ref());
+    engine.input(tag.as_ref());
+    for bytes in data {
+        engine.input(bytes);
+    }
+    sha256::Hash::from_engine(engine).to_byte_array()
+}
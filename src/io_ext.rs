@@ -0,0 +1,151 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Extensions to the `io` module used when encoding and decoding PSBTs as a stream.
+
+use bitcoin::consensus::encode::{Encodable, VarInt};
+
+use crate::io;
+use crate::prelude::*;
+
+/// Wraps a reader and counts the number of bytes read from it.
+pub(crate) struct CountingReader<'a, R: ?Sized> {
+    inner: &'a mut R,
+    count: usize,
+}
+
+impl<'a, R: io::Read + ?Sized> CountingReader<'a, R> {
+    /// Creates a new reader that counts the bytes read from `inner`.
+    pub(crate) fn new(inner: &'a mut R) -> Self { CountingReader { inner, count: 0 } }
+
+    /// Returns the number of bytes read so far.
+    pub(crate) fn count(&self) -> usize { self.count }
+}
+
+impl<'a, R: io::Read + ?Sized> io::Read for CountingReader<'a, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count += n;
+        Ok(n)
+    }
+}
+
+/// A PSBT key or value that can be written to a stream without first serializing it to a buffer.
+pub(crate) trait Encode {
+    /// Writes the raw key data or value data to `w`.
+    fn encode_to<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<()>;
+
+    /// Returns the number of bytes written by [`Encode::encode_to`].
+    ///
+    /// Counts the bytes by encoding to a sink, implementations should override this if the length
+    /// is known without encoding.
+    fn encoded_len(&self) -> usize {
+        let mut counter = ByteCounter(0);
+        self.encode_to(&mut counter).expect("counting writer does not error");
+        counter.0
+    }
+}
+
+impl Encode for [u8] {
+    fn encode_to<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<()> { w.write_all(self) }
+
+    fn encoded_len(&self) -> usize { self.len() }
+}
+
+impl<const N: usize> Encode for [u8; N] {
+    fn encode_to<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<()> { w.write_all(self) }
+
+    fn encoded_len(&self) -> usize { N }
+}
+
+impl Encode for Vec<u8> {
+    fn encode_to<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<()> { w.write_all(self) }
+
+    fn encoded_len(&self) -> usize { self.len() }
+}
+
+/// A writer that discards the data written to it and counts the bytes.
+struct ByteCounter(usize);
+
+impl io::Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> { Ok(()) }
+}
+
+/// Receives the key-value pairs of a PSBT map one at a time.
+///
+/// Lets a map describe its pairs once, for both collecting them and writing them to a stream.
+pub(crate) trait PairSink {
+    /// Receives a pair with key type `type_value`, key data `key` and value data `value`.
+    fn push_pair<K, V>(&mut self, type_value: u8, key: &K, value: &V)
+    where
+        K: Encode + ?Sized,
+        V: Encode + ?Sized;
+}
+
+/// Writes key-value pairs to a writer as they are received.
+///
+/// `PairSink` is infallible so the first I/O error is stored and returned by `finish`, any pairs
+/// received after an error are dropped.
+pub(crate) struct PairWriter<'a, W: ?Sized> {
+    inner: &'a mut W,
+    written: usize,
+    error: Option<io::Error>,
+}
+
+impl<'a, W: io::Write + ?Sized> PairWriter<'a, W> {
+    /// Creates a new pair writer that writes to `inner`.
+    pub(crate) fn new(inner: &'a mut W) -> Self { PairWriter { inner, written: 0, error: None } }
+
+    /// Returns the number of bytes written or the first error encountered.
+    pub(crate) fn finish(self) -> io::Result<usize> {
+        match self.error {
+            Some(e) => Err(e),
+            None => Ok(self.written),
+        }
+    }
+
+    /// Writes `<keylen> <keytype> <keydata> <valuelen> <valuedata>`.
+    fn write_pair<K, V>(&mut self, type_value: u8, key: &K, value: &V) -> io::Result<usize>
+    where
+        K: Encode + ?Sized,
+        V: Encode + ?Sized,
+    {
+        let key_len = key.encoded_len();
+        let mut len = VarInt::from(key_len + 1).consensus_encode(self.inner)?;
+        self.inner.write_all(&[type_value])?;
+        key.encode_to(self.inner)?;
+        len += 1 + key_len;
+
+        let value_len = value.encoded_len();
+        len += VarInt::from(value_len).consensus_encode(self.inner)?;
+        value.encode_to(self.inner)?;
+        Ok(len + value_len)
+    }
+}
+
+impl<'a, W: io::Write + ?Sized> PairSink for PairWriter<'a, W> {
+    fn push_pair<K, V>(&mut self, type_value: u8, key: &K, value: &V)
+    where
+        K: Encode + ?Sized,
+        V: Encode + ?Sized,
+    {
+        if self.error.is_some() {
+            return;
+        }
+        match self.write_pair(type_value, key, value) {
+            Ok(n) => self.written += n,
+            Err(e) => self.error = Some(e),
+        }
+    }
+}
+
+/// Encodes `data` into a new buffer.
+pub(crate) fn encode_to_vec<T: Encode + ?Sized>(data: &T) -> Vec<u8> {
+    let mut buf = Vec::new();
+    data.encode_to(&mut buf).expect("in-memory writers don't error");
+    buf
+}
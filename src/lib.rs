@@ -36,14 +36,18 @@ pub extern crate miniscript;
 
 mod consts;
 mod error;
+mod hash;
+mod io_ext;
 #[macro_use]
 mod macros;
 #[cfg(feature = "serde")]
 mod serde_utils;
 mod sighash_type;
 
+pub mod musig2;
 pub mod raw;
 pub mod serialize;
+pub mod silent_payments;
 pub mod v0;
 pub mod v2;
 mod version;
@@ -57,7 +61,7 @@ use core2::io;
 #[rustfmt::skip]                // Keep pubic re-exports separate
 #[doc(inline)]
 pub use crate::{
-    error::{InconsistentKeySourcesError, FeeError, FundingUtxoError},
+    error::{InconsistentKeySourcesError, FeeError, FundingUtxoError, PayFeeError},
     sighash_type::{PsbtSighashType, InvalidSighashTypeError, ParseSighashTypeError},
     version::{Version, UnsupportedVersionError},
 };
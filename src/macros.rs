@@ -20,9 +20,11 @@ macro_rules! v2_combine_map {
 }
 
 // Implements our Serialize/Deserialize traits using bitcoin consensus serialization.
+//
+// `$encoded_len` returns the length of the consensus encoding of a `&$thing`.
 macro_rules! v2_impl_psbt_de_serialize {
-    ($thing:ty) => {
-        v2_impl_psbt_serialize!($thing);
+    ($thing:ty, $encoded_len:expr) => {
+        v2_impl_psbt_serialize!($thing, $encoded_len);
         v2_impl_psbt_deserialize!($thing);
     };
 }
@@ -39,9 +41,16 @@ macro_rules! v2_impl_psbt_deserialize {
 }
 
 macro_rules! v2_impl_psbt_serialize {
-    ($thing:ty) => {
-        impl $crate::serialize::Serialize for $thing {
-            fn serialize(&self) -> $crate::prelude::Vec<u8> { bitcoin::consensus::serialize(self) }
+    ($thing:ty, $encoded_len:expr) => {
+        impl $crate::io_ext::Encode for $thing {
+            fn encode_to<W: $crate::io::Write + ?Sized>(
+                &self,
+                w: &mut W,
+            ) -> $crate::io::Result<()> {
+                bitcoin::consensus::Encodable::consensus_encode(self, w).map(|_| ())
+            }
+
+            fn encoded_len(&self) -> usize { ($encoded_len)(self) }
         }
     };
 }
@@ -79,26 +88,24 @@ macro_rules! v2_impl_psbt_insert_pair {
 
 #[rustfmt::skip]
 macro_rules! v2_impl_psbt_get_pair {
-    ($rv:ident.push($slf:ident.$unkeyed_name:ident, $unkeyed_typeval:ident)) => {
+    ($pairs:ident.push($slf:ident.$unkeyed_name:ident, $unkeyed_typeval:ident)) => {
         if let Some(ref $unkeyed_name) = $slf.$unkeyed_name {
-            $rv.push($crate::raw::Pair {
-                key: $crate::raw::Key {
-                    type_value: $unkeyed_typeval,
-                    key: vec![],
-                },
-                value: $crate::serialize::Serialize::serialize($unkeyed_name),
-            });
+            $crate::io_ext::PairSink::push_pair(
+                $pairs,
+                $unkeyed_typeval,
+                &[],
+                $unkeyed_name,
+            );
         }
     };
-    ($rv:ident.push_map($slf:ident.$keyed_name:ident, $keyed_typeval:ident)) => {
+    ($pairs:ident.push_map($slf:ident.$keyed_name:ident, $keyed_typeval:ident)) => {
         for (key, val) in &$slf.$keyed_name {
-            $rv.push($crate::raw::Pair {
-                key: $crate::raw::Key {
-                    type_value: $keyed_typeval,
-                    key: $crate::serialize::Serialize::serialize(key),
-                },
-                value: $crate::serialize::Serialize::serialize(val),
-            });
+            $crate::io_ext::PairSink::push_pair(
+                $pairs,
+                $keyed_typeval,
+                key,
+                val,
+            );
         }
     };
 }
@@ -123,8 +130,15 @@ macro_rules! v2_impl_psbt_hash_deserialize {
 
 macro_rules! v2_impl_psbt_hash_serialize {
     ($hash_type:ty) => {
-        impl $crate::serialize::Serialize for $hash_type {
-            fn serialize(&self) -> $crate::prelude::Vec<u8> { self.as_byte_array().to_vec() }
+        impl $crate::io_ext::Encode for $hash_type {
+            fn encode_to<W: $crate::io::Write + ?Sized>(
+                &self,
+                w: &mut W,
+            ) -> $crate::io::Result<()> {
+                w.write_all(self.as_byte_array())
+            }
+
+            fn encoded_len(&self) -> usize { self.as_byte_array().len() }
         }
     };
 }
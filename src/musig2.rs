@@ -0,0 +1,154 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! MuSig2 types used by the PSBT fields defined in [BIP-373].
+//!
+//! This crate does not implement the MuSig2 signing protocol (see [BIP-327]), it only carries the
+//! data between participants and aggregates complete partial signatures into a BIP-340 signature.
+//!
+//! [BIP-327]: <https://github.com/bitcoin/bips/blob/master/bip-0327.mediawiki>
+//! [BIP-373]: <https://github.com/bitcoin/bips/blob/master/bip-0373.mediawiki>
+
+use bitcoin::secp256k1::{self, schnorr, PublicKey, Scalar, Secp256k1, SecretKey, Verification};
+
+use crate::hash::tagged_hash;
+use crate::prelude::*;
+
+/// A MuSig2 public nonce, the two points a participant commits to before signing.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(crate = "actual_serde"))]
+pub struct PublicNonce {
+    /// The first nonce point.
+    pub r1: PublicKey,
+    /// The second nonce point.
+    pub r2: PublicKey,
+}
+
+impl PublicNonce {
+    /// Parses a public nonce from the two 33 byte compressed points.
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, secp256k1::Error> {
+        if bytes.len() != 66 {
+            return Err(secp256k1::Error::InvalidPublicKey);
+        }
+        let r1 = PublicKey::from_slice(&bytes[..33])?;
+        let r2 = PublicKey::from_slice(&bytes[33..])?;
+        Ok(PublicNonce { r1, r2 })
+    }
+
+    /// Serializes the public nonce as the two 33 byte compressed points.
+    pub fn serialize(&self) -> [u8; 66] {
+        let mut buf = [0_u8; 66];
+        buf[..33].copy_from_slice(&self.r1.serialize());
+        buf[33..].copy_from_slice(&self.r2.serialize());
+        buf
+    }
+}
+
+/// A MuSig2 partial signature.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(crate = "actual_serde"))]
+pub struct PartialSignature(
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::hex_bytes"))] [u8; 32],
+);
+
+impl PartialSignature {
+    /// Creates a partial signature from its 32 byte big-endian encoding.
+    pub fn from_byte_array(bytes: [u8; 32]) -> Self { PartialSignature(bytes) }
+
+    /// Returns the 32 byte big-endian encoding of this partial signature.
+    pub fn to_byte_array(self) -> [u8; 32] { self.0 }
+}
+
+/// Computes the MuSig2 aggregate key of `participants` as defined by the BIP-327 `KeyAgg`
+/// algorithm, the keys are used in the order given (i.e., not sorted).
+///
+/// Returns `None` if `participants` is empty or the keys sum to the point at infinity.
+pub fn aggregate_key<C: Verification>(
+    secp: &Secp256k1<C>,
+    participants: &[PublicKey],
+) -> Option<PublicKey> {
+    let serialized: Vec<[u8; 33]> = participants.iter().map(|pk| pk.serialize()).collect();
+    let list = tagged_hash("KeyAgg list", serialized.iter().map(|pk| &pk[..]));
+    let second = serialized.iter().find(|pk| **pk != serialized[0]);
+
+    let mut points = Vec::with_capacity(participants.len());
+    for (pk, ser) in participants.iter().zip(serialized.iter()) {
+        if Some(ser) == second {
+            points.push(*pk);
+        } else {
+            let coefficient = tagged_hash("KeyAgg coefficient", [&list[..], &ser[..]]);
+            let coefficient = Scalar::from_be_bytes(coefficient).ok()?;
+            points.push(pk.mul_tweak(secp, &coefficient).ok()?);
+        }
+    }
+    PublicKey::combine_keys(&points.iter().collect::<Vec<_>>()).ok()
+}
+
+/// Aggregates complete partial signatures into a BIP-340 signature for `msg`.
+///
+/// Implements the BIP-327 `NonceAgg` and `PartialSigAgg` algorithms for a session where the
+/// aggregate key has had the single x-only `tweak` applied to it (e.g., a Taproot tweak).
+///
+/// Returns `None` if any intermediate point is the point at infinity or a hash is not a valid
+/// scalar, both of which only happen with negligible probability for honest participants.
+pub(crate) fn aggregate_signature<C: Verification>(
+    secp: &Secp256k1<C>,
+    aggregate_key: PublicKey,
+    tweak: Option<Scalar>,
+    nonces: &[PublicNonce],
+    partial_sigs: &[Scalar],
+    msg: &[u8; 32],
+) -> Option<schnorr::Signature> {
+    // ApplyTweak, with `is_xonly` set.
+    let (key, tacc) = match tweak {
+        Some(tweak) => {
+            let key =
+                if has_even_y(&aggregate_key) { aggregate_key } else { aggregate_key.negate(secp) };
+            (key.add_exp_tweak(secp, &tweak).ok()?, Some(tweak))
+        }
+        None => (aggregate_key, None),
+    };
+    let key_bytes = key.x_only_public_key().0.serialize();
+
+    // NonceAgg.
+    let r1 = PublicKey::combine_keys(&nonces.iter().map(|n| &n.r1).collect::<Vec<_>>()).ok()?;
+    let r2 = PublicKey::combine_keys(&nonces.iter().map(|n| &n.r2).collect::<Vec<_>>()).ok()?;
+    let agg_nonce = PublicNonce { r1, r2 }.serialize();
+
+    let b = tagged_hash("MuSig/noncecoef", [&agg_nonce[..], &key_bytes[..], &msg[..]]);
+    let b = Scalar::from_be_bytes(b).ok()?;
+    let r = r1.combine(&r2.mul_tweak(secp, &b).ok()?).ok()?;
+    let r_bytes = r.x_only_public_key().0.serialize();
+
+    let e = tagged_hash("BIP0340/challenge", [&r_bytes[..], &key_bytes[..], &msg[..]]);
+    let e = Scalar::from_be_bytes(e).ok()?;
+
+    // PartialSigAgg, we use `None` to represent zero since a `SecretKey` can not be zero.
+    let mut s = partial_sigs.iter().fold(None, scalar_add);
+    if let Some(tacc) = tacc {
+        if let Ok(e) = SecretKey::from_slice(&e.to_be_bytes()) {
+            let mut et = e.mul_tweak(&tacc).ok()?;
+            if !has_even_y(&key) {
+                et = et.negate();
+            }
+            s = scalar_add(s, &Scalar::from(et));
+        }
+    }
+    let s_bytes = s.map(|s| s.secret_bytes()).unwrap_or([0; 32]);
+
+    let mut sig = [0_u8; 64];
+    sig[..32].copy_from_slice(&r_bytes);
+    sig[32..].copy_from_slice(&s_bytes);
+    schnorr::Signature::from_slice(&sig).ok()
+}
+
+/// Adds `b` to `a` modulo the curve order, `None` represents zero.
+fn scalar_add(a: Option<SecretKey>, b: &Scalar) -> Option<SecretKey> {
+    match a {
+        Some(a) => a.add_tweak(b).ok(),
+        None => SecretKey::from_slice(&b.to_be_bytes()).ok(),
+    }
+}
+
+fn has_even_y(pk: &PublicKey) -> bool { pk.serialize()[0] == 0x02 }
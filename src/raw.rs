@@ -18,8 +18,10 @@ use bitcoin::consensus::encode::{
 };
 use bitcoin::hex::DisplayHex;
 
+use crate::io_ext::{self, Encode, PairSink};
 use crate::prelude::*;
-use crate::serialize::{Deserialize, Serialize};
+use crate::serialize::Deserialize;
+use crate::v0::bitcoin::raw as v0;
 use crate::{io, serialize};
 
 /// A PSBT key-value pair in its raw byte form.
@@ -44,19 +46,32 @@ impl Pair {
     }
 }
 
+impl PairSink for Vec<Pair> {
+    fn push_pair<K, V>(&mut self, type_value: u8, key: &K, value: &V)
+    where
+        K: Encode + ?Sized,
+        V: Encode + ?Sized,
+    {
+        let key = Key { type_value, key: io_ext::encode_to_vec(key) };
+        self.push(Pair { key, value: io_ext::encode_to_vec(value) });
+    }
+}
+
 impl fmt::Display for Key {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "type: {:#x}, key: {:x}", self.type_value, self.key.as_hex())
     }
 }
 
-impl Serialize for Pair {
-    fn serialize(&self) -> Vec<u8> {
-        let mut buf = Vec::new();
-        buf.extend(self.key.serialize());
+impl Encode for Pair {
+    fn encode_to<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<()> {
+        self.key.encode_to(w)?;
         // <value> := <valuelen> <valuedata>
-        self.value.consensus_encode(&mut buf).unwrap();
-        buf
+        self.value.consensus_encode(w).map(|_| ())
+    }
+
+    fn encoded_len(&self) -> usize {
+        self.key.encoded_len() + VarInt::from(self.value.len()).size() + self.value.len()
     }
 }
 
@@ -114,21 +129,14 @@ impl Key {
     }
 }
 
-impl Serialize for Key {
-    fn serialize(&self) -> Vec<u8> {
-        let mut buf = Vec::new();
-        VarInt::from(self.key.len() + 1)
-            .consensus_encode(&mut buf)
-            .expect("in-memory writers don't error");
-
-        self.type_value.consensus_encode(&mut buf).expect("in-memory writers don't error");
-
-        for key in &self.key {
-            key.consensus_encode(&mut buf).expect("in-memory writers don't error");
-        }
-
-        buf
+impl Encode for Key {
+    fn encode_to<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<()> {
+        VarInt::from(self.key.len() + 1).consensus_encode(w)?;
+        w.emit_u8(self.type_value)?;
+        w.write_all(&self.key)
     }
+
+    fn encoded_len(&self) -> usize { VarInt::from(self.key.len() + 1).size() + 1 + self.key.len() }
 }
 
 /// Default implementation for proprietary key subtyping
@@ -162,6 +170,20 @@ where
     pub fn to_key(&self) -> Key { Key { type_value: 0xFC, key: serialize(self) } }
 }
 
+/// Encodes the key data of the [`Key`] corresponding to this proprietary key.
+impl<Subtype> Encode for ProprietaryKey<Subtype>
+where
+    Subtype: Copy + From<u8> + Into<u8>,
+{
+    fn encode_to<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<()> {
+        self.consensus_encode(w).map(|_| ())
+    }
+
+    fn encoded_len(&self) -> usize {
+        VarInt::from(self.prefix.len()).size() + self.prefix.len() + 1 + self.key.len()
+    }
+}
+
 impl<Subtype> TryFrom<Key> for ProprietaryKey<Subtype>
 where
     Subtype: Copy + From<u8> + Into<u8>,
@@ -208,6 +230,32 @@ where
     }
 }
 
+impl From<Key> for v0::Key {
+    fn from(k: Key) -> Self { v0::Key { type_value: k.type_value, key: k.key } }
+}
+
+impl<Subtype> From<ProprietaryKey<Subtype>> for v0::ProprietaryKey<Subtype>
+where
+    Subtype: Copy + From<u8> + Into<u8>,
+{
+    fn from(k: ProprietaryKey<Subtype>) -> Self {
+        v0::ProprietaryKey { prefix: k.prefix, subtype: k.subtype, key: k.key }
+    }
+}
+
+impl From<v0::Key> for Key {
+    fn from(k: v0::Key) -> Self { Key { type_value: k.type_value, key: k.key } }
+}
+
+impl<Subtype> From<v0::ProprietaryKey<Subtype>> for ProprietaryKey<Subtype>
+where
+    Subtype: Copy + From<u8> + Into<u8>,
+{
+    fn from(k: v0::ProprietaryKey<Subtype>) -> Self {
+        ProprietaryKey { prefix: k.prefix, subtype: k.subtype, key: k.key }
+    }
+}
+
 // core2 doesn't have read_to_end
 pub(crate) fn read_to_end<D: io::Read>(mut d: D) -> Result<Vec<u8>, io::Error> {
     let mut result = vec![];
@@ -17,21 +17,15 @@ use bitcoin::taproot::{
     ControlBlock, LeafVersion, TapLeafHash, TapNodeHash, TapTree, TaprootBuilder,
 };
 use bitcoin::{
-    absolute, ecdsa, taproot, transaction, Amount, ScriptBuf, Sequence, Transaction, TxOut, Txid,
-    VarInt, Witness,
+    absolute, ecdsa, taproot, transaction, Amount, ScriptBuf, Sequence, TapSighashType,
+    Transaction, TxOut, Txid, VarInt, Witness,
 };
 
 use crate::error::write_err;
+use crate::io_ext::Encode;
 use crate::prelude::*;
 use crate::sighash_type::PsbtSighashType;
-use crate::version;
-
-/// A trait for serializing a value as raw data for insertion into PSBT
-/// key-value maps.
-pub(crate) trait Serialize {
-    /// Serialize a value as raw data.
-    fn serialize(&self) -> Vec<u8>;
-}
+use crate::{io, musig2, silent_payments, version};
 
 /// A trait for deserializing a value from raw data in PSBT key-value maps.
 pub(crate) trait Deserialize: Sized {
@@ -42,13 +36,13 @@ pub(crate) trait Deserialize: Sized {
 // Strictly speaking these do not need the prefix because the v0 versions are
 // unused but we want to leave thoes in the code so the the files are close as
 // possible to the original from bitcoin/miniscript repos.
-v2_impl_psbt_de_serialize!(absolute::LockTime);
-v2_impl_psbt_de_serialize!(Amount);
-v2_impl_psbt_de_serialize!(Transaction);
-v2_impl_psbt_de_serialize!(transaction::Version);
-v2_impl_psbt_de_serialize!(TxOut);
-v2_impl_psbt_de_serialize!(Witness);
-v2_impl_psbt_de_serialize!(VarInt);
+v2_impl_psbt_de_serialize!(absolute::LockTime, |_| 4);
+v2_impl_psbt_de_serialize!(Amount, |_| 8);
+v2_impl_psbt_de_serialize!(Transaction, Transaction::total_size);
+v2_impl_psbt_de_serialize!(transaction::Version, |_| 4);
+v2_impl_psbt_de_serialize!(TxOut, TxOut::size);
+v2_impl_psbt_de_serialize!(Witness, Witness::size);
+v2_impl_psbt_de_serialize!(VarInt, VarInt::size);
 v2_impl_psbt_hash_de_serialize!(ripemd160::Hash);
 v2_impl_psbt_hash_de_serialize!(sha256::Hash);
 v2_impl_psbt_hash_de_serialize!(TapLeafHash);
@@ -58,21 +52,31 @@ v2_impl_psbt_hash_de_serialize!(hash160::Hash);
 v2_impl_psbt_hash_de_serialize!(sha256d::Hash);
 
 // taproot
-v2_impl_psbt_de_serialize!(Vec<TapLeafHash>);
+v2_impl_psbt_de_serialize!(Vec<TapLeafHash>, |hashes: &Vec<TapLeafHash>| {
+    VarInt::from(hashes.len()).size() + hashes.len() * TapLeafHash::LEN
+});
+
+impl Encode for ScriptBuf {
+    fn encode_to<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<()> {
+        w.write_all(self.as_bytes())
+    }
 
-impl Serialize for ScriptBuf {
-    fn serialize(&self) -> Vec<u8> { self.to_bytes() }
+    fn encoded_len(&self) -> usize { self.len() }
 }
 
 impl Deserialize for ScriptBuf {
     fn deserialize(bytes: &[u8]) -> Result<Self, Error> { Ok(Self::from(bytes.to_vec())) }
 }
 
-impl Serialize for PublicKey {
-    fn serialize(&self) -> Vec<u8> {
-        let mut buf = Vec::new();
-        self.write_into(&mut buf).expect("vecs don't error");
-        buf
+impl Encode for PublicKey {
+    fn encode_to<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<()> { self.write_into(w) }
+
+    fn encoded_len(&self) -> usize {
+        if self.compressed {
+            33
+        } else {
+            65
+        }
     }
 }
 
@@ -82,8 +86,12 @@ impl Deserialize for PublicKey {
     }
 }
 
-impl Serialize for secp256k1::PublicKey {
-    fn serialize(&self) -> Vec<u8> { self.serialize().to_vec() }
+impl Encode for secp256k1::PublicKey {
+    fn encode_to<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<()> {
+        w.write_all(&self.serialize())
+    }
+
+    fn encoded_len(&self) -> usize { secp256k1::constants::PUBLIC_KEY_SIZE }
 }
 
 impl Deserialize for secp256k1::PublicKey {
@@ -92,8 +100,12 @@ impl Deserialize for secp256k1::PublicKey {
     }
 }
 
-impl Serialize for ecdsa::Signature {
-    fn serialize(&self) -> Vec<u8> { self.to_vec() }
+impl Encode for ecdsa::Signature {
+    fn encode_to<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<()> {
+        w.write_all(&self.serialize())
+    }
+
+    fn encoded_len(&self) -> usize { self.serialize().len() }
 }
 
 impl Deserialize for ecdsa::Signature {
@@ -121,18 +133,18 @@ impl Deserialize for ecdsa::Signature {
     }
 }
 
-impl Serialize for KeySource {
-    fn serialize(&self) -> Vec<u8> {
-        let mut rv: Vec<u8> = Vec::with_capacity(key_source_len(self));
-
-        rv.append(&mut self.0.to_bytes().to_vec());
+impl Encode for KeySource {
+    fn encode_to<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<()> {
+        w.write_all(self.0.as_bytes())?;
 
         for cnum in self.1.into_iter() {
-            rv.append(&mut consensus::serialize(&u32::from(*cnum)))
+            u32::from(*cnum).consensus_encode(w)?;
         }
 
-        rv
+        Ok(())
     }
+
+    fn encoded_len(&self) -> usize { key_source_len(self) }
 }
 
 impl Deserialize for KeySource {
@@ -156,8 +168,12 @@ impl Deserialize for KeySource {
     }
 }
 
-impl Serialize for u32 {
-    fn serialize(&self) -> Vec<u8> { consensus::serialize(&self) }
+impl Encode for u32 {
+    fn encode_to<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<()> {
+        self.consensus_encode(w).map(|_| ())
+    }
+
+    fn encoded_len(&self) -> usize { 4 }
 }
 
 impl Deserialize for u32 {
@@ -167,8 +183,12 @@ impl Deserialize for u32 {
     }
 }
 
-impl Serialize for Sequence {
-    fn serialize(&self) -> Vec<u8> { consensus::serialize(&self) }
+impl Encode for Sequence {
+    fn encode_to<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<()> {
+        self.consensus_encode(w).map(|_| ())
+    }
+
+    fn encoded_len(&self) -> usize { 4 }
 }
 
 impl Deserialize for Sequence {
@@ -178,8 +198,12 @@ impl Deserialize for Sequence {
     }
 }
 
-impl Serialize for absolute::Height {
-    fn serialize(&self) -> Vec<u8> { consensus::serialize(&self.to_consensus_u32()) }
+impl Encode for absolute::Height {
+    fn encode_to<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<()> {
+        self.to_consensus_u32().encode_to(w)
+    }
+
+    fn encoded_len(&self) -> usize { 4 }
 }
 
 impl Deserialize for absolute::Height {
@@ -190,8 +214,12 @@ impl Deserialize for absolute::Height {
     }
 }
 
-impl Serialize for absolute::Time {
-    fn serialize(&self) -> Vec<u8> { consensus::serialize(&self.to_consensus_u32()) }
+impl Encode for absolute::Time {
+    fn encode_to<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<()> {
+        self.to_consensus_u32().encode_to(w)
+    }
+
+    fn encoded_len(&self) -> usize { 4 }
 }
 
 impl Deserialize for absolute::Time {
@@ -203,16 +231,17 @@ impl Deserialize for absolute::Time {
 }
 
 // partial sigs
-impl Serialize for Vec<u8> {
-    fn serialize(&self) -> Vec<u8> { self.clone() }
-}
 
 impl Deserialize for Vec<u8> {
     fn deserialize(bytes: &[u8]) -> Result<Self, Error> { Ok(bytes.to_vec()) }
 }
 
-impl Serialize for PsbtSighashType {
-    fn serialize(&self) -> Vec<u8> { consensus::serialize(&self.to_u32()) }
+impl Encode for PsbtSighashType {
+    fn encode_to<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<()> {
+        self.to_u32().encode_to(w)
+    }
+
+    fn encoded_len(&self) -> usize { 4 }
 }
 
 impl Deserialize for PsbtSighashType {
@@ -223,8 +252,12 @@ impl Deserialize for PsbtSighashType {
 }
 
 // Taproot related ser/deser
-impl Serialize for XOnlyPublicKey {
-    fn serialize(&self) -> Vec<u8> { XOnlyPublicKey::serialize(self).to_vec() }
+impl Encode for XOnlyPublicKey {
+    fn encode_to<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<()> {
+        w.write_all(&XOnlyPublicKey::serialize(self))
+    }
+
+    fn encoded_len(&self) -> usize { secp256k1::constants::SCHNORR_PUBLIC_KEY_SIZE }
 }
 
 impl Deserialize for XOnlyPublicKey {
@@ -233,8 +266,22 @@ impl Deserialize for XOnlyPublicKey {
     }
 }
 
-impl Serialize for taproot::Signature {
-    fn serialize(&self) -> Vec<u8> { self.to_vec() }
+impl Encode for taproot::Signature {
+    fn encode_to<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<()> {
+        w.write_all(self.sig.as_ref())?;
+        if self.hash_ty != TapSighashType::Default {
+            w.write_all(&[self.hash_ty as u8])?;
+        }
+        Ok(())
+    }
+
+    fn encoded_len(&self) -> usize {
+        if self.hash_ty == TapSighashType::Default {
+            64
+        } else {
+            65
+        }
+    }
 }
 
 impl Deserialize for taproot::Signature {
@@ -250,14 +297,13 @@ impl Deserialize for taproot::Signature {
     }
 }
 
-impl Serialize for (XOnlyPublicKey, TapLeafHash) {
-    fn serialize(&self) -> Vec<u8> {
-        let ser_pk = self.0.serialize();
-        let mut buf = Vec::with_capacity(ser_pk.len() + self.1.as_byte_array().len());
-        buf.extend(&ser_pk);
-        buf.extend(self.1.as_byte_array());
-        buf
+impl Encode for (XOnlyPublicKey, TapLeafHash) {
+    fn encode_to<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<()> {
+        self.0.encode_to(w)?;
+        self.1.encode_to(w)
     }
+
+    fn encoded_len(&self) -> usize { self.0.encoded_len() + self.1.encoded_len() }
 }
 
 impl Deserialize for (XOnlyPublicKey, TapLeafHash) {
@@ -271,8 +317,12 @@ impl Deserialize for (XOnlyPublicKey, TapLeafHash) {
     }
 }
 
-impl Serialize for ControlBlock {
-    fn serialize(&self) -> Vec<u8> { ControlBlock::serialize(self) }
+impl Encode for ControlBlock {
+    fn encode_to<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<()> {
+        self.encode(w).map(|_| ())
+    }
+
+    fn encoded_len(&self) -> usize { self.size() }
 }
 
 impl Deserialize for ControlBlock {
@@ -282,13 +332,13 @@ impl Deserialize for ControlBlock {
 }
 
 // Versioned ScriptBuf
-impl Serialize for (ScriptBuf, LeafVersion) {
-    fn serialize(&self) -> Vec<u8> {
-        let mut buf = Vec::with_capacity(self.0.len() + 1);
-        buf.extend(self.0.as_bytes());
-        buf.push(self.1.to_consensus());
-        buf
+impl Encode for (ScriptBuf, LeafVersion) {
+    fn encode_to<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<()> {
+        w.write_all(self.0.as_bytes())?;
+        w.write_all(&[self.1.to_consensus()])
     }
+
+    fn encoded_len(&self) -> usize { self.0.len() + 1 }
 }
 
 impl Deserialize for (ScriptBuf, LeafVersion) {
@@ -304,13 +354,14 @@ impl Deserialize for (ScriptBuf, LeafVersion) {
     }
 }
 
-impl Serialize for (Vec<TapLeafHash>, KeySource) {
-    fn serialize(&self) -> Vec<u8> {
-        let mut buf = Vec::with_capacity(32 * self.0.len() + key_source_len(&self.1));
-        self.0.consensus_encode(&mut buf).expect("Vecs don't error allocation");
-        // TODO: Add support for writing into a writer for key-source
-        buf.extend(self.1.serialize());
-        buf
+impl Encode for (Vec<TapLeafHash>, KeySource) {
+    fn encode_to<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<()> {
+        self.0.consensus_encode(w)?;
+        self.1.encode_to(w)
+    }
+
+    fn encoded_len(&self) -> usize {
+        VarInt::from(self.0.len()).size() + 32 * self.0.len() + key_source_len(&self.1)
     }
 }
 
@@ -322,27 +373,28 @@ impl Deserialize for (Vec<TapLeafHash>, KeySource) {
     }
 }
 
-impl Serialize for TapTree {
-    fn serialize(&self) -> Vec<u8> {
-        let capacity = self
-            .script_leaves()
-            .map(|l| {
-                l.script().len() + VarInt::from(l.script().len()).size() // script version
-            + 1 // merkle branch
-            + 1 // leaf version
-            })
-            .sum::<usize>();
-        let mut buf = Vec::with_capacity(capacity);
+impl Encode for TapTree {
+    fn encode_to<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<()> {
         for leaf_info in self.script_leaves() {
             // # Cast Safety:
             //
             // TaprootMerkleBranch can only have len atmost 128(TAPROOT_CONTROL_MAX_NODE_COUNT).
             // safe to cast from usize to u8
-            buf.push(leaf_info.merkle_branch().len() as u8);
-            buf.push(leaf_info.version().to_consensus());
-            leaf_info.script().consensus_encode(&mut buf).expect("Vecs dont err");
+            w.write_all(&[leaf_info.merkle_branch().len() as u8])?;
+            w.write_all(&[leaf_info.version().to_consensus()])?;
+            leaf_info.script().consensus_encode(w)?;
         }
-        buf
+        Ok(())
+    }
+
+    fn encoded_len(&self) -> usize {
+        self.script_leaves()
+            .map(|l| {
+                l.script().len() + VarInt::from(l.script().len()).size() // script version
+            + 1 // merkle branch
+            + 1 // leaf version
+            })
+            .sum::<usize>()
     }
 }
 
@@ -367,6 +419,116 @@ impl Deserialize for TapTree {
     }
 }
 
+// MuSig2 related ser/deser
+impl Encode for Vec<secp256k1::PublicKey> {
+    fn encode_to<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<()> {
+        for pk in self {
+            pk.encode_to(w)?;
+        }
+        Ok(())
+    }
+
+    fn encoded_len(&self) -> usize { secp256k1::constants::PUBLIC_KEY_SIZE * self.len() }
+}
+
+impl Deserialize for Vec<secp256k1::PublicKey> {
+    fn deserialize(bytes: &[u8]) -> Result<Self, Error> {
+        // A MuSig2 aggregate key has at least one participant.
+        if bytes.is_empty() || bytes.len() % 33 != 0 {
+            return Err(Error::NotEnoughData);
+        }
+        bytes.chunks_exact(33).map(Deserialize::deserialize).collect()
+    }
+}
+
+impl Encode for (secp256k1::PublicKey, secp256k1::PublicKey, Option<TapLeafHash>) {
+    fn encode_to<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<()> {
+        self.0.encode_to(w)?;
+        self.1.encode_to(w)?;
+        if let Some(ref leaf_hash) = self.2 {
+            leaf_hash.encode_to(w)?;
+        }
+        Ok(())
+    }
+
+    fn encoded_len(&self) -> usize {
+        self.0.encoded_len() + self.1.encoded_len() + self.2.map_or(0, |h| h.encoded_len())
+    }
+}
+
+impl Deserialize for (secp256k1::PublicKey, secp256k1::PublicKey, Option<TapLeafHash>) {
+    fn deserialize(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() < 66 {
+            return Err(Error::NotEnoughData);
+        }
+        let participant: secp256k1::PublicKey = Deserialize::deserialize(&bytes[..33])?;
+        let aggregate: secp256k1::PublicKey = Deserialize::deserialize(&bytes[33..66])?;
+        let leaf_hash =
+            if bytes.len() > 66 { Some(TapLeafHash::deserialize(&bytes[66..])?) } else { None };
+        Ok((participant, aggregate, leaf_hash))
+    }
+}
+
+impl Encode for musig2::PublicNonce {
+    fn encode_to<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<()> {
+        w.write_all(&musig2::PublicNonce::serialize(self))
+    }
+
+    fn encoded_len(&self) -> usize { 66 }
+}
+
+impl Deserialize for musig2::PublicNonce {
+    fn deserialize(bytes: &[u8]) -> Result<Self, Error> {
+        musig2::PublicNonce::from_slice(bytes).map_err(Error::InvalidSecp256k1PublicKey)
+    }
+}
+
+impl Encode for musig2::PartialSignature {
+    fn encode_to<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<()> {
+        w.write_all(&self.to_byte_array())
+    }
+
+    fn encoded_len(&self) -> usize { 32 }
+}
+
+impl Deserialize for musig2::PartialSignature {
+    fn deserialize(bytes: &[u8]) -> Result<Self, Error> {
+        let bytes = <[u8; 32]>::try_from(bytes).map_err(|_| Error::NotEnoughData)?;
+        Ok(musig2::PartialSignature::from_byte_array(bytes))
+    }
+}
+
+// Silent payments related ser/deser
+impl Encode for silent_payments::SilentPaymentInfo {
+    fn encode_to<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<()> {
+        w.write_all(&silent_payments::SilentPaymentInfo::serialize(self))
+    }
+
+    fn encoded_len(&self) -> usize { 66 }
+}
+
+impl Deserialize for silent_payments::SilentPaymentInfo {
+    fn deserialize(bytes: &[u8]) -> Result<Self, Error> {
+        silent_payments::SilentPaymentInfo::from_slice(bytes)
+            .map_err(Error::InvalidSecp256k1PublicKey)
+    }
+}
+
+impl Encode for silent_payments::DleqProof {
+    fn encode_to<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<()> {
+        w.write_all(&self.to_byte_array())
+    }
+
+    fn encoded_len(&self) -> usize { 64 }
+}
+
+impl Deserialize for silent_payments::DleqProof {
+    fn deserialize(bytes: &[u8]) -> Result<Self, Error> {
+        let bytes = <[u8; 64]>::try_from(bytes).map_err(|_| Error::NotEnoughData)?;
+        Ok(silent_payments::DleqProof::from_byte_array(bytes))
+    }
+}
+
 // Helper function to compute key source len
 fn key_source_len(key_source: &KeySource) -> usize { 4 + 4 * (key_source.1).as_ref().len() }
 
@@ -537,10 +699,52 @@ mod tests {
             )
             .unwrap();
         let tree = TapTree::try_from(builder).unwrap();
-        let tree_prime = TapTree::deserialize(&tree.serialize()).unwrap();
+        let tree_prime = TapTree::deserialize(&crate::io_ext::encode_to_vec(&tree)).unwrap();
         assert_eq!(tree, tree_prime);
     }
 
+    fn assert_encoded_len<T: Encode + ?Sized>(data: &T) {
+        assert_eq!(data.encoded_len(), crate::io_ext::encode_to_vec(data).len());
+    }
+
+    #[test]
+    fn encoded_len_consensus_types() {
+        let witness = Witness::from_slice(&[vec![0xab; 72], vec![0x02; 33]]);
+        let tx_out = TxOut {
+            value: Amount::from_sat(1000),
+            script_pubkey: ScriptBuf::from_hex("0014abcdef").unwrap(),
+        };
+        let tx = Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![bitcoin::TxIn { witness: witness.clone(), ..Default::default() }],
+            output: vec![tx_out.clone()],
+        };
+
+        assert_encoded_len(&absolute::LockTime::ZERO);
+        assert_encoded_len(&Amount::from_sat(1000));
+        assert_encoded_len(&tx);
+        assert_encoded_len(&transaction::Version::TWO);
+        assert_encoded_len(&tx_out);
+        assert_encoded_len(&witness);
+        assert_encoded_len(&VarInt(0x1_0000));
+        assert_encoded_len(&vec![TapLeafHash::all_zeros(); 3]);
+    }
+
+    #[test]
+    fn deserialize_musig2_participants() {
+        let secp = secp256k1::Secp256k1::new();
+        let sk = secp256k1::SecretKey::from_slice(&[0x01; 32]).unwrap();
+        let pk = secp256k1::PublicKey::from_secret_key(&secp, &sk);
+
+        let bytes = [pk.serialize(), pk.serialize()].concat();
+        let keys = Vec::<secp256k1::PublicKey>::deserialize(&bytes).unwrap();
+        assert_eq!(keys, vec![pk, pk]);
+
+        assert!(Vec::<secp256k1::PublicKey>::deserialize(&[]).is_err());
+        assert!(Vec::<secp256k1::PublicKey>::deserialize(&bytes[1..]).is_err());
+    }
+
     #[test]
     fn can_deserialize_non_standard_psbt_sighash_type() {
         let non_standard_sighash = [222u8, 0u8, 0u8, 0u8]; // 32 byte value.
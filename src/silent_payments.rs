@@ -0,0 +1,210 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Silent payment types used by the PSBT fields defined in [BIP-375].
+//!
+//! Output scripts are derived from the ECDH shares as defined in [BIP-352]. The DLEQ proofs of the
+//! shares (see [BIP-374]) are verified before deriving the scripts, but are not created by this
+//! crate.
+//!
+//! [BIP-352]: <https://github.com/bitcoin/bips/blob/master/bip-0352.mediawiki>
+//! [BIP-374]: <https://github.com/bitcoin/bips/blob/master/bip-0374.mediawiki>
+//! [BIP-375]: <https://github.com/bitcoin/bips/blob/master/bip-0375.mediawiki>
+
+use bitcoin::hashes::Hash as _;
+use bitcoin::secp256k1::constants::GENERATOR_X;
+use bitcoin::secp256k1::{self, PublicKey, Scalar, Secp256k1, Verification, XOnlyPublicKey};
+use bitcoin::{consensus, OutPoint};
+
+use crate::hash::tagged_hash;
+use crate::v2::{Input, SilentPaymentError};
+
+/// The x-only NUMS point `H` from BIP-341, Taproot inputs using it as internal key are not
+/// eligible for silent payments.
+pub(crate) const NUMS_H: [u8; 32] = [
+    0x50, 0x92, 0x9b, 0x74, 0xc1, 0xa0, 0x49, 0x54, 0xb7, 0x8b, 0x4b, 0x60, 0x35, 0xe9, 0x7a, 0x5e,
+    0x07, 0x8a, 0x5a, 0x0f, 0x28, 0xec, 0x96, 0xd5, 0x47, 0xbf, 0xee, 0x9a, 0xce, 0x80, 0x3a, 0xc0,
+];
+
+/// The silent payment address an output pays to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(crate = "actual_serde"))]
+pub struct SilentPaymentInfo {
+    /// The scan key of the recipient.
+    pub scan_key: PublicKey,
+    /// The spend key of the recipient, with the label already applied if one is used.
+    pub spend_key: PublicKey,
+}
+
+impl SilentPaymentInfo {
+    /// Parses the silent payment info from the scan key and spend key as 33 byte compressed points.
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, secp256k1::Error> {
+        if bytes.len() != 66 {
+            return Err(secp256k1::Error::InvalidPublicKey);
+        }
+        let scan_key = PublicKey::from_slice(&bytes[..33])?;
+        let spend_key = PublicKey::from_slice(&bytes[33..])?;
+        Ok(SilentPaymentInfo { scan_key, spend_key })
+    }
+
+    /// Serializes the silent payment info as the scan key and spend key compressed points.
+    pub fn serialize(&self) -> [u8; 66] {
+        let mut buf = [0_u8; 66];
+        buf[..33].copy_from_slice(&self.scan_key.serialize());
+        buf[33..].copy_from_slice(&self.spend_key.serialize());
+        buf
+    }
+}
+
+/// A BIP-374 DLEQ proof that an ECDH share was computed correctly.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(crate = "actual_serde"))]
+pub struct DleqProof {
+    /// The challenge.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::hex_bytes"))]
+    pub e: [u8; 32],
+    /// The response.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::hex_bytes"))]
+    pub s: [u8; 32],
+}
+
+impl DleqProof {
+    /// Creates a proof from its 64 byte encoding.
+    pub fn from_byte_array(bytes: [u8; 64]) -> Self {
+        let mut e = [0_u8; 32];
+        let mut s = [0_u8; 32];
+        e.copy_from_slice(&bytes[..32]);
+        s.copy_from_slice(&bytes[32..]);
+        DleqProof { e, s }
+    }
+
+    /// Returns the 64 byte encoding of this proof.
+    pub fn to_byte_array(self) -> [u8; 64] {
+        let mut buf = [0_u8; 64];
+        buf[..32].copy_from_slice(&self.e);
+        buf[32..].copy_from_slice(&self.s);
+        buf
+    }
+
+    /// Verifies that `c` is `b` multiplied by the secret key of `a`, see [BIP-374].
+    ///
+    /// For an ECDH share `a` is the input public key, `b` the scan key and `c` the share. No
+    /// message is committed to.
+    ///
+    /// [BIP-374]: <https://github.com/bitcoin/bips/blob/master/bip-0374.mediawiki>
+    pub fn verify<C: Verification>(
+        &self,
+        secp: &Secp256k1<C>,
+        a: &PublicKey,
+        b: &PublicKey,
+        c: &PublicKey,
+    ) -> bool {
+        self.challenge(secp, a, b, c) == Some(self.e)
+    }
+
+    /// Computes the challenge from the nonce points `R1 = s⋅G - e⋅A` and `R2 = s⋅B - e⋅C`.
+    ///
+    /// Returns `None` if `e` or `s` is not a valid non-zero scalar, or a nonce point is the point
+    /// at infinity.
+    fn challenge<C: Verification>(
+        &self,
+        secp: &Secp256k1<C>,
+        a: &PublicKey,
+        b: &PublicKey,
+        c: &PublicKey,
+    ) -> Option<[u8; 32]> {
+        let e = Scalar::from_be_bytes(self.e).ok()?;
+        let s = Scalar::from_be_bytes(self.s).ok()?;
+
+        // `mul_tweak` fails for a zero scalar and `combine` for the point at infinity.
+        let r1 = generator()
+            .mul_tweak(secp, &s)
+            .ok()?
+            .combine(&a.mul_tweak(secp, &e).ok()?.negate(secp))
+            .ok()?;
+        let r2 =
+            b.mul_tweak(secp, &s).ok()?.combine(&c.mul_tweak(secp, &e).ok()?.negate(secp)).ok()?;
+
+        let points = [*a, *b, *c, generator(), r1, r2].map(|point| point.serialize());
+        Some(tagged_hash("BIP0374/challenge", points.iter().map(|bytes| &bytes[..])))
+    }
+}
+
+/// Returns the secp256k1 generator point `G`.
+fn generator() -> PublicKey {
+    let mut bytes = [0x02; 33];
+    bytes[1..].copy_from_slice(&GENERATOR_X);
+    PublicKey::from_slice(&bytes).expect("generator is a valid point")
+}
+
+/// Returns the public key an input contributes to the silent payment shared secret.
+///
+/// Returns `None` if the input is not eligible i.e., it is not a Taproot, P2WPKH, P2SH-P2WPKH or
+/// P2PKH input, it is a Taproot input with the NUMS point as internal key, or it spends to an
+/// uncompressed key.
+pub(crate) fn input_key(
+    input_index: usize,
+    input: &Input,
+) -> Result<Option<PublicKey>, SilentPaymentError> {
+    use SilentPaymentError::*;
+
+    let utxo = input.funding_utxo().map_err(|error| FundingUtxo { input_index, error })?;
+    let spk = &utxo.script_pubkey;
+
+    if spk.is_p2tr() {
+        if input.tap_internal_key.map(|key| key.serialize()) == Some(NUMS_H) {
+            return Ok(None);
+        }
+        let key = XOnlyPublicKey::from_slice(&spk.as_bytes()[2..34])
+            .map_err(|_| MissingInputKey { input_index })?;
+        return Ok(Some(key.public_key(secp256k1::Parity::Even)));
+    }
+
+    let pubkey_hash = if spk.is_p2wpkh() {
+        &spk.as_bytes()[2..22]
+    } else if spk.is_p2pkh() {
+        &spk.as_bytes()[3..23]
+    } else {
+        match input.redeem_script {
+            Some(ref redeem_script) if spk.is_p2sh() && redeem_script.is_p2wpkh() =>
+                &redeem_script.as_bytes()[2..22],
+            _ => return Ok(None),
+        }
+    };
+
+    let key = input
+        .bip32_derivations
+        .keys()
+        .map(|pk| bitcoin::PublicKey::new(*pk))
+        .chain(input.partial_sigs.keys().copied())
+        .find(|pk| pk.pubkey_hash().as_byte_array()[..] == *pubkey_hash)
+        .ok_or(MissingInputKey { input_index })?;
+    Ok(if key.compressed { Some(key.inner) } else { None })
+}
+
+/// Computes the BIP-352 `input_hash` from the smallest outpoint spent by the transaction and the
+/// sum of the eligible input keys.
+///
+/// Returns `None` if the hash is not a valid scalar.
+pub(crate) fn input_hash(smallest_out_point: &OutPoint, input_keys: &PublicKey) -> Option<Scalar> {
+    let out_point = consensus::serialize(smallest_out_point);
+    let hash = tagged_hash("BIP0352/Inputs", [&out_point[..], &input_keys.serialize()[..]]);
+    Scalar::from_be_bytes(hash).ok()
+}
+
+/// Derives the output key of the `k`-th output paying to `spend_key` using `shared_secret`.
+///
+/// Returns `None` if the tweak is not a valid scalar or the output key is the point at infinity.
+pub(crate) fn output_key<C: Verification>(
+    secp: &Secp256k1<C>,
+    shared_secret: &PublicKey,
+    k: u32,
+    spend_key: &PublicKey,
+) -> Option<XOnlyPublicKey> {
+    let hash =
+        tagged_hash("BIP0352/SharedSecret", [&shared_secret.serialize()[..], &k.to_be_bytes()[..]]);
+    let tweak = Scalar::from_be_bytes(hash).ok()?;
+    let key = spend_key.add_exp_tweak(secp, &tweak).ok()?;
+    Some(key.x_only_public_key().0)
+}
@@ -116,26 +116,24 @@ macro_rules! impl_psbt_insert_pair {
 
 #[rustfmt::skip]
 macro_rules! impl_psbt_get_pair {
-    ($rv:ident.push($slf:ident.$unkeyed_name:ident, $unkeyed_typeval:ident)) => {
+    ($pairs:ident.push($slf:ident.$unkeyed_name:ident, $unkeyed_typeval:ident)) => {
         if let Some(ref $unkeyed_name) = $slf.$unkeyed_name {
-            $rv.push($crate::v0::bitcoin::raw::Pair {
-                key: $crate::v0::bitcoin::raw::Key {
-                    type_value: $unkeyed_typeval,
-                    key: vec![],
-                },
-                value: $crate::v0::bitcoin::serialize::Serialize::serialize($unkeyed_name),
-            });
+            $crate::io_ext::PairSink::push_pair(
+                $pairs,
+                $unkeyed_typeval,
+                &[],
+                &$crate::v0::bitcoin::serialize::Serialize::serialize($unkeyed_name),
+            );
         }
     };
-    ($rv:ident.push_map($slf:ident.$keyed_name:ident, $keyed_typeval:ident)) => {
+    ($pairs:ident.push_map($slf:ident.$keyed_name:ident, $keyed_typeval:ident)) => {
         for (key, val) in &$slf.$keyed_name {
-            $rv.push($crate::v0::bitcoin::raw::Pair {
-                key: $crate::v0::bitcoin::raw::Key {
-                    type_value: $keyed_typeval,
-                    key: $crate::v0::bitcoin::serialize::Serialize::serialize(key),
-                },
-                value: $crate::v0::bitcoin::serialize::Serialize::serialize(val),
-            });
+            $crate::io_ext::PairSink::push_pair(
+                $pairs,
+                $keyed_typeval,
+                &$crate::v0::bitcoin::serialize::Serialize::serialize(key),
+                &$crate::v0::bitcoin::serialize::Serialize::serialize(val),
+            );
         }
     };
 }
@@ -5,9 +5,10 @@ use core::convert::TryFrom;
 use bitcoin::bip32::{ChildNumber, DerivationPath, Fingerprint, Xpub};
 use bitcoin::blockdata::transaction::Transaction;
 use bitcoin::consensus::encode::MAX_VEC_SIZE;
-use bitcoin::consensus::{encode, Decodable};
+use bitcoin::consensus::{Decodable, Encodable};
 
 use crate::io::{self, Cursor, Read};
+use crate::io_ext::{Encode, PairSink};
 use crate::prelude::*;
 use crate::v0::bitcoin::map::Map;
 use crate::v0::bitcoin::{raw, Error, Psbt};
@@ -33,52 +34,41 @@ const PSBT_GLOBAL_VERSION: u8 = 0xFB;
 const PSBT_GLOBAL_PROPRIETARY: u8 = 0xFC;
 
 impl Map for Psbt {
-    fn get_pairs(&self) -> Vec<raw::Pair> {
-        let mut rv: Vec<raw::Pair> = Default::default();
-
-        rv.push(raw::Pair {
-            key: raw::Key { type_value: PSBT_GLOBAL_UNSIGNED_TX, key: vec![] },
-            value: {
-                // Manually serialized to ensure 0-input txs are serialized
-                // without witnesses.
-                let mut ret = Vec::new();
-                ret.extend(encode::serialize(&self.unsigned_tx.version));
-                ret.extend(encode::serialize(&self.unsigned_tx.input));
-                ret.extend(encode::serialize(&self.unsigned_tx.output));
-                ret.extend(encode::serialize(&self.unsigned_tx.lock_time));
-                ret
-            },
-        });
-
-        for (xpub, (fingerprint, derivation)) in &self.xpub {
-            rv.push(raw::Pair {
-                key: raw::Key { type_value: PSBT_GLOBAL_XPUB, key: xpub.encode().to_vec() },
-                value: {
-                    let mut ret = Vec::with_capacity(4 + derivation.len() * 4);
-                    ret.extend(fingerprint.as_bytes());
-                    derivation.into_iter().for_each(|n| ret.extend(&u32::from(*n).to_le_bytes()));
-                    ret
-                },
-            });
+    fn push_pairs<S: PairSink>(&self, pairs: &mut S) {
+        pairs.push_pair(PSBT_GLOBAL_UNSIGNED_TX, &[], &UnsignedTx(&self.unsigned_tx));
+
+        for (xpub, key_source) in &self.xpub {
+            pairs.push_pair(PSBT_GLOBAL_XPUB, &xpub.encode(), key_source);
         }
 
         // Serializing version only for non-default value; otherwise test vectors fail
         if self.version > 0 {
-            rv.push(raw::Pair {
-                key: raw::Key { type_value: PSBT_GLOBAL_VERSION, key: vec![] },
-                value: self.version.to_le_bytes().to_vec(),
-            });
+            pairs.push_pair(PSBT_GLOBAL_VERSION, &[], &self.version.to_le_bytes());
         }
 
         for (key, value) in self.proprietary.iter() {
-            rv.push(raw::Pair { key: key.to_key(), value: value.clone() });
+            let key = key.to_key();
+            pairs.push_pair(key.type_value, &key.key, value);
         }
 
         for (key, value) in self.unknown.iter() {
-            rv.push(raw::Pair { key: key.clone(), value: value.clone() });
+            pairs.push_pair(key.type_value, &key.key, value);
         }
+    }
+}
 
-        rv
+/// Encodes an unsigned transaction without the segwit marker and flag.
+///
+/// Manually serialized to ensure 0-input txs are serialized without witnesses.
+struct UnsignedTx<'a>(&'a Transaction);
+
+impl<'a> Encode for UnsignedTx<'a> {
+    fn encode_to<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<()> {
+        self.0.version.consensus_encode(w)?;
+        self.0.input.consensus_encode(w)?;
+        self.0.output.consensus_encode(w)?;
+        self.0.lock_time.consensus_encode(w)?;
+        Ok(())
     }
 }
 
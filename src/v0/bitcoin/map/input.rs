@@ -13,6 +13,7 @@ use bitcoin::sighash::{EcdsaSighashType, NonStandardSighashTypeError, TapSighash
 use bitcoin::taproot::{ControlBlock, LeafVersion, TapLeafHash, TapNodeHash};
 use bitcoin::{ecdsa, taproot};
 
+use crate::io_ext::PairSink;
 use crate::prelude::*;
 use crate::sighash_type::*;
 use crate::v0::bitcoin::map::Map;
@@ -338,93 +339,90 @@ impl Input {
 }
 
 impl Map for Input {
-    fn get_pairs(&self) -> Vec<raw::Pair> {
-        let mut rv: Vec<raw::Pair> = Default::default();
-
+    fn push_pairs<S: PairSink>(&self, pairs: &mut S) {
         impl_psbt_get_pair! {
-            rv.push(self.non_witness_utxo, PSBT_IN_NON_WITNESS_UTXO)
+            pairs.push(self.non_witness_utxo, PSBT_IN_NON_WITNESS_UTXO)
         }
 
         impl_psbt_get_pair! {
-            rv.push(self.witness_utxo, PSBT_IN_WITNESS_UTXO)
+            pairs.push(self.witness_utxo, PSBT_IN_WITNESS_UTXO)
         }
 
         impl_psbt_get_pair! {
-            rv.push_map(self.partial_sigs, PSBT_IN_PARTIAL_SIG)
+            pairs.push_map(self.partial_sigs, PSBT_IN_PARTIAL_SIG)
         }
 
         impl_psbt_get_pair! {
-            rv.push(self.sighash_type, PSBT_IN_SIGHASH_TYPE)
+            pairs.push(self.sighash_type, PSBT_IN_SIGHASH_TYPE)
         }
 
         impl_psbt_get_pair! {
-            rv.push(self.redeem_script, PSBT_IN_REDEEM_SCRIPT)
+            pairs.push(self.redeem_script, PSBT_IN_REDEEM_SCRIPT)
         }
 
         impl_psbt_get_pair! {
-            rv.push(self.witness_script, PSBT_IN_WITNESS_SCRIPT)
+            pairs.push(self.witness_script, PSBT_IN_WITNESS_SCRIPT)
         }
 
         impl_psbt_get_pair! {
-            rv.push_map(self.bip32_derivation, PSBT_IN_BIP32_DERIVATION)
+            pairs.push_map(self.bip32_derivation, PSBT_IN_BIP32_DERIVATION)
         }
 
         impl_psbt_get_pair! {
-            rv.push(self.final_script_sig, PSBT_IN_FINAL_SCRIPTSIG)
+            pairs.push(self.final_script_sig, PSBT_IN_FINAL_SCRIPTSIG)
         }
 
         impl_psbt_get_pair! {
-            rv.push(self.final_script_witness, PSBT_IN_FINAL_SCRIPTWITNESS)
+            pairs.push(self.final_script_witness, PSBT_IN_FINAL_SCRIPTWITNESS)
         }
 
         impl_psbt_get_pair! {
-            rv.push_map(self.ripemd160_preimages, PSBT_IN_RIPEMD160)
+            pairs.push_map(self.ripemd160_preimages, PSBT_IN_RIPEMD160)
         }
 
         impl_psbt_get_pair! {
-            rv.push_map(self.sha256_preimages, PSBT_IN_SHA256)
+            pairs.push_map(self.sha256_preimages, PSBT_IN_SHA256)
         }
 
         impl_psbt_get_pair! {
-            rv.push_map(self.hash160_preimages, PSBT_IN_HASH160)
+            pairs.push_map(self.hash160_preimages, PSBT_IN_HASH160)
         }
 
         impl_psbt_get_pair! {
-            rv.push_map(self.hash256_preimages, PSBT_IN_HASH256)
+            pairs.push_map(self.hash256_preimages, PSBT_IN_HASH256)
         }
 
         impl_psbt_get_pair! {
-            rv.push(self.tap_key_sig, PSBT_IN_TAP_KEY_SIG)
+            pairs.push(self.tap_key_sig, PSBT_IN_TAP_KEY_SIG)
         }
 
         impl_psbt_get_pair! {
-            rv.push_map(self.tap_script_sigs, PSBT_IN_TAP_SCRIPT_SIG)
+            pairs.push_map(self.tap_script_sigs, PSBT_IN_TAP_SCRIPT_SIG)
         }
 
         impl_psbt_get_pair! {
-            rv.push_map(self.tap_scripts, PSBT_IN_TAP_LEAF_SCRIPT)
+            pairs.push_map(self.tap_scripts, PSBT_IN_TAP_LEAF_SCRIPT)
         }
 
         impl_psbt_get_pair! {
-            rv.push_map(self.tap_key_origins, PSBT_IN_TAP_BIP32_DERIVATION)
+            pairs.push_map(self.tap_key_origins, PSBT_IN_TAP_BIP32_DERIVATION)
         }
 
         impl_psbt_get_pair! {
-            rv.push(self.tap_internal_key, PSBT_IN_TAP_INTERNAL_KEY)
+            pairs.push(self.tap_internal_key, PSBT_IN_TAP_INTERNAL_KEY)
         }
 
         impl_psbt_get_pair! {
-            rv.push(self.tap_merkle_root, PSBT_IN_TAP_MERKLE_ROOT)
+            pairs.push(self.tap_merkle_root, PSBT_IN_TAP_MERKLE_ROOT)
         }
         for (key, value) in self.proprietary.iter() {
-            rv.push(raw::Pair { key: key.to_key(), value: value.clone() });
+            let key = key.to_key();
+            pairs.push_pair(key.type_value, &key.key, value);
         }
 
         for (key, value) in self.unknown.iter() {
-            rv.push(raw::Pair { key: key.clone(), value: value.clone() });
+            pairs.push_pair(key.type_value, &key.key, value);
         }
-
-        rv
     }
 }
 
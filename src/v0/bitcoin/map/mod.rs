@@ -1,14 +1,15 @@
 // SPDX-License-Identifier: CC0-1.0
 
+use crate::io;
+use crate::io_ext::{PairSink, PairWriter};
 use crate::prelude::*;
+#[cfg(test)]
 use crate::v0::bitcoin::raw;
 
 mod global;
 mod input;
 mod output;
 
-use super::serialize::Serialize;
-
 #[rustfmt::skip]                // Keep public exports separate.
 #[doc(inline)]
 pub use self::{
@@ -18,8 +19,16 @@ pub use self::{
 
 /// A trait that describes a PSBT key-value map.
 pub(super) trait Map {
+    /// Passes all key-value pairs of this map to `pairs`, in serialization order.
+    fn push_pairs<S: PairSink>(&self, pairs: &mut S);
+
     /// Attempt to get all key-value pairs.
-    fn get_pairs(&self) -> Vec<raw::Pair>;
+    #[cfg(test)]
+    fn get_pairs(&self) -> Vec<raw::Pair> {
+        let mut rv: Vec<raw::Pair> = Default::default();
+        self.push_pairs(&mut rv);
+        rv
+    }
 
     /// Serialize Psbt binary map data according to BIP-174 specification.
     ///
@@ -30,10 +39,18 @@ pub(super) trait Map {
     /// actual keys. It can thus be used as a separator and allow for easier unserializer implementation.
     fn serialize_map(&self) -> Vec<u8> {
         let mut buf = Vec::new();
-        for pair in Map::get_pairs(self) {
-            buf.extend(&pair.serialize());
-        }
-        buf.push(0x00_u8);
+        self.encode_map(&mut buf).expect("in-memory writers don't error");
         buf
     }
+
+    /// Writes the map to `w` as described in [`Map::serialize_map`], without collecting the pairs.
+    ///
+    /// Returns the number of bytes written.
+    fn encode_map<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<usize> {
+        let mut writer = PairWriter::new(w);
+        self.push_pairs(&mut writer);
+        let len = writer.finish()?;
+        w.write_all(&[0x00])?;
+        Ok(len + 1)
+    }
 }
@@ -7,6 +7,7 @@ use bitcoin::blockdata::script::ScriptBuf;
 use bitcoin::secp256k1::{self, XOnlyPublicKey};
 use bitcoin::taproot::{TapLeafHash, TapTree};
 
+use crate::io_ext::PairSink;
 use crate::prelude::*;
 use crate::v0::bitcoin::map::Map;
 use crate::v0::bitcoin::{raw, Error};
@@ -132,42 +133,39 @@ impl Output {
 }
 
 impl Map for Output {
-    fn get_pairs(&self) -> Vec<raw::Pair> {
-        let mut rv: Vec<raw::Pair> = Default::default();
-
+    fn push_pairs<S: PairSink>(&self, pairs: &mut S) {
         impl_psbt_get_pair! {
-            rv.push(self.redeem_script, PSBT_OUT_REDEEM_SCRIPT)
+            pairs.push(self.redeem_script, PSBT_OUT_REDEEM_SCRIPT)
         }
 
         impl_psbt_get_pair! {
-            rv.push(self.witness_script, PSBT_OUT_WITNESS_SCRIPT)
+            pairs.push(self.witness_script, PSBT_OUT_WITNESS_SCRIPT)
         }
 
         impl_psbt_get_pair! {
-            rv.push_map(self.bip32_derivation, PSBT_OUT_BIP32_DERIVATION)
+            pairs.push_map(self.bip32_derivation, PSBT_OUT_BIP32_DERIVATION)
         }
 
         impl_psbt_get_pair! {
-            rv.push(self.tap_internal_key, PSBT_OUT_TAP_INTERNAL_KEY)
+            pairs.push(self.tap_internal_key, PSBT_OUT_TAP_INTERNAL_KEY)
         }
 
         impl_psbt_get_pair! {
-            rv.push(self.tap_tree, PSBT_OUT_TAP_TREE)
+            pairs.push(self.tap_tree, PSBT_OUT_TAP_TREE)
         }
 
         impl_psbt_get_pair! {
-            rv.push_map(self.tap_key_origins, PSBT_OUT_TAP_BIP32_DERIVATION)
+            pairs.push_map(self.tap_key_origins, PSBT_OUT_TAP_BIP32_DERIVATION)
         }
 
         for (key, value) in self.proprietary.iter() {
-            rv.push(raw::Pair { key: key.to_key(), value: value.clone() });
+            let key = key.to_key();
+            pairs.push_pair(key.type_value, &key.key, value);
         }
 
         for (key, value) in self.unknown.iter() {
-            rv.push(raw::Pair { key: key.clone(), value: value.clone() });
+            pairs.push_pair(key.type_value, &key.key, value);
         }
-
-        rv
     }
 }
 
@@ -15,6 +15,7 @@ use bitcoin::consensus::encode::{
 
 use super::serialize::{Deserialize, Serialize};
 use crate::io;
+use crate::io_ext::{self, Encode, PairSink};
 use crate::prelude::*;
 use crate::v0::bitcoin::Error;
 
@@ -45,6 +46,17 @@ pub struct Pair {
     pub value: Vec<u8>,
 }
 
+impl PairSink for Vec<Pair> {
+    fn push_pair<K, V>(&mut self, type_value: u8, key: &K, value: &V)
+    where
+        K: Encode + ?Sized,
+        V: Encode + ?Sized,
+    {
+        let key = Key { type_value, key: io_ext::encode_to_vec(key) };
+        self.push(Pair { key, value: io_ext::encode_to_vec(value) });
+    }
+}
+
 /// Default implementation for proprietary key subtyping
 pub type ProprietaryType = u8;
 
@@ -22,11 +22,15 @@ use bitcoin::taproot::{
 use bitcoin::{ecdsa, taproot, VarInt};
 
 use super::map::{Input, Map, Output};
-use crate::io;
+use crate::io::{self, Read as _};
+use crate::io_ext::CountingReader;
 use crate::prelude::*;
 use crate::sighash_type::PsbtSighashType;
 use crate::v0::bitcoin::{Error, Psbt};
 
+const MAGIC_BYTES: &[u8] = b"psbt";
+const PSBT_SERPARATOR: u8 = 0xff_u8;
+
 /// A trait for serializing a value as raw data for insertion into PSBT
 /// key-value maps.
 pub(crate) trait Serialize {
@@ -47,40 +51,65 @@ impl Psbt {
     /// Serialize as raw binary data
     pub fn serialize(&self) -> Vec<u8> {
         let mut buf: Vec<u8> = Vec::new();
+        self.encode(&mut buf).expect("in-memory writers don't error");
+        buf
+    }
 
+    /// Encodes this PSBT to `w`, writing each key-value pair directly to the writer.
+    ///
+    /// Returns the number of bytes written.
+    pub fn encode<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<usize> {
         //  <magic>
-        buf.extend_from_slice(b"psbt");
-
-        buf.push(0xff_u8);
+        w.write_all(MAGIC_BYTES)?;
+        w.write_all(&[PSBT_SERPARATOR])?;
+        let mut len = MAGIC_BYTES.len() + 1;
 
-        buf.extend(self.serialize_map());
+        len += self.encode_map(w)?;
 
         for i in &self.inputs {
-            buf.extend(i.serialize_map());
+            len += i.encode_map(w)?;
         }
 
         for i in &self.outputs {
-            buf.extend(i.serialize_map());
+            len += i.encode_map(w)?;
         }
 
-        buf
+        Ok(len)
     }
 
     /// Deserialize a value from raw binary data.
     pub fn deserialize(bytes: &[u8]) -> Result<Self, Error> {
-        const MAGIC_BYTES: &[u8] = b"psbt";
         if bytes.get(0..MAGIC_BYTES.len()) != Some(MAGIC_BYTES) {
             return Err(Error::InvalidMagic);
         }
-
-        const PSBT_SERPARATOR: u8 = 0xff_u8;
         if bytes.get(MAGIC_BYTES.len()) != Some(&PSBT_SERPARATOR) {
             return Err(Error::InvalidSeparator);
         }
 
-        let mut d = bytes.get(5..).ok_or(Error::NoMorePairs)?;
+        let mut d = bytes;
+        Psbt::decode(&mut d).map(|(psbt, _)| psbt)
+    }
+
+    /// Decodes a PSBT from `r`, reading no further than the end of the last output map.
+    ///
+    /// Returns the PSBT along with the number of bytes consumed from `r`, allowing a PSBT to be
+    /// parsed from a stream or from a larger buffer without knowing its length up front.
+    pub fn decode<R: io::Read + ?Sized>(r: &mut R) -> Result<(Self, usize), Error> {
+        let mut r = CountingReader::new(r);
 
-        let mut global = Psbt::decode_global(&mut d)?;
+        let mut magic = [0_u8; 4];
+        r.read_exact(&mut magic)?;
+        if magic[..] != *MAGIC_BYTES {
+            return Err(Error::InvalidMagic);
+        }
+
+        let mut separator = [0_u8; 1];
+        r.read_exact(&mut separator)?;
+        if separator[0] != PSBT_SERPARATOR {
+            return Err(Error::InvalidSeparator);
+        }
+
+        let mut global = Psbt::decode_global(&mut r)?;
         global.unsigned_tx_checks()?;
 
         let inputs: Vec<Input> = {
@@ -89,7 +118,7 @@ impl Psbt {
             let mut inputs: Vec<Input> = Vec::with_capacity(inputs_len);
 
             for _ in 0..inputs_len {
-                inputs.push(Input::decode(&mut d)?);
+                inputs.push(Input::decode(&mut r)?);
             }
 
             inputs
@@ -101,7 +130,7 @@ impl Psbt {
             let mut outputs: Vec<Output> = Vec::with_capacity(outputs_len);
 
             for _ in 0..outputs_len {
-                outputs.push(Output::decode(&mut d)?);
+                outputs.push(Output::decode(&mut r)?);
             }
 
             outputs
@@ -109,9 +138,10 @@ impl Psbt {
 
         global.inputs = inputs;
         global.outputs = outputs;
-        Ok(global)
+        Ok((global, r.count()))
     }
 }
+
 impl_psbt_de_serialize!(Transaction);
 impl_psbt_de_serialize!(TxOut);
 impl_psbt_de_serialize!(Witness);
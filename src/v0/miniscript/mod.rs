@@ -25,7 +25,7 @@ use miniscript::{
     Preimage32, Satisfier, SigType, ToPublicKey, TranslatePk, Translator,
 };
 
-use crate::v0::bitcoin::{raw, Input, Output, Psbt};
+use crate::v0::bitcoin::{Input, Output, Psbt};
 use crate::prelude::*;
 
 mod finalizer;
@@ -1003,8 +1003,9 @@ impl Translator<DefiniteDescriptorKey, bitcoin::PublicKey, descriptor::Conversio
     miniscript::translate_hash_clone!(DescriptorPublicKey, bitcoin::PublicKey, descriptor::ConversionError);
 }
 
-// Provides generalized access to PSBT fields common to inputs and outputs
-trait PsbtFields {
+// Provides generalized access to PSBT fields common to inputs and outputs, also implemented for
+// the v2 `Input` and `Output` so the descriptor update logic is shared.
+pub(crate) trait PsbtFields {
     // Common fields are returned as a mutable ref of the same type
     fn redeem_script(&mut self) -> &mut Option<ScriptBuf>;
     fn witness_script(&mut self) -> &mut Option<ScriptBuf>;
@@ -1013,8 +1014,6 @@ trait PsbtFields {
     fn tap_key_origins(
         &mut self,
     ) -> &mut BTreeMap<bitcoin::key::XOnlyPublicKey, (Vec<TapLeafHash>, bip32::KeySource)>;
-    fn proprietary(&mut self) -> &mut BTreeMap<raw::ProprietaryKey, Vec<u8>>;
-    fn unknown(&mut self) -> &mut BTreeMap<raw::Key, Vec<u8>>;
 
     // `tap_tree` only appears in Output, so it's returned as an option of a mutable ref
     fn tap_tree(&mut self) -> Option<&mut Option<taproot::TapTree>> { None }
@@ -1040,10 +1039,6 @@ impl PsbtFields for Input {
     ) -> &mut BTreeMap<bitcoin::key::XOnlyPublicKey, (Vec<TapLeafHash>, bip32::KeySource)> {
         &mut self.tap_key_origins
     }
-    fn proprietary(&mut self) -> &mut BTreeMap<raw::ProprietaryKey, Vec<u8>> {
-        &mut self.proprietary
-    }
-    fn unknown(&mut self) -> &mut BTreeMap<raw::Key, Vec<u8>> { &mut self.unknown }
 
     fn tap_scripts(&mut self) -> Option<&mut BTreeMap<ControlBlock, (ScriptBuf, LeafVersion)>> {
         Some(&mut self.tap_scripts)
@@ -1067,15 +1062,11 @@ impl PsbtFields for Output {
     ) -> &mut BTreeMap<bitcoin::key::XOnlyPublicKey, (Vec<TapLeafHash>, bip32::KeySource)> {
         &mut self.tap_key_origins
     }
-    fn proprietary(&mut self) -> &mut BTreeMap<raw::ProprietaryKey, Vec<u8>> {
-        &mut self.proprietary
-    }
-    fn unknown(&mut self) -> &mut BTreeMap<raw::Key, Vec<u8>> { &mut self.unknown }
 
     fn tap_tree(&mut self) -> Option<&mut Option<taproot::TapTree>> { Some(&mut self.tap_tree) }
 }
 
-fn update_item_with_descriptor_helper<F: PsbtFields>(
+pub(crate) fn update_item_with_descriptor_helper<F: PsbtFields>(
     item: &mut F,
     descriptor: &Descriptor<DefiniteDescriptorKey>,
     check_script: Option<&Script>,
@@ -0,0 +1,371 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Replace-by-fee (BIP-125) fee bumping.
+//!
+//! A [`FeeBumper`] builds a replacement for a transaction that has already been signed. The fee is
+//! increased by reducing a change output and by adding inputs. All signatures are removed so the
+//! replacement is returned ready to be signed again.
+
+use core::fmt;
+
+use bitcoin::{Amount, FeeRate, OutPoint, Sequence, Transaction, Weight};
+
+use crate::error::{change_amount, fee_rate, write_err, FeeError, PayFeeError};
+use crate::prelude::*;
+use crate::v2::map::input::Input;
+use crate::v2::{
+    Constructor, DetermineLockTimeError, IndexOutOfBoundsError, InputsOnlyModifiable, Psbt, Updater,
+};
+
+/// The weight of an input without its script sig and witness (outpoint, empty script sig length,
+/// and sequence).
+const TX_IN_BASE_WEIGHT: u64 = 4 * (36 + 1 + 4);
+
+/// Builds a BIP-125 replacement transaction paying a higher fee rate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeeBumper {
+    /// The PSBT of the original transaction.
+    psbt: Psbt,
+    /// The fee paid by the original transaction.
+    original_fee: Amount,
+    /// The weight of the signed original transaction.
+    original_weight: Weight,
+    /// True if the original transaction has any witness data.
+    original_segwit: bool,
+    /// The output whose amount is reduced to pay the additional fee.
+    change: Option<usize>,
+    /// Additional inputs and the weight required to satisfy them.
+    inputs: Vec<(Input, Weight)>,
+}
+
+impl FeeBumper {
+    /// Creates a `FeeBumper` for the transaction of a finalized PSBT.
+    pub fn new(psbt: Psbt) -> Result<Self, NewFeeBumperError> {
+        if !psbt.is_finalized() {
+            return Err(NewFeeBumperError::NotFinalized);
+        }
+        let tx = Transaction {
+            version: psbt.global.tx_version,
+            lock_time: psbt.determine_lock_time()?,
+            input: psbt.inputs.iter().map(|input| input.signed_tx_in()).collect(),
+            output: psbt.outputs.iter().map(|output| output.tx_out()).collect(),
+        };
+        Self::from_tx(&tx, psbt)
+    }
+
+    /// Creates a `FeeBumper` for the signed transaction `tx` that was created from `psbt`.
+    ///
+    /// The PSBT need not be finalized, the size of the signatures is taken from `tx`.
+    pub fn from_tx(tx: &Transaction, psbt: Psbt) -> Result<Self, NewFeeBumperError> {
+        let mut unsigned = tx.clone();
+        for input in &mut unsigned.input {
+            input.script_sig = Default::default();
+            input.witness = Default::default();
+            // Same as `Psbt::id`, sequences may be changed by the updater.
+            input.sequence = Sequence::ZERO;
+        }
+        if unsigned.txid() != psbt.id()? {
+            return Err(NewFeeBumperError::TransactionMismatch);
+        }
+
+        Ok(FeeBumper {
+            original_fee: psbt.fee()?,
+            original_weight: tx.weight(),
+            original_segwit: tx.input.iter().any(|input| !input.witness.is_empty()),
+            psbt,
+            change: None,
+            inputs: vec![],
+        })
+    }
+
+    /// Returns the fee paid by the original transaction.
+    pub fn original_fee(&self) -> Amount { self.original_fee }
+
+    /// Returns the fee rate of the original transaction.
+    pub fn original_fee_rate(&self) -> FeeRate { fee_rate(self.original_fee, self.original_weight) }
+
+    /// Sets the output whose amount is reduced to pay the additional fee.
+    ///
+    /// Without a change output the additional fee must be paid by added inputs.
+    pub fn change_output(mut self, output_index: usize) -> Self {
+        self.change = Some(output_index);
+        self
+    }
+
+    /// Adds an input to pay the additional fee.
+    ///
+    /// `satisfaction_weight` is the weight added to the transaction by the script sig and witness
+    /// of the input e.g., from `Input::max_weight_to_satisfy` or
+    /// `Descriptor::max_weight_to_satisfy`. The input must have a funding utxo and must not spend
+    /// the same outpoint as any other input.
+    pub fn input(mut self, input: Input, satisfaction_weight: Weight) -> Self {
+        self.inputs.push((input, satisfaction_weight));
+        self
+    }
+
+    /// Builds the replacement transaction paying at least `fee_rate`.
+    ///
+    /// The fee is at least `fee_rate` times the estimated weight of the replacement and exceeds
+    /// the original fee by at least the minimum incremental relay fee. Any value added by inputs in
+    /// excess of the fee goes to the change output. Inputs without an explicit sequence that signals
+    /// replaceability are set to [`Sequence::ENABLE_RBF_NO_LOCKTIME`].
+    ///
+    /// The returned PSBT has no signatures or final scripts and needs signing again, its inputs and
+    /// outputs are marked as modifiable as for a newly constructed PSBT. If the original PSBT was
+    /// finalized it no longer has the data needed to sign (e.g., the BIP-32 derivations), update
+    /// the inputs again before signing.
+    pub fn bump(self, fee_rate: FeeRate) -> Result<Updater, BumpFeeError> {
+        use BumpFeeError::*;
+
+        if fee_rate <= self.original_fee_rate() {
+            return Err(FeeRateTooLow {
+                original: self.original_fee_rate(),
+                replacement: fee_rate,
+            });
+        }
+
+        let mut out_points: BTreeSet<OutPoint> =
+            self.psbt.inputs.iter().map(Input::out_point).collect();
+        for (input, _) in &self.inputs {
+            let out_point = input.out_point();
+            if !out_points.insert(out_point) {
+                return Err(DuplicateInput { out_point });
+            }
+        }
+
+        let mut psbt = self.psbt;
+        let weight = replacement_weight(
+            self.original_weight,
+            self.original_segwit,
+            psbt.inputs.len(),
+            &self.inputs,
+        )?;
+
+        // The signatures commit to the original transaction, once they are removed the inputs and
+        // outputs can be modified again.
+        for input in &mut psbt.inputs {
+            input.clear_sig_data();
+        }
+        psbt.global.set_inputs_modifiable_flag();
+        psbt.global.set_outputs_modifiable_flag();
+        psbt.global.clear_sighash_single_flag();
+
+        let mut input_value = Amount::ZERO;
+        if !self.inputs.is_empty() {
+            if psbt.outputs.iter().any(|output| output.sp_v0_info.is_some()) {
+                // The silent payment output scripts depend on the inputs.
+                return Err(SilentPaymentOutputs);
+            }
+            let mut constructor =
+                Constructor::<InputsOnlyModifiable>::new(psbt).expect("inputs flag was set above");
+            for (input, _) in self.inputs {
+                let utxo = input.funding_utxo().map_err(|_| MissingFundingUtxo)?;
+                input_value =
+                    input_value.checked_add(utxo.value).ok_or(PayFeeError::FeeOverflow)?;
+                constructor = constructor.input(input);
+            }
+            psbt = constructor.psbt()?;
+        }
+
+        let required_fee = required_fee(self.original_fee, fee_rate, weight)?;
+        let available =
+            self.original_fee.checked_add(input_value).ok_or(PayFeeError::FeeOverflow)?;
+
+        if let Some(output_index) = self.change {
+            let change = psbt.checked_output_mut(output_index)?;
+            let change_value =
+                change.amount.checked_add(available).ok_or(PayFeeError::FeeOverflow)?;
+            change.amount = change_amount(change_value, required_fee, &change.script_pubkey)?;
+        } else if available < required_fee {
+            let missing = required_fee - available;
+            return Err(PayFee(PayFeeError::InsufficientFunds { missing }));
+        }
+
+        for input in &mut psbt.inputs {
+            if !input.sequence.map(|n| n.is_rbf()).unwrap_or(false) {
+                input.sequence = Some(Sequence::ENABLE_RBF_NO_LOCKTIME);
+            }
+        }
+
+        Ok(Updater::new(psbt)?)
+    }
+}
+
+impl Psbt {
+    /// Returns a [`FeeBumper`] for the transaction of this finalized PSBT.
+    pub fn into_fee_bumper(self) -> Result<FeeBumper, NewFeeBumperError> { FeeBumper::new(self) }
+}
+
+/// Estimates the weight of the replacement transaction.
+fn replacement_weight(
+    original: Weight,
+    original_segwit: bool,
+    original_inputs: usize,
+    added: &[(Input, Weight)],
+) -> Result<Weight, BumpFeeError> {
+    if added.is_empty() {
+        return Ok(original);
+    }
+    let mut weight = original.to_wu();
+
+    let count = |n: usize| bitcoin::VarInt(n as u64).size() as u64;
+    weight += 4 * (count(original_inputs + added.len()) - count(original_inputs));
+
+    let mut segwit = original_segwit;
+    for (input, satisfaction_weight) in added {
+        weight += TX_IN_BASE_WEIGHT + satisfaction_weight.to_wu();
+        let utxo = input.funding_utxo().map_err(|_| BumpFeeError::MissingFundingUtxo)?;
+        let witness_program = utxo.script_pubkey.is_witness_program()
+            || input.redeem_script.as_ref().map(|s| s.is_witness_program()).unwrap_or(false);
+        segwit |= witness_program;
+    }
+    if segwit {
+        // The empty witness stack length of each added input.
+        weight += added.len() as u64;
+        if !original_segwit {
+            // Segwit marker and flag plus the empty witness stack length of each original input.
+            weight += 2 + original_inputs as u64;
+        }
+    }
+    Ok(Weight::from_wu(weight))
+}
+
+/// Returns the fee required by the replacement, the higher of the fee at `fee_rate` and the fee
+/// required by BIP-125 rule 4 (pay for the replacement's own bandwidth).
+fn required_fee(
+    original_fee: Amount,
+    fee_rate: FeeRate,
+    weight: Weight,
+) -> Result<Amount, PayFeeError> {
+    let target = fee_rate.fee_wu(weight).ok_or(PayFeeError::FeeOverflow)?;
+    let incremental = FeeRate::BROADCAST_MIN.fee_wu(weight).ok_or(PayFeeError::FeeOverflow)?;
+    let rule_four = original_fee.checked_add(incremental).ok_or(PayFeeError::FeeOverflow)?;
+    Ok(target.max(rule_four))
+}
+
+/// Error creating a [`FeeBumper`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum NewFeeBumperError {
+    /// The PSBT is not finalized, use [`FeeBumper::from_tx`] with the signed transaction.
+    NotFinalized,
+    /// The transaction was not created from the PSBT.
+    TransactionMismatch,
+    /// Failed to determine lock time.
+    DetermineLockTime(DetermineLockTimeError),
+    /// Failed to calculate the fee of the original transaction.
+    Fee(FeeError),
+}
+
+impl fmt::Display for NewFeeBumperError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use NewFeeBumperError::*;
+
+        match *self {
+            NotFinalized => f.write_str("PSBT is not finalized"),
+            TransactionMismatch => f.write_str("transaction was not created from the PSBT"),
+            DetermineLockTime(ref e) => write_err!(f, "fee bumper determine lock time"; e),
+            Fee(ref e) => write_err!(f, "failed to calculate original fee"; e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for NewFeeBumperError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use NewFeeBumperError::*;
+
+        match *self {
+            DetermineLockTime(ref e) => Some(e),
+            Fee(ref e) => Some(e),
+            NotFinalized | TransactionMismatch => None,
+        }
+    }
+}
+
+impl From<DetermineLockTimeError> for NewFeeBumperError {
+    fn from(e: DetermineLockTimeError) -> Self { Self::DetermineLockTime(e) }
+}
+
+impl From<FeeError> for NewFeeBumperError {
+    fn from(e: FeeError) -> Self { Self::Fee(e) }
+}
+
+/// Error building a replacement transaction.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum BumpFeeError {
+    /// The replacement fee rate must be higher than the fee rate of the original transaction.
+    FeeRateTooLow {
+        /// Fee rate of the original transaction.
+        original: FeeRate,
+        /// Requested fee rate of the replacement.
+        replacement: FeeRate,
+    },
+    /// The change output and added inputs can not pay the required fee.
+    PayFee(PayFeeError),
+    /// An added input has no funding utxo.
+    MissingFundingUtxo,
+    /// An added input spends the same outpoint as another input.
+    DuplicateInput {
+        /// The outpoint spent more than once.
+        out_point: OutPoint,
+    },
+    /// Inputs can not be added to a transaction with silent payment outputs.
+    SilentPaymentOutputs,
+    /// The change output index is out of bounds.
+    IndexOutOfBounds(IndexOutOfBoundsError),
+    /// Failed to determine lock time.
+    DetermineLockTime(DetermineLockTimeError),
+}
+
+impl fmt::Display for BumpFeeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use BumpFeeError::*;
+
+        match *self {
+            FeeRateTooLow { original, replacement } => write!(
+                f,
+                "replacement fee rate {} must be higher than the original fee rate {}",
+                replacement, original
+            ),
+            PayFee(ref e) => write_err!(f, "failed to pay the replacement fee"; e),
+            MissingFundingUtxo => f.write_str("added input has no funding utxo"),
+            DuplicateInput { out_point } =>
+                write!(f, "added input spends outpoint {} which is already spent", out_point),
+            SilentPaymentOutputs =>
+                f.write_str("can not add inputs to a transaction with silent payment outputs"),
+            IndexOutOfBounds(ref e) => write_err!(f, "change output"; e),
+            DetermineLockTime(ref e) => write_err!(f, "fee bumper determine lock time"; e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for BumpFeeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use BumpFeeError::*;
+
+        match *self {
+            PayFee(ref e) => Some(e),
+            IndexOutOfBounds(ref e) => Some(e),
+            DetermineLockTime(ref e) => Some(e),
+            FeeRateTooLow { .. }
+            | MissingFundingUtxo
+            | DuplicateInput { .. }
+            | SilentPaymentOutputs => None,
+        }
+    }
+}
+
+impl From<PayFeeError> for BumpFeeError {
+    fn from(e: PayFeeError) -> Self { Self::PayFee(e) }
+}
+
+impl From<IndexOutOfBoundsError> for BumpFeeError {
+    fn from(e: IndexOutOfBoundsError) -> Self { Self::IndexOutOfBounds(e) }
+}
+
+impl From<DetermineLockTimeError> for BumpFeeError {
+    fn from(e: DetermineLockTimeError) -> Self { Self::DetermineLockTime(e) }
+}
@@ -0,0 +1,248 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Child-pays-for-parent (CPFP) fee bumping.
+//!
+//! A [`ChildBuilder`] builds a child PSBT spending outputs of a stuck parent transaction. The
+//! child pays enough fee to lift the fee rate of the package (parent and child) to a target.
+
+use core::fmt;
+
+use bitcoin::{
+    absolute, transaction, Amount, FeeRate, OutPoint, ScriptBuf, Transaction, TxIn, TxOut, Txid,
+    Weight,
+};
+
+use crate::error::{change_amount, fee_rate, write_err, FeeError, PayFeeError};
+use crate::prelude::*;
+use crate::v2::{
+    Constructor, ExtractError, Extractor, InputBuilder, Modifiable, OutputBuilder, Psbt,
+};
+
+/// Builds a child PSBT that pays for its parent transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChildBuilder {
+    /// The signed parent transaction.
+    parent: Transaction,
+    /// The txid of the parent transaction.
+    parent_txid: Txid,
+    /// The fee paid by the parent transaction.
+    parent_fee: Amount,
+    /// Spent parent output indices and the weight required to satisfy them.
+    spent: Vec<(u32, Weight)>,
+}
+
+impl ChildBuilder {
+    /// Creates a `ChildBuilder` for the transaction of a finalized PSBT.
+    pub fn new(parent: Psbt) -> Result<Self, NewChildBuilderError> {
+        let parent_fee = parent.fee()?;
+        let parent = Extractor::new(parent)?
+            .extract_tx_unchecked_fee_rate()
+            .expect("Extractor guarantees the PSBT is finalized and lock time can be determined");
+        Ok(Self::from_tx(parent, parent_fee))
+    }
+
+    /// Creates a `ChildBuilder` for the signed transaction `parent` paying `parent_fee`.
+    ///
+    /// The fee can not be calculated from the transaction alone, it is the caller's responsibility
+    /// to pass the correct fee.
+    pub fn from_tx(parent: Transaction, parent_fee: Amount) -> Self {
+        ChildBuilder { parent_txid: parent.txid(), parent, parent_fee, spent: vec![] }
+    }
+
+    /// Returns the fee paid by the parent transaction.
+    pub fn parent_fee(&self) -> Amount { self.parent_fee }
+
+    /// Returns the fee rate of the parent transaction.
+    pub fn parent_fee_rate(&self) -> FeeRate { fee_rate(self.parent_fee, self.parent.weight()) }
+
+    /// Spends the parent output at index `vout`.
+    ///
+    /// `satisfaction_weight` is the weight added to the child by the script sig and witness of the
+    /// input e.g., from `Descriptor::max_weight_to_satisfy`.
+    pub fn spend(mut self, vout: u32, satisfaction_weight: Weight) -> Self {
+        self.spent.push((vout, satisfaction_weight));
+        self
+    }
+
+    /// Builds the child paying the value of the spent outputs, less the fee, to `change`.
+    ///
+    /// The child fee lifts the fee rate of the package to at least `fee_rate`, and the child alone
+    /// pays at least `fee_rate`. Inputs spending witness programs are funded with the witness utxo,
+    /// all other inputs with the parent transaction.
+    ///
+    /// Adding inputs or outputs to the returned constructor changes the weight of the child, the
+    /// fee is not adjusted.
+    pub fn build(
+        self,
+        fee_rate: FeeRate,
+        change: ScriptBuf,
+    ) -> Result<Constructor<Modifiable>, BuildChildError> {
+        use BuildChildError::*;
+
+        if self.spent.is_empty() {
+            return Err(NoSpentOutputs);
+        }
+
+        let mut inputs = Vec::with_capacity(self.spent.len());
+        let mut spent = BTreeSet::new();
+        let mut value = Amount::ZERO;
+        for &(vout, _) in &self.spent {
+            if !spent.insert(vout) {
+                return Err(DuplicateSpentOutput { vout });
+            }
+            let utxo = self
+                .parent
+                .output
+                .get(vout as usize)
+                .ok_or(SpentOutputIndex { vout, output_count: self.parent.output.len() })?;
+            value = value.checked_add(utxo.value).ok_or(PayFeeError::FeeOverflow)?;
+
+            let builder = InputBuilder::new(&OutPoint { txid: self.parent_txid, vout });
+            let input = if utxo.script_pubkey.is_witness_program() {
+                builder.segwit_fund(utxo.clone())
+            } else {
+                builder.legacy_fund(self.parent.clone())
+            };
+            inputs.push(input.build());
+        }
+
+        let weight = self.child_weight(&change);
+        let fee = self.child_fee(fee_rate, weight)?;
+
+        let amount = change_amount(value, fee, &change)?;
+
+        let constructor = inputs
+            .into_iter()
+            .fold(Constructor::<Modifiable>::default(), |c, input| c.input(input));
+        let change = TxOut { value: amount, script_pubkey: change };
+        Ok(constructor.output(OutputBuilder::new(change).build()))
+    }
+
+    /// Estimates the weight of the child paying to `change`.
+    fn child_weight(&self, change: &ScriptBuf) -> Weight {
+        let unsigned = Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: self
+                .spent
+                .iter()
+                .map(|&(vout, _)| TxIn {
+                    previous_output: OutPoint { txid: self.parent_txid, vout },
+                    ..Default::default()
+                })
+                .collect(),
+            output: vec![TxOut { value: Amount::ZERO, script_pubkey: change.clone() }],
+        };
+        let mut weight = unsigned.weight().to_wu();
+        // Segwit marker and flag plus the empty witness stack length of each input. Assumed even if
+        // no input is segwit, this overestimates the weight of a legacy child by a few units.
+        weight += 2 + self.spent.len() as u64;
+        weight += self.spent.iter().map(|(_, w)| w.to_wu()).sum::<u64>();
+        Weight::from_wu(weight)
+    }
+
+    /// Returns the fee the child must pay to lift the package to `fee_rate`.
+    fn child_fee(&self, fee_rate: FeeRate, weight: Weight) -> Result<Amount, PayFeeError> {
+        let package_weight = self.parent.weight() + weight;
+        let package_fee = fee_rate.fee_wu(package_weight).ok_or(PayFeeError::FeeOverflow)?;
+        let own_fee = fee_rate.fee_wu(weight).ok_or(PayFeeError::FeeOverflow)?;
+        Ok(package_fee.checked_sub(self.parent_fee).unwrap_or(Amount::ZERO).max(own_fee))
+    }
+}
+
+/// Error creating a [`ChildBuilder`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum NewChildBuilderError {
+    /// Failed to extract the parent transaction.
+    Extract(ExtractError),
+    /// Failed to calculate the fee of the parent transaction.
+    Fee(FeeError),
+}
+
+impl fmt::Display for NewChildBuilderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use NewChildBuilderError::*;
+
+        match *self {
+            Extract(ref e) => write_err!(f, "failed to extract parent transaction"; e),
+            Fee(ref e) => write_err!(f, "failed to calculate parent fee"; e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for NewChildBuilderError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use NewChildBuilderError::*;
+
+        match *self {
+            Extract(ref e) => Some(e),
+            Fee(ref e) => Some(e),
+        }
+    }
+}
+
+impl From<ExtractError> for NewChildBuilderError {
+    fn from(e: ExtractError) -> Self { Self::Extract(e) }
+}
+
+impl From<FeeError> for NewChildBuilderError {
+    fn from(e: FeeError) -> Self { Self::Fee(e) }
+}
+
+/// Error building a child transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum BuildChildError {
+    /// No parent outputs are spent.
+    NoSpentOutputs,
+    /// The spent output index is out of bounds for the parent outputs.
+    SpentOutputIndex {
+        /// The spent output index.
+        vout: u32,
+        /// The number of parent outputs.
+        output_count: usize,
+    },
+    /// The parent output is spent more than once.
+    DuplicateSpentOutput {
+        /// The spent output index.
+        vout: u32,
+    },
+    /// The spent outputs can not pay the required fee.
+    PayFee(PayFeeError),
+}
+
+impl fmt::Display for BuildChildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use BuildChildError::*;
+
+        match *self {
+            NoSpentOutputs => f.write_str("child does not spend any parent outputs"),
+            SpentOutputIndex { vout, output_count } => write!(
+                f,
+                "spent output index {} is out of bounds for parent with {} outputs",
+                vout, output_count
+            ),
+            DuplicateSpentOutput { vout } =>
+                write!(f, "parent output {} is spent more than once", vout),
+            PayFee(ref e) => write_err!(f, "failed to pay the child fee"; e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for BuildChildError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use BuildChildError::*;
+
+        match *self {
+            PayFee(ref e) => Some(e),
+            NoSpentOutputs | SpentOutputIndex { .. } | DuplicateSpentOutput { .. } => None,
+        }
+    }
+}
+
+impl From<PayFeeError> for BuildChildError {
+    fn from(e: PayFeeError) -> Self { Self::PayFee(e) }
+}
@@ -5,9 +5,10 @@
 use core::fmt;
 
 use bitcoin::sighash::{self, EcdsaSighashType, NonStandardSighashTypeError};
-use bitcoin::PublicKey;
+use bitcoin::{secp256k1, PublicKey};
 
 use crate::error::{write_err, FundingUtxoError};
+use crate::io;
 use crate::v2::map::{global, input, output};
 
 /// Error while deserializing a PSBT.
@@ -31,15 +32,43 @@ pub enum DeserializeError {
     DecodeInput(input::DecodeError),
     /// Error decoding an output map.
     DecodeOutput(output::DecodeError),
+    /// I/O error reading the magic bytes or separator.
+    Io(io::Error),
 }
 
 impl fmt::Display for DeserializeError {
-    fn fmt(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result { todo!() }
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use DeserializeError::*;
+
+        match *self {
+            InvalidMagic => f.write_str("invalid magic"),
+            InvalidSeparator => f.write_str("invalid separator"),
+            NoMorePairs => f.write_str("no more key-value pairs for this psbt map"),
+            DecodeGlobal(ref e) => write_err!(f, "error decoding global map"; e),
+            DecodeInput(ref e) => write_err!(f, "error decoding input map"; e),
+            DecodeOutput(ref e) => write_err!(f, "error decoding output map"; e),
+            Io(ref e) => write_err!(f, "I/O error"; e),
+        }
+    }
 }
 
 #[cfg(feature = "std")]
 impl std::error::Error for DeserializeError {
-    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> { todo!() }
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use DeserializeError::*;
+
+        match *self {
+            DecodeGlobal(ref e) => Some(e),
+            DecodeInput(ref e) => Some(e),
+            DecodeOutput(ref e) => Some(e),
+            Io(ref e) => Some(e),
+            InvalidMagic | InvalidSeparator | NoMorePairs => None,
+        }
+    }
+}
+
+impl From<io::Error> for DeserializeError {
+    fn from(e: io::Error) -> Self { Self::Io(e) }
 }
 
 impl From<global::DecodeError> for DeserializeError {
@@ -54,7 +83,7 @@ impl From<output::DecodeError> for DeserializeError {
     fn from(e: output::DecodeError) -> Self { Self::DecodeOutput(e) }
 }
 
-/// Input index out of bounds (actual index, maximum index allowed).
+/// Input or output index out of bounds (actual index, maximum index allowed).
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[non_exhaustive]
 pub enum IndexOutOfBoundsError {
@@ -72,6 +101,20 @@ pub enum IndexOutOfBoundsError {
         /// Global input count.
         count: usize,
     },
+    /// The index is out of bounds for the `psbt.outputs` vector.
+    Outputs {
+        /// Attempted index access.
+        index: usize,
+        /// Length of the PBST outputs vector.
+        length: usize,
+    },
+    /// The index greater than the `psbt.global.output_count`.
+    OutputCount {
+        /// Attempted index access.
+        index: usize,
+        /// Global output count.
+        count: usize,
+    },
 }
 
 impl fmt::Display for IndexOutOfBoundsError {
@@ -86,6 +129,13 @@ impl fmt::Display for IndexOutOfBoundsError {
             ),
             Count { ref index, ref count } =>
                 write!(f, "index {} is greater global.input_count {}", index, count),
+            Outputs { ref index, ref length } => write!(
+                f,
+                "index {} is out-of-bounds for PSBT outputs vector length {}",
+                index, length
+            ),
+            OutputCount { ref index, ref count } =>
+                write!(f, "index {} is greater global.output_count {}", index, count),
         }
     }
 }
@@ -96,7 +146,7 @@ impl std::error::Error for IndexOutOfBoundsError {
         use IndexOutOfBoundsError::*;
 
         match *self {
-            Inputs { .. } | Count { .. } => None,
+            Inputs { .. } | Count { .. } | Outputs { .. } | OutputCount { .. } => None,
         }
     }
 }
@@ -131,8 +181,6 @@ pub enum SignError {
     KeyNotFound,
     /// Attempt to sign an input with the wrong signing algorithm.
     WrongSigningAlgorithm,
-    /// Signing request currently unsupported.
-    Unsupported,
 }
 
 impl fmt::Display for SignError {
@@ -154,7 +202,6 @@ impl fmt::Display for SignError {
             KeyNotFound => write!(f, "unable to find key"),
             WrongSigningAlgorithm =>
                 write!(f, "attempt to sign an input with the wrong signing algorithm"),
-            Unsupported => write!(f, "signing request currently unsupported"),
         }
     }
 }
@@ -177,8 +224,7 @@ impl std::error::Error for SignError {
             | NotWpkh
             | UnknownOutputType
             | KeyNotFound
-            | WrongSigningAlgorithm
-            | Unsupported => None,
+            | WrongSigningAlgorithm => None,
         }
     }
 }
@@ -195,6 +241,420 @@ impl From<FundingUtxoError> for SignError {
     fn from(e: FundingUtxoError) -> Self { Self::FundingUtxo(e) }
 }
 
+/// Error returned by [`crate::v2::Psbt::signer_checks`].
+///
+/// Every variant names the input or output that failed the check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SignerChecksError {
+    /// A silent payment output does not have its script yet.
+    SilentPaymentPending(SilentPaymentPendingError),
+    /// A check required the funding utxo but it is missing or invalid.
+    FundingUtxo {
+        /// The index of the offending input.
+        input_index: usize,
+        /// The funding utxo error.
+        error: FundingUtxoError,
+    },
+    /// The `non_witness_utxo` txid does not match the input's `previous_txid`.
+    NonWitnessUtxoTxidMismatch {
+        /// The index of the offending input.
+        input_index: usize,
+    },
+    /// The `witness_utxo` does not match the output of the `non_witness_utxo` being spent.
+    WitnessUtxoMismatch {
+        /// The index of the offending input.
+        input_index: usize,
+    },
+    /// Only a `witness_utxo` was provided but the input requires a non-witness signature.
+    NonWitnessSig {
+        /// The index of the offending input.
+        input_index: usize,
+    },
+    /// The `redeem_script` does not hash to the P2SH `scriptPubkey`.
+    RedeemScriptMismatch {
+        /// The index of the offending input.
+        input_index: usize,
+    },
+    /// The `witness_script` does not hash to the P2WSH `scriptPubkey`, this includes a
+    /// `scriptPubkey` that is not P2WSH.
+    WitnessScriptMismatchWsh {
+        /// The index of the offending input.
+        input_index: usize,
+    },
+    /// The `witness_script` does not hash to the P2WSH `redeem_script` of a P2SH-P2WSH input, this
+    /// includes a missing `redeem_script`.
+    WitnessScriptMismatchShWsh {
+        /// The index of the offending input.
+        input_index: usize,
+    },
+    /// The `tap_merkle_root` is set but the `tap_internal_key` is not.
+    MissingTapInternalKey {
+        /// The index of the offending input.
+        input_index: usize,
+    },
+    /// The `tap_internal_key` tweaked with `tap_merkle_root` does not match the `scriptPubkey`.
+    TaprootTweakMismatch {
+        /// The index of the offending input.
+        input_index: usize,
+    },
+}
+
+impl fmt::Display for SignerChecksError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use SignerChecksError::*;
+
+        match *self {
+            SilentPaymentPending(ref e) => write_err!(f, "silent payment output is pending"; e),
+            FundingUtxo { input_index, ref error } =>
+                write_err!(f, "funding utxo error for input {}", input_index; error),
+            NonWitnessUtxoTxidMismatch { input_index } => write!(
+                f,
+                "non_witness_utxo txid does not match the previous txid of input {}",
+                input_index
+            ),
+            WitnessUtxoMismatch { input_index } => write!(
+                f,
+                "witness_utxo does not match the non_witness_utxo output spent by input {}",
+                input_index
+            ),
+            NonWitnessSig { input_index } => write!(
+                f,
+                "input {} requires a non-witness signature but only has a witness_utxo",
+                input_index
+            ),
+            RedeemScriptMismatch { input_index } =>
+                write!(f, "redeem_script does not match the scriptPubkey of input {}", input_index),
+            WitnessScriptMismatchWsh { input_index } =>
+                write!(f, "witness_script does not match the scriptPubkey of input {}", input_index),
+            WitnessScriptMismatchShWsh { input_index } => write!(
+                f,
+                "witness_script does not match the redeem_script of input {}",
+                input_index
+            ),
+            MissingTapInternalKey { input_index } => write!(
+                f,
+                "tap_merkle_root is set without a tap_internal_key for input {}",
+                input_index
+            ),
+            TaprootTweakMismatch { input_index } => write!(
+                f,
+                "tweaked tap_internal_key does not match the scriptPubkey of input {}",
+                input_index
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SignerChecksError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use SignerChecksError::*;
+
+        match *self {
+            SilentPaymentPending(ref e) => Some(e),
+            FundingUtxo { ref error, .. } => Some(error),
+            NonWitnessUtxoTxidMismatch { .. }
+            | WitnessUtxoMismatch { .. }
+            | NonWitnessSig { .. }
+            | RedeemScriptMismatch { .. }
+            | WitnessScriptMismatchWsh { .. }
+            | WitnessScriptMismatchShWsh { .. }
+            | MissingTapInternalKey { .. }
+            | TaprootTweakMismatch { .. } => None,
+        }
+    }
+}
+
+/// Error creating a [`crate::v2::Signer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum NewSignerError {
+    /// Unable to determine the lock time.
+    DetermineLockTime(DetermineLockTimeError),
+    /// The PSBT failed the signer checks.
+    SignerChecks(SignerChecksError),
+}
+
+impl fmt::Display for NewSignerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use NewSignerError::*;
+
+        match *self {
+            DetermineLockTime(ref e) => write_err!(f, "unable to determine lock time"; e),
+            SignerChecks(ref e) => write_err!(f, "signer checks failed"; e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for NewSignerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use NewSignerError::*;
+
+        match *self {
+            DetermineLockTime(ref e) => Some(e),
+            SignerChecks(ref e) => Some(e),
+        }
+    }
+}
+
+impl From<DetermineLockTimeError> for NewSignerError {
+    fn from(e: DetermineLockTimeError) -> Self { Self::DetermineLockTime(e) }
+}
+
+impl From<SignerChecksError> for NewSignerError {
+    fn from(e: SignerChecksError) -> Self { Self::SignerChecks(e) }
+}
+
+/// Error aggregating MuSig2 partial signatures into a Taproot key path signature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Musig2AggregateError {
+    /// Input index out of bounds.
+    IndexOutOfBounds(IndexOutOfBoundsError),
+    /// Unable to determine the lock time.
+    DetermineLockTime(DetermineLockTimeError),
+    /// The input has no `tap_internal_key`.
+    MissingTapInternalKey,
+    /// No MuSig2 aggregate key in the input matches the `tap_internal_key`.
+    MissingParticipantPubkeys,
+    /// The participant keys do not aggregate to the `tap_internal_key`.
+    AggregateKeyMismatch,
+    /// A participant has not provided their public nonce.
+    MissingPubNonce {
+        /// The participant key missing a public nonce.
+        participant: secp256k1::PublicKey,
+    },
+    /// A participant has not provided their partial signature.
+    MissingPartialSig {
+        /// The participant key missing a partial signature.
+        participant: secp256k1::PublicKey,
+    },
+    /// A partial signature is not a valid scalar.
+    InvalidPartialSig {
+        /// The participant key with the invalid partial signature.
+        participant: secp256k1::PublicKey,
+    },
+    /// Error calculating the sighash message.
+    Sighash(SignError),
+    /// The nonces and partial signatures can not be aggregated.
+    Aggregation,
+    /// The aggregated signature does not verify against the output key.
+    InvalidSignature,
+}
+
+impl fmt::Display for Musig2AggregateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use Musig2AggregateError::*;
+
+        match *self {
+            IndexOutOfBounds(ref e) => write_err!(f, "index out of bounds"; e),
+            DetermineLockTime(ref e) => write_err!(f, "unable to determine lock time"; e),
+            MissingTapInternalKey => write!(f, "input has no tap_internal_key"),
+            MissingParticipantPubkeys =>
+                write!(f, "no MuSig2 participant pubkeys for the tap_internal_key"),
+            AggregateKeyMismatch =>
+                write!(f, "MuSig2 participant pubkeys do not aggregate to the tap_internal_key"),
+            MissingPubNonce { participant } =>
+                write!(f, "missing MuSig2 public nonce for participant {}", participant),
+            MissingPartialSig { participant } =>
+                write!(f, "missing MuSig2 partial signature for participant {}", participant),
+            InvalidPartialSig { participant } =>
+                write!(f, "invalid MuSig2 partial signature for participant {}", participant),
+            Sighash(ref e) => write_err!(f, "sighash"; e),
+            Aggregation => write!(f, "unable to aggregate MuSig2 nonces and partial signatures"),
+            InvalidSignature => write!(f, "aggregated MuSig2 signature is invalid"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Musig2AggregateError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use Musig2AggregateError::*;
+
+        match *self {
+            IndexOutOfBounds(ref e) => Some(e),
+            DetermineLockTime(ref e) => Some(e),
+            Sighash(ref e) => Some(e),
+            MissingTapInternalKey
+            | MissingParticipantPubkeys
+            | AggregateKeyMismatch
+            | MissingPubNonce { .. }
+            | MissingPartialSig { .. }
+            | InvalidPartialSig { .. }
+            | Aggregation
+            | InvalidSignature => None,
+        }
+    }
+}
+
+impl From<IndexOutOfBoundsError> for Musig2AggregateError {
+    fn from(e: IndexOutOfBoundsError) -> Self { Self::IndexOutOfBounds(e) }
+}
+
+impl From<DetermineLockTimeError> for Musig2AggregateError {
+    fn from(e: DetermineLockTimeError) -> Self { Self::DetermineLockTime(e) }
+}
+
+impl From<SignError> for Musig2AggregateError {
+    fn from(e: SignError) -> Self { Self::Sighash(e) }
+}
+
+/// Error computing the output scripts of silent payment outputs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SilentPaymentError {
+    /// Inputs can still be added to the PSBT, the output scripts commit to all the inputs.
+    InputsModifiable,
+    /// Unable to get the funding UTXO of an input.
+    FundingUtxo {
+        /// The index of the input.
+        input_index: usize,
+        /// The funding UTXO error.
+        error: FundingUtxoError,
+    },
+    /// No public key in the input matches its funding script.
+    MissingInputKey {
+        /// The index of the input.
+        input_index: usize,
+    },
+    /// None of the inputs are eligible for silent payments.
+    NoEligibleInputs,
+    /// An eligible input has no ECDH share for a scan key and there is no global share.
+    MissingEcdhShare {
+        /// The index of the input missing the share.
+        input_index: usize,
+        /// The scan key the share is missing for.
+        scan_key: secp256k1::PublicKey,
+    },
+    /// An ECDH share has no DLEQ proof.
+    MissingDleqProof {
+        /// The index of the input the share belongs to, `None` for a global share.
+        input_index: Option<usize>,
+        /// The scan key of the share.
+        scan_key: secp256k1::PublicKey,
+    },
+    /// The DLEQ proof of an ECDH share is invalid.
+    InvalidDleqProof {
+        /// The index of the input the share belongs to, `None` for a global share.
+        input_index: Option<usize>,
+        /// The scan key of the share.
+        scan_key: secp256k1::PublicKey,
+    },
+    /// The output keys can not be derived from the ECDH shares.
+    Derivation,
+}
+
+impl fmt::Display for SilentPaymentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use SilentPaymentError::*;
+
+        match *self {
+            InputsModifiable => write!(f, "inputs are still modifiable"),
+            FundingUtxo { input_index, ref error } =>
+                write_err!(f, "funding UTXO for input {}", input_index; error),
+            MissingInputKey { input_index } =>
+                write!(f, "no public key found for the script of input {}", input_index),
+            NoEligibleInputs => write!(f, "no inputs are eligible for silent payments"),
+            MissingEcdhShare { input_index, scan_key } =>
+                write!(f, "input {} has no ECDH share for scan key {}", input_index, scan_key),
+            MissingDleqProof { input_index: Some(input_index), scan_key } => write!(
+                f,
+                "ECDH share of input {} for scan key {} has no DLEQ proof",
+                input_index, scan_key
+            ),
+            MissingDleqProof { input_index: None, scan_key } =>
+                write!(f, "global ECDH share for scan key {} has no DLEQ proof", scan_key),
+            InvalidDleqProof { input_index: Some(input_index), scan_key } => write!(
+                f,
+                "invalid DLEQ proof for the ECDH share of input {} for scan key {}",
+                input_index, scan_key
+            ),
+            InvalidDleqProof { input_index: None, scan_key } =>
+                write!(f, "invalid DLEQ proof for the global ECDH share for scan key {}", scan_key),
+            Derivation => write!(f, "unable to derive silent payment output keys"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SilentPaymentError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use SilentPaymentError::*;
+
+        match *self {
+            FundingUtxo { ref error, .. } => Some(error),
+            InputsModifiable
+            | MissingInputKey { .. }
+            | NoEligibleInputs
+            | MissingEcdhShare { .. }
+            | MissingDleqProof { .. }
+            | InvalidDleqProof { .. }
+            | Derivation => None,
+        }
+    }
+}
+
+/// Error when marking the outputs as not modifiable while a silent payment output has no script.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SilentPaymentPendingError {
+    /// The index of the first output with a pending script.
+    pub output_index: usize,
+}
+
+impl fmt::Display for SilentPaymentPendingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "silent payment output {} has no script yet", self.output_index)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SilentPaymentPendingError {}
+
+/// Error ending construction using [`crate::v2::Constructor::updater`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum EndConstructionError {
+    /// Unable to determine the lock time.
+    DetermineLockTime(DetermineLockTimeError),
+    /// A silent payment output does not have its script yet.
+    SilentPaymentPending(SilentPaymentPendingError),
+}
+
+impl fmt::Display for EndConstructionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use EndConstructionError::*;
+
+        match *self {
+            DetermineLockTime(ref e) => write_err!(f, "unable to determine lock time"; e),
+            SilentPaymentPending(ref e) => write_err!(f, "silent payment output is pending"; e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for EndConstructionError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use EndConstructionError::*;
+
+        match *self {
+            DetermineLockTime(ref e) => Some(e),
+            SilentPaymentPending(ref e) => Some(e),
+        }
+    }
+}
+
+impl From<DetermineLockTimeError> for EndConstructionError {
+    fn from(e: DetermineLockTimeError) -> Self { Self::DetermineLockTime(e) }
+}
+
+impl From<SilentPaymentPendingError> for EndConstructionError {
+    fn from(e: SilentPaymentPendingError) -> Self { Self::SilentPaymentPending(e) }
+}
+
 /// Error when passing an un-modifiable PSBT to a `Constructor`.
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[non_exhaustive]
@@ -266,6 +726,47 @@ impl std::error::Error for OutputsNotModifiableError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> { None }
 }
 
+/// Error removing an input or output using a `Constructor`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RemoveError {
+    /// Input or output index out of bounds.
+    IndexOutOfBounds(IndexOutOfBoundsError),
+    /// Removal would break the input/output pairing of a SIGHASH_SINGLE signature.
+    BreaksSighashSinglePairing {
+        /// The index of the input signed using SIGHASH_SINGLE.
+        input_index: usize,
+    },
+}
+
+impl fmt::Display for RemoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use RemoveError::*;
+
+        match *self {
+            IndexOutOfBounds(ref e) => write_err!(f, "index out of bounds"; e),
+            BreaksSighashSinglePairing { input_index } =>
+                write!(f, "removal would break the SIGHASH_SINGLE pairing of input {}", input_index),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for RemoveError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use RemoveError::*;
+
+        match *self {
+            IndexOutOfBounds(ref e) => Some(e),
+            BreaksSighashSinglePairing { .. } => None,
+        }
+    }
+}
+
+impl From<IndexOutOfBoundsError> for RemoveError {
+    fn from(e: IndexOutOfBoundsError) -> Self { Self::IndexOutOfBounds(e) }
+}
+
 /// The input is not 100% unsigned.
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[non_exhaustive]
@@ -310,6 +811,56 @@ impl std::error::Error for DetermineLockTimeError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> { None }
 }
 
+/// Error converting a PSBT v0 to a PSBT v2.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FromV0Error {
+    /// The number of input maps does not match the number of inputs in the unsigned transaction.
+    InputCountMismatch {
+        /// The number of inputs in the unsigned transaction.
+        tx_inputs: usize,
+        /// The number of input maps in the PSBT.
+        input_maps: usize,
+    },
+    /// The number of output maps does not match the number of outputs in the unsigned transaction.
+    OutputCountMismatch {
+        /// The number of outputs in the unsigned transaction.
+        tx_outputs: usize,
+        /// The number of output maps in the PSBT.
+        output_maps: usize,
+    },
+}
+
+impl fmt::Display for FromV0Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use FromV0Error::*;
+
+        match *self {
+            InputCountMismatch { tx_inputs, input_maps } => write!(
+                f,
+                "unsigned tx has {} inputs but PSBT has {} input maps",
+                tx_inputs, input_maps
+            ),
+            OutputCountMismatch { tx_outputs, output_maps } => write!(
+                f,
+                "unsigned tx has {} outputs but PSBT has {} output maps",
+                tx_outputs, output_maps
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for FromV0Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use FromV0Error::*;
+
+        match *self {
+            InputCountMismatch { .. } | OutputCountMismatch { .. } => None,
+        }
+    }
+}
+
 // TODO: Consider creating a type that has input_index and E and simplify all these similar error types?
 /// Error checking the partials sigs have correct sighash types.
 #[derive(Debug)]
@@ -17,7 +17,7 @@ use core::fmt;
 
 use bitcoin::{FeeRate, Transaction, Txid};
 
-use crate::error::{write_err, FeeError};
+use crate::error::{fee_rate, write_err, FeeError};
 use crate::v2::{DetermineLockTimeError, Psbt};
 
 /// Implements the BIP-370 Finalized role.
@@ -92,8 +92,7 @@ impl Extractor {
         let tx = self.internal_extract_tx()?;
 
         // Now that the extracted Transaction is made, decide how to return it.
-        let fee_rate =
-            FeeRate::from_sat_per_kwu(fee.to_sat().saturating_mul(1000) / tx.weight().to_wu());
+        let fee_rate = fee_rate(fee, tx.weight());
         // Prefer to return an AbsurdFeeRate error when both trigger.
         if fee_rate > max_fee_rate {
             return Err(ExtractTxFeeRateError::FeeTooHigh { fee: fee_rate, max: max_fee_rate });
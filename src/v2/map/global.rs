@@ -6,29 +6,31 @@ use core::fmt;
 use bitcoin::bip32::{ChildNumber, DerivationPath, Fingerprint, KeySource, Xpub};
 use bitcoin::consensus::{encode as consensus, Decodable};
 use bitcoin::locktime::absolute;
-use bitcoin::{bip32, transaction, VarInt};
+use bitcoin::{bip32, secp256k1, transaction, Transaction, VarInt};
 
 use crate::consts::{
     PSBT_GLOBAL_FALLBACK_LOCKTIME, PSBT_GLOBAL_INPUT_COUNT, PSBT_GLOBAL_OUTPUT_COUNT,
-    PSBT_GLOBAL_PROPRIETARY, PSBT_GLOBAL_TX_MODIFIABLE, PSBT_GLOBAL_TX_VERSION,
-    PSBT_GLOBAL_UNSIGNED_TX, PSBT_GLOBAL_VERSION, PSBT_GLOBAL_XPUB,
+    PSBT_GLOBAL_PROPRIETARY, PSBT_GLOBAL_SP_DLEQ, PSBT_GLOBAL_SP_ECDH_SHARE,
+    PSBT_GLOBAL_TX_MODIFIABLE, PSBT_GLOBAL_TX_VERSION, PSBT_GLOBAL_UNSIGNED_TX,
+    PSBT_GLOBAL_VERSION, PSBT_GLOBAL_XPUB,
 };
 use crate::error::{write_err, InconsistentKeySourcesError};
 use crate::io::{self, Cursor, Read};
+use crate::io_ext::PairSink;
 use crate::prelude::*;
-use crate::serialize::Serialize;
+use crate::serialize::Deserialize;
 use crate::v2::map::Map;
 use crate::version::Version;
-use crate::{consts, raw, serialize, V2};
+use crate::{consts, raw, serialize, silent_payments, v0, V2};
 
 /// The Inputs Modifiable Flag, set to 1 to indicate whether inputs can be added or removed.
-const INPUTS_MODIFIABLE: u8 = 0x01 << 0;
+pub(crate) const INPUTS_MODIFIABLE: u8 = 0x01 << 0;
 /// The Outputs Modifiable Flag, set to 1 to indicate whether outputs can be added or removed.
-const OUTPUTS_MODIFIABLE: u8 = 0x01 << 1;
+pub(crate) const OUTPUTS_MODIFIABLE: u8 = 0x01 << 1;
 /// The Has SIGHASH_SINGLE flag, set to 1 to indicate whether the transaction has a SIGHASH_SINGLE
 /// signature who's input and output pairing must be preserved. Essentially indicates that the
 /// Constructor must iterate the inputs to determine whether and how to add or remove an input.
-const SIGHASH_SINGLE: u8 = 0x01 << 2;
+pub(crate) const SIGHASH_SINGLE: u8 = 0x01 << 2;
 
 /// The global key-value map.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -56,6 +58,14 @@ pub struct Global {
     /// A map from xpub to the used key fingerprint and derivation path as defined by BIP 32.
     pub xpubs: BTreeMap<Xpub, KeySource>,
 
+    /// Map of silent payment scan keys to the ECDH share covering all inputs.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::btreemap_as_seq"))]
+    pub sp_ecdh_shares: BTreeMap<secp256k1::PublicKey, secp256k1::PublicKey>,
+
+    /// Map of silent payment scan keys to the DLEQ proof for the global ECDH share.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::btreemap_as_seq"))]
+    pub sp_dleq_proofs: BTreeMap<secp256k1::PublicKey, silent_payments::DleqProof>,
+
     /// Global proprietary key-value pairs.
     #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::btreemap_as_seq_byte_values"))]
     pub proprietaries: BTreeMap<raw::ProprietaryKey, Vec<u8>>,
@@ -76,49 +86,124 @@ impl Global {
             input_count: 0,
             output_count: 0,
             xpubs: Default::default(),
+            sp_ecdh_shares: Default::default(),
+            sp_dleq_proofs: Default::default(),
             proprietaries: Default::default(),
             unknowns: Default::default(),
         }
     }
 
-    pub(crate) fn set_inputs_modifiable_flag(&mut self) {
-        self.tx_modifiable_flags |= INPUTS_MODIFIABLE;
-    }
+    /// Sets the inputs modifiable bit in the transaction modifiable flags.
+    pub fn set_inputs_modifiable_flag(&mut self) { self.tx_modifiable_flags |= INPUTS_MODIFIABLE; }
 
-    pub(crate) fn set_outputs_modifiable_flag(&mut self) {
+    /// Sets the outputs modifiable bit in the transaction modifiable flags.
+    pub fn set_outputs_modifiable_flag(&mut self) {
         self.tx_modifiable_flags |= OUTPUTS_MODIFIABLE;
     }
 
-    // TODO: Handle SIGHASH_SINGLE correctly.
-    #[allow(dead_code)]
-    pub(crate) fn set_sighash_single_flag(&mut self) { self.tx_modifiable_flags |= SIGHASH_SINGLE; }
+    /// Sets the has `SIGHASH_SINGLE` bit in the transaction modifiable flags.
+    pub fn set_sighash_single_flag(&mut self) { self.tx_modifiable_flags |= SIGHASH_SINGLE; }
 
-    pub(crate) fn clear_inputs_modifiable_flag(&mut self) {
+    /// Clears the inputs modifiable bit in the transaction modifiable flags.
+    pub fn clear_inputs_modifiable_flag(&mut self) {
         self.tx_modifiable_flags &= !INPUTS_MODIFIABLE;
     }
 
-    pub(crate) fn clear_outputs_modifiable_flag(&mut self) {
+    /// Clears the outputs modifiable bit in the transaction modifiable flags.
+    pub fn clear_outputs_modifiable_flag(&mut self) {
         self.tx_modifiable_flags &= !OUTPUTS_MODIFIABLE;
     }
 
-    // TODO: Handle SIGHASH_SINGLE correctly.
-    #[allow(dead_code)]
-    pub(crate) fn clear_sighash_single_flag(&mut self) {
-        self.tx_modifiable_flags &= !SIGHASH_SINGLE;
+    /// Clears the has `SIGHASH_SINGLE` bit in the transaction modifiable flags.
+    pub fn clear_sighash_single_flag(&mut self) { self.tx_modifiable_flags &= !SIGHASH_SINGLE; }
+
+    /// Returns true if the inputs modifiable bit is set.
+    pub fn is_inputs_modifiable(&self) -> bool { self.tx_modifiable_flags & INPUTS_MODIFIABLE > 0 }
+
+    /// Returns true if the outputs modifiable bit is set.
+    pub fn is_outputs_modifiable(&self) -> bool {
+        self.tx_modifiable_flags & OUTPUTS_MODIFIABLE > 0
     }
 
-    pub(crate) fn is_inputs_modifiable(&self) -> bool {
-        self.tx_modifiable_flags & INPUTS_MODIFIABLE > 0
+    /// Returns true if the has `SIGHASH_SINGLE` bit is set.
+    pub fn has_sighash_single(&self) -> bool { self.tx_modifiable_flags & SIGHASH_SINGLE > 0 }
+
+    /// Creates a `Global` map from the global fields of a `v0::Psbt`.
+    ///
+    /// The lock time of the unsigned transaction is used as the fallback lock time.
+    pub(crate) fn from_v0(
+        unsigned_tx: &Transaction,
+        xpubs: BTreeMap<Xpub, KeySource>,
+        proprietaries: BTreeMap<v0::bitcoin::raw::ProprietaryKey, Vec<u8>>,
+        unknowns: BTreeMap<v0::bitcoin::raw::Key, Vec<u8>>,
+        tx_modifiable_flags: u8,
+    ) -> Self {
+        let mut rv = Global {
+            version: V2,
+            tx_version: unsigned_tx.version,
+            fallback_lock_time: Some(unsigned_tx.lock_time),
+            tx_modifiable_flags,
+            input_count: unsigned_tx.input.len(),
+            output_count: unsigned_tx.output.len(),
+            xpubs,
+            sp_ecdh_shares: Default::default(),
+            sp_dleq_proofs: Default::default(),
+            proprietaries: proprietaries.into_iter().map(|(k, v)| (k.into(), v)).collect(),
+            unknowns: Default::default(),
+        };
+
+        // A v0 PSBT has no typed silent payment fields, they end up in the unknowns.
+        for (key, value) in unknowns {
+            let key = raw::Key::from(key);
+            let pair = raw::Pair { key: key.clone(), value: value.clone() };
+            let inserted = match key.type_value {
+                PSBT_GLOBAL_SP_ECDH_SHARE =>
+                    insert_keyed_pair(&mut rv.sp_ecdh_shares, pair).is_ok(),
+                PSBT_GLOBAL_SP_DLEQ => insert_keyed_pair(&mut rv.sp_dleq_proofs, pair).is_ok(),
+                _ => false,
+            };
+            if !inserted {
+                rv.unknowns.insert(key, value);
+            }
+        }
+        rv
     }
 
-    pub(crate) fn is_outputs_modifiable(&self) -> bool {
-        self.tx_modifiable_flags & OUTPUTS_MODIFIABLE > 0
+    /// Converts this `Global` map into a `v0::Psbt` using the already converted input and output maps.
+    ///
+    /// The caller is responsible for `unsigned_tx` being built from the v2 PSBT this `Global` map
+    /// belongs to (i.e., the transaction version, lock time, and input/output counts are all
+    /// represented by `unsigned_tx`).
+    pub(crate) fn into_v0(
+        self,
+        unsigned_tx: Transaction,
+        inputs: Vec<v0::Input>,
+        outputs: Vec<v0::Output>,
+    ) -> v0::Psbt {
+        // A v0 PSBT has no typed silent payment fields, keep them as unknowns so they are not lost.
+        let mut untyped: Vec<raw::Pair> = Vec::new();
+        self.push_silent_payment_pairs(&mut untyped);
+        let untyped = untyped.into_iter().map(|pair| (pair.key, pair.value));
+        v0::Psbt {
+            unsigned_tx,
+            version: 0,
+            xpub: self.xpubs,
+            proprietary: self.proprietaries.into_iter().map(|(k, v)| (k.into(), v)).collect(),
+            unknown: self.unknowns.into_iter().chain(untyped).map(|(k, v)| (k.into(), v)).collect(),
+            inputs,
+            outputs,
+        }
     }
 
-    // TODO: Investigate if we should be using this function?
-    #[allow(dead_code)]
-    pub(crate) fn has_sighash_single(&self) -> bool {
-        self.tx_modifiable_flags & SIGHASH_SINGLE > 0
+    /// Pushes the key-value pairs for the silent payment fields.
+    fn push_silent_payment_pairs<S: PairSink>(&self, pairs: &mut S) {
+        v2_impl_psbt_get_pair! {
+            pairs.push_map(self.sp_ecdh_shares, PSBT_GLOBAL_SP_ECDH_SHARE)
+        }
+
+        v2_impl_psbt_get_pair! {
+            pairs.push_map(self.sp_dleq_proofs, PSBT_GLOBAL_SP_DLEQ)
+        }
     }
 
     pub(crate) fn decode<R: io::Read + ?Sized>(r: &mut R) -> Result<Self, DecodeError> {
@@ -131,6 +216,10 @@ impl Global {
         let mut input_count: Option<u64> = None;
         let mut output_count: Option<u64> = None;
         let mut xpubs: BTreeMap<Xpub, (Fingerprint, DerivationPath)> = Default::default();
+        let mut sp_ecdh_shares: BTreeMap<secp256k1::PublicKey, secp256k1::PublicKey> =
+            Default::default();
+        let mut sp_dleq_proofs: BTreeMap<secp256k1::PublicKey, silent_payments::DleqProof> =
+            Default::default();
         let mut proprietaries: BTreeMap<raw::ProprietaryKey, Vec<u8>> = Default::default();
         let mut unknowns: BTreeMap<raw::Key, Vec<u8>> = Default::default();
 
@@ -191,7 +280,7 @@ impl Global {
                     },
                 PSBT_GLOBAL_INPUT_COUNT =>
                     if pair.key.key.is_empty() {
-                        if output_count.is_none() {
+                        if input_count.is_none() {
                             // TODO: Do we need to check the length for a VarInt?
                             // let vlen: usize = pair.value.len();
                             let mut decoder = Cursor::new(pair.value);
@@ -268,6 +357,8 @@ impl Global {
                     } else {
                         return Err(InsertPairError::InvalidKeyDataEmpty(pair.key));
                     },
+                PSBT_GLOBAL_SP_ECDH_SHARE => insert_keyed_pair(&mut sp_ecdh_shares, pair)?,
+                PSBT_GLOBAL_SP_DLEQ => insert_keyed_pair(&mut sp_dleq_proofs, pair)?,
                 // TODO: Remove clone by implementing TryFrom for reference.
                 PSBT_GLOBAL_PROPRIETARY =>
                     if !pair.key.key.is_empty() {
@@ -329,6 +420,8 @@ impl Global {
             tx_modifiable_flags,
             version,
             xpubs,
+            sp_ecdh_shares,
+            sp_dleq_proofs,
             proprietaries,
             unknowns,
         })
@@ -389,6 +482,8 @@ impl Global {
             }
         }
 
+        v2_combine_map!(sp_ecdh_shares, self, other);
+        v2_combine_map!(sp_dleq_proofs, self, other);
         v2_combine_map!(proprietaries, self, other);
         v2_combine_map!(unknowns, self, other);
 
@@ -401,60 +496,54 @@ impl Default for Global {
 }
 
 impl Map for Global {
-    fn get_pairs(&self) -> Vec<raw::Pair> {
-        let mut rv: Vec<raw::Pair> = Default::default();
-
-        rv.push(raw::Pair {
-            key: raw::Key { type_value: PSBT_GLOBAL_VERSION, key: vec![] },
-            value: self.version.serialize(),
-        });
+    fn push_pairs<S: PairSink>(&self, pairs: &mut S) {
+        pairs.push_pair(PSBT_GLOBAL_VERSION, &[], &self.version);
 
-        rv.push(raw::Pair {
-            key: raw::Key { type_value: PSBT_GLOBAL_TX_VERSION, key: vec![] },
-            value: self.tx_version.serialize(),
-        });
+        pairs.push_pair(PSBT_GLOBAL_TX_VERSION, &[], &self.tx_version);
 
         v2_impl_psbt_get_pair! {
-            rv.push(self.fallback_lock_time, PSBT_GLOBAL_FALLBACK_LOCKTIME)
+            pairs.push(self.fallback_lock_time, PSBT_GLOBAL_FALLBACK_LOCKTIME)
         }
 
-        rv.push(raw::Pair {
-            key: raw::Key { type_value: PSBT_GLOBAL_INPUT_COUNT, key: vec![] },
-            value: VarInt::from(self.input_count).serialize(),
-        });
-
-        rv.push(raw::Pair {
-            key: raw::Key { type_value: PSBT_GLOBAL_OUTPUT_COUNT, key: vec![] },
-            value: VarInt::from(self.output_count).serialize(),
-        });
-
-        rv.push(raw::Pair {
-            key: raw::Key { type_value: PSBT_GLOBAL_TX_MODIFIABLE, key: vec![] },
-            value: vec![self.tx_modifiable_flags],
-        });
-
-        for (xpub, (fingerprint, derivation)) in &self.xpubs {
-            rv.push(raw::Pair {
-                key: raw::Key { type_value: PSBT_GLOBAL_XPUB, key: xpub.encode().to_vec() },
-                value: {
-                    let mut ret = Vec::with_capacity(4 + derivation.len() * 4);
-                    ret.extend(fingerprint.as_bytes());
-                    derivation.into_iter().for_each(|n| ret.extend(&u32::from(*n).to_le_bytes()));
-                    ret
-                },
-            });
+        pairs.push_pair(PSBT_GLOBAL_INPUT_COUNT, &[], &VarInt::from(self.input_count));
+
+        pairs.push_pair(PSBT_GLOBAL_OUTPUT_COUNT, &[], &VarInt::from(self.output_count));
+
+        pairs.push_pair(PSBT_GLOBAL_TX_MODIFIABLE, &[], &[self.tx_modifiable_flags]);
+
+        for (xpub, key_source) in &self.xpubs {
+            pairs.push_pair(PSBT_GLOBAL_XPUB, &xpub.encode(), key_source);
         }
 
+        self.push_silent_payment_pairs(pairs);
+
         for (key, value) in self.proprietaries.iter() {
-            rv.push(raw::Pair { key: key.to_key(), value: value.clone() });
+            pairs.push_pair(0xFC, key, value);
         }
 
         for (key, value) in self.unknowns.iter() {
-            rv.push(raw::Pair { key: key.clone(), value: value.clone() });
+            pairs.push_pair(key.type_value, &key.key, value);
         }
+    }
+}
 
-        rv
+/// Inserts a pair with non-empty key data into `map`, deserializing both the key and the value.
+fn insert_keyed_pair<K, V>(map: &mut BTreeMap<K, V>, pair: raw::Pair) -> Result<(), InsertPairError>
+where
+    K: Deserialize + Ord,
+    V: Deserialize,
+{
+    if pair.key.key.is_empty() {
+        return Err(InsertPairError::InvalidKeyDataEmpty(pair.key));
+    }
+    let key = K::deserialize(&pair.key.key)?;
+    match map.entry(key) {
+        btree_map::Entry::Vacant(empty_key) => {
+            empty_key.insert(V::deserialize(&pair.value)?);
+        }
+        btree_map::Entry::Occupied(_) => return Err(InsertPairError::DuplicateKey(pair.key)),
     }
+    Ok(())
 }
 
 /// An error while decoding.
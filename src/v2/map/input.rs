@@ -4,7 +4,7 @@ use core::convert::TryFrom;
 use core::fmt;
 
 use bitcoin::bip32::KeySource;
-use bitcoin::hashes::{hash160, ripemd160, sha256, sha256d, Hash as _};
+use bitcoin::hashes::{hash160, ripemd160, sha256, sha256d};
 use bitcoin::hex::DisplayHex;
 use bitcoin::key::{PublicKey, XOnlyPublicKey};
 use bitcoin::locktime::absolute;
@@ -17,19 +17,22 @@ use bitcoin::{
 
 use crate::consts::{
     PSBT_IN_BIP32_DERIVATION, PSBT_IN_FINAL_SCRIPTSIG, PSBT_IN_FINAL_SCRIPTWITNESS,
-    PSBT_IN_HASH160, PSBT_IN_HASH256, PSBT_IN_NON_WITNESS_UTXO, PSBT_IN_OUTPUT_INDEX,
-    PSBT_IN_PARTIAL_SIG, PSBT_IN_PREVIOUS_TXID, PSBT_IN_PROPRIETARY, PSBT_IN_REDEEM_SCRIPT,
-    PSBT_IN_REQUIRED_HEIGHT_LOCKTIME, PSBT_IN_REQUIRED_TIME_LOCKTIME, PSBT_IN_RIPEMD160,
-    PSBT_IN_SEQUENCE, PSBT_IN_SHA256, PSBT_IN_SIGHASH_TYPE, PSBT_IN_TAP_BIP32_DERIVATION,
-    PSBT_IN_TAP_INTERNAL_KEY, PSBT_IN_TAP_KEY_SIG, PSBT_IN_TAP_LEAF_SCRIPT,
-    PSBT_IN_TAP_MERKLE_ROOT, PSBT_IN_TAP_SCRIPT_SIG, PSBT_IN_WITNESS_SCRIPT, PSBT_IN_WITNESS_UTXO,
+    PSBT_IN_HASH160, PSBT_IN_HASH256, PSBT_IN_MUSIG2_PARTIAL_SIG,
+    PSBT_IN_MUSIG2_PARTICIPANT_PUBKEYS, PSBT_IN_MUSIG2_PUB_NONCE, PSBT_IN_NON_WITNESS_UTXO,
+    PSBT_IN_OUTPUT_INDEX, PSBT_IN_PARTIAL_SIG, PSBT_IN_PREVIOUS_TXID, PSBT_IN_PROPRIETARY,
+    PSBT_IN_REDEEM_SCRIPT, PSBT_IN_REQUIRED_HEIGHT_LOCKTIME, PSBT_IN_REQUIRED_TIME_LOCKTIME,
+    PSBT_IN_RIPEMD160, PSBT_IN_SEQUENCE, PSBT_IN_SHA256, PSBT_IN_SIGHASH_TYPE, PSBT_IN_SP_DLEQ,
+    PSBT_IN_SP_ECDH_SHARE, PSBT_IN_TAP_BIP32_DERIVATION, PSBT_IN_TAP_INTERNAL_KEY,
+    PSBT_IN_TAP_KEY_SIG, PSBT_IN_TAP_LEAF_SCRIPT, PSBT_IN_TAP_MERKLE_ROOT, PSBT_IN_TAP_SCRIPT_SIG,
+    PSBT_IN_WITNESS_SCRIPT, PSBT_IN_WITNESS_UTXO,
 };
 use crate::error::{write_err, FundingUtxoError};
+use crate::io_ext::PairSink;
 use crate::prelude::*;
-use crate::serialize::{Deserialize, Serialize};
+use crate::serialize::Deserialize;
 use crate::sighash_type::{InvalidSighashTypeError, PsbtSighashType};
-use crate::v2::map::Map;
-use crate::{io, raw, serialize};
+use crate::v2::map::{insert_required_pair, InsertPairErrorExt, Map};
+use crate::{io, musig2, raw, serialize, silent_payments, v0};
 
 /// A key-value map for an input of the corresponding index in the unsigned
 /// transaction.
@@ -114,6 +117,27 @@ pub struct Input {
     pub tap_internal_key: Option<XOnlyPublicKey>,
     /// Taproot Merkle root.
     pub tap_merkle_root: Option<TapNodeHash>,
+    /// Map of MuSig2 aggregate keys to the participant keys, in the order used for aggregation.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::btreemap_as_seq"))]
+    pub musig2_participant_pubkeys: BTreeMap<secp256k1::PublicKey, Vec<secp256k1::PublicKey>>,
+    /// Map of `<participant key>|<aggregate key>|<leaf hash>` with MuSig2 public nonce.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::btreemap_as_seq"))]
+    pub musig2_pub_nonces: BTreeMap<
+        (secp256k1::PublicKey, secp256k1::PublicKey, Option<TapLeafHash>),
+        musig2::PublicNonce,
+    >,
+    /// Map of `<participant key>|<aggregate key>|<leaf hash>` with MuSig2 partial signature.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::btreemap_as_seq"))]
+    pub musig2_partial_sigs: BTreeMap<
+        (secp256k1::PublicKey, secp256k1::PublicKey, Option<TapLeafHash>),
+        musig2::PartialSignature,
+    >,
+    /// Map of silent payment scan keys to the ECDH share of this input.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::btreemap_as_seq"))]
+    pub sp_ecdh_shares: BTreeMap<secp256k1::PublicKey, secp256k1::PublicKey>,
+    /// Map of silent payment scan keys to the DLEQ proof for the ECDH share of this input.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::btreemap_as_seq"))]
+    pub sp_dleq_proofs: BTreeMap<secp256k1::PublicKey, silent_payments::DleqProof>,
     /// Proprietary key-value pairs for this input.
     #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::btreemap_as_seq_byte_values"))]
     pub proprietaries: BTreeMap<raw::ProprietaryKey, Vec<u8>>,
@@ -150,37 +174,100 @@ impl Input {
             tap_key_origins: BTreeMap::new(),
             tap_internal_key: None,
             tap_merkle_root: None,
+            musig2_participant_pubkeys: BTreeMap::new(),
+            musig2_pub_nonces: BTreeMap::new(),
+            musig2_partial_sigs: BTreeMap::new(),
+            sp_ecdh_shares: BTreeMap::new(),
+            sp_dleq_proofs: BTreeMap::new(),
             proprietaries: BTreeMap::new(),
             unknowns: BTreeMap::new(),
         }
     }
 
-    // /// Converts this `Input` to a `v0::Input`.
-    // pub(crate) fn into_v0(self) -> v0::Input {
-    //     v0::Input {
-    //         non_witness_utxo: self.non_witness_utxo,
-    //         witness_utxo: self.witness_utxo,
-    //         partial_sigs: self.partial_sigs,
-    //         sighash_type: self.sighash_type,
-    //         redeem_script: self.redeem_script,
-    //         witness_script: self.witness_script,
-    //         bip32_derivation: self.bip32_derivations,
-    //         final_script_sig: self.final_script_sig,
-    //         final_script_witness: self.final_script_witness,
-    //         ripemd160_preimages: self.ripemd160_preimages,
-    //         sha256_preimages: self.sha256_preimages,
-    //         hash160_preimages: self.hash160_preimages,
-    //         hash256_preimages: self.hash256_preimages,
-    //         tap_key_sig: self.tap_key_sig,
-    //         tap_script_sigs: self.tap_script_sigs,
-    //         tap_scripts: self.tap_scripts,
-    //         tap_key_origins: self.tap_key_origins,
-    //         tap_internal_key: self.tap_internal_key,
-    //         tap_merkle_root: self.tap_merkle_root,
-    //         proprietary: self.proprietaries,
-    //         unknown: self.unknowns,
-    //     }
-    // }
+    /// Creates an `Input` from a `v0::Input` and the associated input of the unsigned transaction.
+    pub(crate) fn from_v0(input: v0::Input, tx_in: &TxIn) -> Self {
+        let mut rv = Input {
+            previous_txid: tx_in.previous_output.txid,
+            spent_output_index: tx_in.previous_output.vout,
+            sequence: Some(tx_in.sequence),
+            min_time: None,
+            min_height: None,
+            non_witness_utxo: input.non_witness_utxo,
+            witness_utxo: input.witness_utxo,
+            partial_sigs: input.partial_sigs,
+            sighash_type: input.sighash_type,
+            redeem_script: input.redeem_script,
+            witness_script: input.witness_script,
+            bip32_derivations: input.bip32_derivation,
+            final_script_sig: input.final_script_sig,
+            final_script_witness: input.final_script_witness,
+            ripemd160_preimages: input.ripemd160_preimages,
+            sha256_preimages: input.sha256_preimages,
+            hash160_preimages: input.hash160_preimages,
+            hash256_preimages: input.hash256_preimages,
+            tap_key_sig: input.tap_key_sig,
+            tap_script_sigs: input.tap_script_sigs,
+            tap_scripts: input.tap_scripts,
+            tap_key_origins: input.tap_key_origins,
+            tap_internal_key: input.tap_internal_key,
+            tap_merkle_root: input.tap_merkle_root,
+            musig2_participant_pubkeys: BTreeMap::new(),
+            musig2_pub_nonces: BTreeMap::new(),
+            musig2_partial_sigs: BTreeMap::new(),
+            sp_ecdh_shares: BTreeMap::new(),
+            sp_dleq_proofs: BTreeMap::new(),
+            proprietaries: input.proprietary.into_iter().map(|(k, v)| (k.into(), v)).collect(),
+            unknowns: BTreeMap::new(),
+        };
+
+        // A v0 input has no typed MuSig2 or silent payment fields, they end up in the unknowns.
+        for (key, value) in input.unknown {
+            let key = raw::Key::from(key);
+            if is_v0_untyped_key_type(key.type_value)
+                && rv.insert_pair(raw::Pair { key: key.clone(), value: value.clone() }).is_ok()
+            {
+                continue;
+            }
+            rv.unknowns.insert(key, value);
+        }
+        rv
+    }
+
+    /// Converts this `Input` to a `v0::Input`.
+    ///
+    /// The outpoint and sequence number are not part of a v0 input map, use
+    /// [`Self::unsigned_tx_in`] to get them before calling this function. The required lock time
+    /// fields are dropped, in a v0 PSBT they are expressed by the lock time of the unsigned tx.
+    pub(crate) fn into_v0(self) -> v0::Input {
+        // A v0 input has no typed MuSig2 or silent payment fields, keep them as unknowns so they
+        // are not lost.
+        let mut untyped: Vec<raw::Pair> = Vec::new();
+        self.push_v0_untyped_pairs(&mut untyped);
+        let untyped = untyped.into_iter().map(|pair| (pair.key, pair.value));
+        v0::Input {
+            non_witness_utxo: self.non_witness_utxo,
+            witness_utxo: self.witness_utxo,
+            partial_sigs: self.partial_sigs,
+            sighash_type: self.sighash_type,
+            redeem_script: self.redeem_script,
+            witness_script: self.witness_script,
+            bip32_derivation: self.bip32_derivations,
+            final_script_sig: self.final_script_sig,
+            final_script_witness: self.final_script_witness,
+            ripemd160_preimages: self.ripemd160_preimages,
+            sha256_preimages: self.sha256_preimages,
+            hash160_preimages: self.hash160_preimages,
+            hash256_preimages: self.hash256_preimages,
+            tap_key_sig: self.tap_key_sig,
+            tap_script_sigs: self.tap_script_sigs,
+            tap_scripts: self.tap_scripts,
+            tap_key_origins: self.tap_key_origins,
+            tap_internal_key: self.tap_internal_key,
+            tap_merkle_root: self.tap_merkle_root,
+            proprietary: self.proprietaries.into_iter().map(|(k, v)| (k.into(), v)).collect(),
+            unknown: self.unknowns.into_iter().chain(untyped).map(|(k, v)| (k.into(), v)).collect(),
+        }
+    }
 
     /// Creates a new finalized input.
     ///
@@ -206,10 +293,12 @@ impl Input {
             final_script_sig: None,
             final_script_witness: None,
 
+            // These are part of the unsigned transaction, clearing them would change the txid.
+            sequence: self.sequence,
+            min_time: self.min_time,
+            min_height: self.min_height,
+
             // Clear everything else.
-            sequence: None,
-            min_time: None,
-            min_height: None,
             partial_sigs: BTreeMap::new(),
             sighash_type: None,
             redeem_script: None,
@@ -225,6 +314,11 @@ impl Input {
             tap_key_origins: BTreeMap::new(),
             tap_internal_key: None,
             tap_merkle_root: None,
+            musig2_participant_pubkeys: BTreeMap::new(),
+            musig2_pub_nonces: BTreeMap::new(),
+            musig2_partial_sigs: BTreeMap::new(),
+            sp_ecdh_shares: BTreeMap::new(),
+            sp_dleq_proofs: BTreeMap::new(),
             proprietaries: BTreeMap::new(),
             unknowns: BTreeMap::new(),
         };
@@ -240,6 +334,8 @@ impl Input {
         } else {
             // TODO: Any checks should do here?
             ret.final_script_sig = Some(final_script_sig);
+            // An empty witness marks the input as finalized, see `Self::is_finalized`.
+            ret.final_script_witness = Some(Witness::default());
         }
 
         Ok(ret)
@@ -331,15 +427,58 @@ impl Input {
         self.final_script_sig.is_some() && self.final_script_witness.is_some()
     }
 
-    /// TODO: Use this.
-    #[allow(dead_code)]
-    fn has_sig_data(&self) -> bool {
+    /// Returns true if this input has any signatures (ECDSA, Taproot key path or script path, or
+    /// MuSig2 partial signatures).
+    pub fn has_sig_data(&self) -> bool {
         !(self.partial_sigs.is_empty()
             && self.tap_key_sig.is_none()
-            && self.tap_script_sigs.is_empty())
+            && self.tap_script_sigs.is_empty()
+            && self.musig2_partial_sigs.is_empty())
     }
 
-    fn out_point(&self) -> OutPoint {
+    /// Removes all signatures, MuSig2 nonces, and final scripts from this input.
+    ///
+    /// Used when the transaction changes and the input needs to be signed again.
+    pub(crate) fn clear_sig_data(&mut self) {
+        self.partial_sigs.clear();
+        self.tap_key_sig = None;
+        self.tap_script_sigs.clear();
+        self.musig2_pub_nonces.clear();
+        self.musig2_partial_sigs.clear();
+        self.final_script_sig = None;
+        self.final_script_witness = None;
+    }
+
+    /// Returns the sighash types of the ECDSA and Taproot signatures in this input.
+    pub(crate) fn sighash_types(&self) -> impl Iterator<Item = u8> + '_ {
+        let ecdsa = self.partial_sigs.values().map(|sig| sig.hash_ty as u8);
+        let taproot = self
+            .tap_key_sig
+            .iter()
+            .chain(self.tap_script_sigs.values())
+            .map(|sig| sig.hash_ty as u8);
+        ecdsa.chain(taproot)
+    }
+
+    /// Returns true if this input has a signature that uses `SIGHASH_SINGLE`.
+    pub(crate) fn has_sighash_single_sig(&self) -> bool {
+        use EcdsaSighashType as Ecdsa;
+        use TapSighashType as Tap;
+
+        let ecdsa = self
+            .partial_sigs
+            .values()
+            .any(|sig| matches!(sig.hash_ty, Ecdsa::Single | Ecdsa::SinglePlusAnyoneCanPay));
+        let taproot = self
+            .tap_key_sig
+            .iter()
+            .chain(self.tap_script_sigs.values())
+            .any(|sig| matches!(sig.hash_ty, Tap::Single | Tap::SinglePlusAnyoneCanPay));
+
+        ecdsa || taproot
+    }
+
+    pub(crate) fn out_point(&self) -> OutPoint {
         OutPoint { txid: self.previous_txid, vout: self.spent_output_index }
     }
 
@@ -367,25 +506,52 @@ impl Input {
             .unwrap_or(Ok(TapSighashType::Default))
     }
 
+    /// Pushes the key-value pairs for the fields of this input that are not typed in a v0 input
+    /// (i.e., the MuSig2 and silent payment fields).
+    fn push_v0_untyped_pairs<S: PairSink>(&self, pairs: &mut S) {
+        v2_impl_psbt_get_pair! {
+            pairs.push_map(self.musig2_participant_pubkeys, PSBT_IN_MUSIG2_PARTICIPANT_PUBKEYS)
+        }
+
+        v2_impl_psbt_get_pair! {
+            pairs.push_map(self.musig2_pub_nonces, PSBT_IN_MUSIG2_PUB_NONCE)
+        }
+
+        v2_impl_psbt_get_pair! {
+            pairs.push_map(self.musig2_partial_sigs, PSBT_IN_MUSIG2_PARTIAL_SIG)
+        }
+
+        v2_impl_psbt_get_pair! {
+            pairs.push_map(self.sp_ecdh_shares, PSBT_IN_SP_ECDH_SHARE)
+        }
+
+        v2_impl_psbt_get_pair! {
+            pairs.push_map(self.sp_dleq_proofs, PSBT_IN_SP_DLEQ)
+        }
+    }
+
     pub(in crate::v2) fn decode<R: io::Read + ?Sized>(r: &mut R) -> Result<Self, DecodeError> {
-        // These are placeholder values that never exist in a encode `Input`.
-        let invalid = OutPoint { txid: Txid::all_zeros(), vout: u32::MAX };
-        let mut rv = Self::new(&invalid);
+        // The required fields are tracked separately so that every value can be decoded.
+        let mut previous_txid: Option<Txid> = None;
+        let mut spent_output_index: Option<u32> = None;
+        let mut rv = Self::new(&OutPoint::null());
 
         loop {
             match raw::Pair::decode(r) {
-                Ok(pair) => rv.insert_pair(pair)?,
+                Ok(pair) => match pair.key.type_value {
+                    PSBT_IN_PREVIOUS_TXID =>
+                        insert_required_pair::<_, InsertPairError>(&mut previous_txid, pair)?,
+                    PSBT_IN_OUTPUT_INDEX =>
+                        insert_required_pair::<_, InsertPairError>(&mut spent_output_index, pair)?,
+                    _ => rv.insert_pair(pair)?,
+                },
                 Err(serialize::Error::NoMorePairs) => break,
                 Err(e) => return Err(DecodeError::DeserPair(e)),
             }
         }
 
-        if rv.previous_txid == Txid::all_zeros() {
-            return Err(DecodeError::MissingPreviousTxid);
-        }
-        if rv.spent_output_index == u32::MAX {
-            return Err(DecodeError::MissingSpentOutputIndex);
-        }
+        rv.previous_txid = previous_txid.ok_or(DecodeError::MissingPreviousTxid)?;
+        rv.spent_output_index = spent_output_index.ok_or(DecodeError::MissingSpentOutputIndex)?;
         Ok(rv)
     }
 
@@ -393,20 +559,6 @@ impl Input {
         let raw::Pair { key: raw_key, value: raw_value } = pair;
 
         match raw_key.type_value {
-            PSBT_IN_PREVIOUS_TXID => {
-                if self.previous_txid != Txid::all_zeros() {
-                    return Err(InsertPairError::DuplicateKey(raw_key));
-                }
-                let txid: Txid = Deserialize::deserialize(&raw_value)?;
-                self.previous_txid = txid;
-            }
-            PSBT_IN_OUTPUT_INDEX => {
-                if self.spent_output_index != u32::MAX {
-                    return Err(InsertPairError::DuplicateKey(raw_key));
-                }
-                let vout: u32 = Deserialize::deserialize(&raw_value)?;
-                self.spent_output_index = vout;
-            }
             PSBT_IN_SEQUENCE => {
                 v2_impl_psbt_insert_pair! {
                     self.sequence <= <raw_key: _>|<raw_value: Sequence>
@@ -422,6 +574,11 @@ impl Input {
                     self.min_height <= <raw_key: _>|<raw_value: absolute::Height>
                 }
             }
+            PSBT_IN_NON_WITNESS_UTXO => {
+                v2_impl_psbt_insert_pair! {
+                    self.non_witness_utxo <= <raw_key: _>|<raw_value: Transaction>
+                }
+            }
             PSBT_IN_WITNESS_UTXO => {
                 v2_impl_psbt_insert_pair! {
                     self.witness_utxo <= <raw_key: _>|<raw_value: TxOut>
@@ -524,6 +681,31 @@ impl Input {
                     self.tap_merkle_root <= <raw_key: _>|< raw_value: TapNodeHash>
                 }
             }
+            PSBT_IN_MUSIG2_PARTICIPANT_PUBKEYS => {
+                v2_impl_psbt_insert_pair! {
+                    self.musig2_participant_pubkeys <= <raw_key: secp256k1::PublicKey>|<raw_value: Vec<secp256k1::PublicKey>>
+                }
+            }
+            PSBT_IN_MUSIG2_PUB_NONCE => {
+                v2_impl_psbt_insert_pair! {
+                    self.musig2_pub_nonces <= <raw_key: (secp256k1::PublicKey, secp256k1::PublicKey, Option<TapLeafHash>)>|<raw_value: musig2::PublicNonce>
+                }
+            }
+            PSBT_IN_MUSIG2_PARTIAL_SIG => {
+                v2_impl_psbt_insert_pair! {
+                    self.musig2_partial_sigs <= <raw_key: (secp256k1::PublicKey, secp256k1::PublicKey, Option<TapLeafHash>)>|<raw_value: musig2::PartialSignature>
+                }
+            }
+            PSBT_IN_SP_ECDH_SHARE => {
+                v2_impl_psbt_insert_pair! {
+                    self.sp_ecdh_shares <= <raw_key: secp256k1::PublicKey>|<raw_value: secp256k1::PublicKey>
+                }
+            }
+            PSBT_IN_SP_DLEQ => {
+                v2_impl_psbt_insert_pair! {
+                    self.sp_dleq_proofs <= <raw_key: secp256k1::PublicKey>|<raw_value: silent_payments::DleqProof>
+                }
+            }
             PSBT_IN_PROPRIETARY => {
                 let key = raw::ProprietaryKey::try_from(raw_key.clone())?;
                 match self.proprietaries.entry(key) {
@@ -592,6 +774,11 @@ impl Input {
         v2_combine_map!(tap_key_origins, self, other);
         v2_combine_option!(tap_internal_key, self, other);
         v2_combine_option!(tap_merkle_root, self, other);
+        v2_combine_map!(musig2_participant_pubkeys, self, other);
+        v2_combine_map!(musig2_pub_nonces, self, other);
+        v2_combine_map!(musig2_partial_sigs, self, other);
+        v2_combine_map!(sp_ecdh_shares, self, other);
+        v2_combine_map!(sp_dleq_proofs, self, other);
         v2_combine_map!(proprietaries, self, other);
         v2_combine_map!(unknowns, self, other);
 
@@ -600,116 +787,120 @@ impl Input {
 }
 
 impl Map for Input {
-    fn get_pairs(&self) -> Vec<raw::Pair> {
-        let mut rv: Vec<raw::Pair> = Default::default();
-
-        rv.push(raw::Pair {
-            key: raw::Key { type_value: PSBT_IN_PREVIOUS_TXID, key: vec![] },
-            value: self.previous_txid.serialize(),
-        });
+    fn push_pairs<S: PairSink>(&self, pairs: &mut S) {
+        pairs.push_pair(PSBT_IN_PREVIOUS_TXID, &[], &self.previous_txid);
 
-        rv.push(raw::Pair {
-            key: raw::Key { type_value: PSBT_IN_OUTPUT_INDEX, key: vec![] },
-            value: self.spent_output_index.serialize(),
-        });
+        pairs.push_pair(PSBT_IN_OUTPUT_INDEX, &[], &self.spent_output_index);
 
         v2_impl_psbt_get_pair! {
-            rv.push(self.sequence, PSBT_IN_SEQUENCE)
+            pairs.push(self.sequence, PSBT_IN_SEQUENCE)
         }
         v2_impl_psbt_get_pair! {
-            rv.push(self.min_time, PSBT_IN_REQUIRED_TIME_LOCKTIME)
+            pairs.push(self.min_time, PSBT_IN_REQUIRED_TIME_LOCKTIME)
         }
         v2_impl_psbt_get_pair! {
-            rv.push(self.min_height, PSBT_IN_REQUIRED_HEIGHT_LOCKTIME)
+            pairs.push(self.min_height, PSBT_IN_REQUIRED_HEIGHT_LOCKTIME)
         }
 
         v2_impl_psbt_get_pair! {
-            rv.push(self.non_witness_utxo, PSBT_IN_NON_WITNESS_UTXO)
+            pairs.push(self.non_witness_utxo, PSBT_IN_NON_WITNESS_UTXO)
         }
 
         v2_impl_psbt_get_pair! {
-            rv.push(self.witness_utxo, PSBT_IN_WITNESS_UTXO)
+            pairs.push(self.witness_utxo, PSBT_IN_WITNESS_UTXO)
         }
 
         v2_impl_psbt_get_pair! {
-            rv.push_map(self.partial_sigs, PSBT_IN_PARTIAL_SIG)
+            pairs.push_map(self.partial_sigs, PSBT_IN_PARTIAL_SIG)
         }
 
         v2_impl_psbt_get_pair! {
-            rv.push(self.sighash_type, PSBT_IN_SIGHASH_TYPE)
+            pairs.push(self.sighash_type, PSBT_IN_SIGHASH_TYPE)
         }
 
         v2_impl_psbt_get_pair! {
-            rv.push(self.redeem_script, PSBT_IN_REDEEM_SCRIPT)
+            pairs.push(self.redeem_script, PSBT_IN_REDEEM_SCRIPT)
         }
 
         v2_impl_psbt_get_pair! {
-            rv.push(self.witness_script, PSBT_IN_WITNESS_SCRIPT)
+            pairs.push(self.witness_script, PSBT_IN_WITNESS_SCRIPT)
         }
 
         v2_impl_psbt_get_pair! {
-            rv.push_map(self.bip32_derivations, PSBT_IN_BIP32_DERIVATION)
+            pairs.push_map(self.bip32_derivations, PSBT_IN_BIP32_DERIVATION)
         }
 
         v2_impl_psbt_get_pair! {
-            rv.push(self.final_script_sig, PSBT_IN_FINAL_SCRIPTSIG)
+            pairs.push(self.final_script_sig, PSBT_IN_FINAL_SCRIPTSIG)
         }
 
         v2_impl_psbt_get_pair! {
-            rv.push(self.final_script_witness, PSBT_IN_FINAL_SCRIPTWITNESS)
+            pairs.push(self.final_script_witness, PSBT_IN_FINAL_SCRIPTWITNESS)
         }
 
         v2_impl_psbt_get_pair! {
-            rv.push_map(self.ripemd160_preimages, PSBT_IN_RIPEMD160)
+            pairs.push_map(self.ripemd160_preimages, PSBT_IN_RIPEMD160)
         }
 
         v2_impl_psbt_get_pair! {
-            rv.push_map(self.sha256_preimages, PSBT_IN_SHA256)
+            pairs.push_map(self.sha256_preimages, PSBT_IN_SHA256)
         }
 
         v2_impl_psbt_get_pair! {
-            rv.push_map(self.hash160_preimages, PSBT_IN_HASH160)
+            pairs.push_map(self.hash160_preimages, PSBT_IN_HASH160)
         }
 
         v2_impl_psbt_get_pair! {
-            rv.push_map(self.hash256_preimages, PSBT_IN_HASH256)
+            pairs.push_map(self.hash256_preimages, PSBT_IN_HASH256)
         }
 
         v2_impl_psbt_get_pair! {
-            rv.push(self.tap_key_sig, PSBT_IN_TAP_KEY_SIG)
+            pairs.push(self.tap_key_sig, PSBT_IN_TAP_KEY_SIG)
         }
 
         v2_impl_psbt_get_pair! {
-            rv.push_map(self.tap_script_sigs, PSBT_IN_TAP_SCRIPT_SIG)
+            pairs.push_map(self.tap_script_sigs, PSBT_IN_TAP_SCRIPT_SIG)
         }
 
         v2_impl_psbt_get_pair! {
-            rv.push_map(self.tap_scripts, PSBT_IN_TAP_LEAF_SCRIPT)
+            pairs.push_map(self.tap_scripts, PSBT_IN_TAP_LEAF_SCRIPT)
         }
 
         v2_impl_psbt_get_pair! {
-            rv.push_map(self.tap_key_origins, PSBT_IN_TAP_BIP32_DERIVATION)
+            pairs.push_map(self.tap_key_origins, PSBT_IN_TAP_BIP32_DERIVATION)
         }
 
         v2_impl_psbt_get_pair! {
-            rv.push(self.tap_internal_key, PSBT_IN_TAP_INTERNAL_KEY)
+            pairs.push(self.tap_internal_key, PSBT_IN_TAP_INTERNAL_KEY)
         }
 
         v2_impl_psbt_get_pair! {
-            rv.push(self.tap_merkle_root, PSBT_IN_TAP_MERKLE_ROOT)
+            pairs.push(self.tap_merkle_root, PSBT_IN_TAP_MERKLE_ROOT)
         }
+
+        self.push_v0_untyped_pairs(pairs);
         for (key, value) in self.proprietaries.iter() {
-            rv.push(raw::Pair { key: key.to_key(), value: value.clone() });
+            pairs.push_pair(0xFC, key, value);
         }
 
         for (key, value) in self.unknowns.iter() {
-            rv.push(raw::Pair { key: key.clone(), value: value.clone() });
+            pairs.push_pair(key.type_value, &key.key, value);
         }
-
-        rv
     }
 }
 
+/// Returns true if `type_value` is the key type of a field that is not typed in a v0 input.
+fn is_v0_untyped_key_type(type_value: u8) -> bool {
+    matches!(
+        type_value,
+        PSBT_IN_MUSIG2_PARTICIPANT_PUBKEYS
+            | PSBT_IN_MUSIG2_PUB_NONCE
+            | PSBT_IN_MUSIG2_PARTIAL_SIG
+            | PSBT_IN_SP_ECDH_SHARE
+            | PSBT_IN_SP_DLEQ
+    )
+}
+
 // TODO: This is an exact duplicate of that in v0.
 fn psbt_insert_hash_pair<H>(
     map: &mut BTreeMap<H, Vec<u8>>,
@@ -873,6 +1064,12 @@ impl From<serialize::Error> for InsertPairError {
     fn from(e: serialize::Error) -> Self { Self::Deser(e) }
 }
 
+impl InsertPairErrorExt for InsertPairError {
+    fn duplicate_key(key: raw::Key) -> Self { Self::DuplicateKey(key) }
+
+    fn invalid_key_data_not_empty(key: raw::Key) -> Self { Self::InvalidKeyDataNotEmpty(key) }
+}
+
 impl From<HashPreimageError> for InsertPairError {
     fn from(e: HashPreimageError) -> Self { Self::HashPreimage(e) }
 }
@@ -998,6 +1195,8 @@ impl std::error::Error for CombineError {
 
 #[cfg(test)]
 mod test {
+    use bitcoin::hashes::Hash as _;
+
     use super::*;
 
     #[cfg(feature = "std")]
@@ -1012,7 +1211,28 @@ mod test {
     fn serialize_roundtrip() {
         let input = Input::new(&out_point());
 
-        let ser = input.serialize_map();
+        let mut ser = Vec::new();
+        input.encode_map(&mut ser).expect("in-memory writers don't error");
+        let mut d = std::io::Cursor::new(ser);
+
+        let decoded = Input::decode(&mut d).expect("failed to decode");
+
+        assert_eq!(decoded, input);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn serialize_roundtrip_non_witness_utxo() {
+        let tx = Transaction {
+            version: bitcoin::transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![TxIn::default()],
+            output: vec![TxOut::NULL],
+        };
+        let input = InputBuilder::new(&out_point()).legacy_fund(tx).build();
+
+        let mut ser = Vec::new();
+        input.encode_map(&mut ser).expect("in-memory writers don't error");
         let mut d = std::io::Cursor::new(ser);
 
         let decoded = Input::decode(&mut d).expect("failed to decode");
@@ -16,28 +16,56 @@ pub mod input;
 /// The `output-map`.
 pub mod output;
 
-use crate::prelude::*;
-use crate::raw;
-use crate::serialize::Serialize;
+use crate::io_ext::{PairSink, PairWriter};
+use crate::serialize::{self, Deserialize};
+use crate::{io, raw};
 
 /// A trait that describes a PSBT key-value map.
 pub(crate) trait Map {
-    /// Attempt to get all key-value pairs.
-    fn get_pairs(&self) -> Vec<raw::Pair>;
+    /// Passes all key-value pairs of this map to `pairs`, in serialization order.
+    fn push_pairs<S: PairSink>(&self, pairs: &mut S);
 
-    /// Serialize Psbt binary map data according to BIP-174 specification.
+    /// Writes the map to `w` according to BIP-174 specification, without collecting the pairs.
     ///
     /// <map> := <keypair>* 0x00
     ///
     /// Why is the separator here 0x00 instead of 0xff? The separator here is used to distinguish between each chunk of data.
     /// A separator of 0x00 would mean that the unserializer can read it as a key length of 0, which would never occur with
     /// actual keys. It can thus be used as a separator and allow for easier unserializer implementation.
-    fn serialize_map(&self) -> Vec<u8> {
-        let mut buf = Vec::new();
-        for pair in Map::get_pairs(self) {
-            buf.extend(&pair.serialize());
-        }
-        buf.push(0x00_u8);
-        buf
+    ///
+    /// Returns the number of bytes written.
+    fn encode_map<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<usize> {
+        let mut writer = PairWriter::new(w);
+        self.push_pairs(&mut writer);
+        let len = writer.finish()?;
+        w.write_all(&[0x00])?;
+        Ok(len + 1)
+    }
+}
+
+/// An error inserting a key-value pair into one of the maps.
+pub(crate) trait InsertPairErrorExt: From<serialize::Error> {
+    /// Returns the error for a pair whose key is already present.
+    fn duplicate_key(key: raw::Key) -> Self;
+
+    /// Returns the error for a pair whose key should not contain data.
+    fn invalid_key_data_not_empty(key: raw::Key) -> Self;
+}
+
+/// Inserts the value of a required field into `slot`, the field must not already be present.
+fn insert_required_pair<T, E>(slot: &mut Option<T>, pair: raw::Pair) -> Result<(), E>
+where
+    T: Deserialize,
+    E: InsertPairErrorExt,
+{
+    let raw::Pair { key: raw_key, value: raw_value } = pair;
+
+    if !raw_key.key.is_empty() {
+        return Err(E::invalid_key_data_not_empty(raw_key));
+    }
+    if slot.is_some() {
+        return Err(E::duplicate_key(raw_key));
     }
+    *slot = Some(Deserialize::deserialize(&raw_value)?);
+    Ok(())
 }
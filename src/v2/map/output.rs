@@ -9,15 +9,16 @@ use bitcoin::taproot::{TapLeafHash, TapTree};
 use bitcoin::{secp256k1, Amount, ScriptBuf, TxOut};
 
 use crate::consts::{
-    PSBT_OUT_AMOUNT, PSBT_OUT_BIP32_DERIVATION, PSBT_OUT_PROPRIETARY, PSBT_OUT_REDEEM_SCRIPT,
-    PSBT_OUT_SCRIPT, PSBT_OUT_TAP_BIP32_DERIVATION, PSBT_OUT_TAP_INTERNAL_KEY, PSBT_OUT_TAP_TREE,
-    PSBT_OUT_WITNESS_SCRIPT,
+    PSBT_OUT_AMOUNT, PSBT_OUT_BIP32_DERIVATION, PSBT_OUT_MUSIG2_PARTICIPANT_PUBKEYS,
+    PSBT_OUT_PROPRIETARY, PSBT_OUT_REDEEM_SCRIPT, PSBT_OUT_SCRIPT, PSBT_OUT_SP_V0_INFO,
+    PSBT_OUT_SP_V0_LABEL, PSBT_OUT_TAP_BIP32_DERIVATION, PSBT_OUT_TAP_INTERNAL_KEY,
+    PSBT_OUT_TAP_TREE, PSBT_OUT_WITNESS_SCRIPT,
 };
 use crate::error::write_err;
+use crate::io_ext::PairSink;
 use crate::prelude::*;
-use crate::serialize::{Deserialize, Serialize};
-use crate::v2::map::Map;
-use crate::{io, raw, serialize};
+use crate::v2::map::{insert_required_pair, InsertPairErrorExt, Map};
+use crate::{io, raw, serialize, silent_payments, v0};
 
 /// A key-value map for an output of the corresponding index in the unsigned
 /// transaction.
@@ -46,6 +47,16 @@ pub struct Output {
     /// Map of tap root x only keys to origin info and leaf hashes contained in it.
     #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::btreemap_as_seq"))]
     pub tap_key_origins: BTreeMap<XOnlyPublicKey, (Vec<TapLeafHash>, KeySource)>,
+    /// Map of MuSig2 aggregate keys to the participant keys, in the order used for aggregation.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::btreemap_as_seq"))]
+    pub musig2_participant_pubkeys: BTreeMap<secp256k1::PublicKey, Vec<secp256k1::PublicKey>>,
+    /// The silent payment address this output pays to.
+    ///
+    /// The `script_pubkey` of a silent payment output is empty until it is computed from the ECDH
+    /// shares of the inputs, see [`crate::v2::Updater::compute_silent_payment_scripts`].
+    pub sp_v0_info: Option<silent_payments::SilentPaymentInfo>,
+    /// The label applied to the spend key of the silent payment address, if any.
+    pub sp_v0_label: Option<u32>,
     /// Proprietary key-value pairs for this output.
     #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::btreemap_as_seq_byte_values"))]
     pub proprietaries: BTreeMap<raw::ProprietaryKey, Vec<u8>>,
@@ -66,24 +77,87 @@ impl Output {
             tap_internal_key: None,
             tap_tree: None,
             tap_key_origins: BTreeMap::new(),
+            musig2_participant_pubkeys: BTreeMap::new(),
+            sp_v0_info: None,
+            sp_v0_label: None,
             proprietaries: BTreeMap::new(),
             unknowns: BTreeMap::new(),
         }
     }
 
-    // /// Converts this `Output` to a `v0::Output`.
-    // pub(crate) fn into_v0(self) -> v0::Output {
-    //     v0::Output {
-    //         redeem_script: self.redeem_script,
-    //         witness_script: self.witness_script,
-    //         bip32_derivation: self.bip32_derivations,
-    //         tap_internal_key: self.tap_internal_key,
-    //         tap_tree: self.tap_tree,
-    //         tap_key_origins: self.tap_key_origins,
-    //         proprietary: self.proprietaries,
-    //         unknown: self.unknowns,
-    //     }
-    // }
+    /// Creates an `Output` from a `v0::Output` and the associated output of the unsigned transaction.
+    pub(crate) fn from_v0(output: v0::Output, tx_out: &TxOut) -> Self {
+        let mut rv = Output {
+            amount: tx_out.value,
+            script_pubkey: tx_out.script_pubkey.clone(),
+            redeem_script: output.redeem_script,
+            witness_script: output.witness_script,
+            bip32_derivations: output.bip32_derivation,
+            tap_internal_key: output.tap_internal_key,
+            tap_tree: output.tap_tree,
+            tap_key_origins: output.tap_key_origins,
+            musig2_participant_pubkeys: BTreeMap::new(),
+            sp_v0_info: None,
+            sp_v0_label: None,
+            proprietaries: output.proprietary.into_iter().map(|(k, v)| (k.into(), v)).collect(),
+            unknowns: BTreeMap::new(),
+        };
+
+        // A v0 output has no typed MuSig2 or silent payment fields, they end up in the unknowns.
+        for (key, value) in output.unknown {
+            let key = raw::Key::from(key);
+            if is_v0_untyped_key_type(key.type_value)
+                && rv.insert_pair(raw::Pair { key: key.clone(), value: value.clone() }).is_ok()
+            {
+                continue;
+            }
+            rv.unknowns.insert(key, value);
+        }
+        rv
+    }
+
+    /// Converts this `Output` to a `v0::Output`.
+    ///
+    /// The `amount` and `script_pubkey` are not part of a v0 output map, use [`Self::tx_out`] to
+    /// get them before calling this function.
+    pub(crate) fn into_v0(self) -> v0::Output {
+        // A v0 output has no typed MuSig2 or silent payment fields, keep them as unknowns so they
+        // are not lost.
+        let mut untyped: Vec<raw::Pair> = Vec::new();
+        self.push_v0_untyped_pairs(&mut untyped);
+        let untyped = untyped.into_iter().map(|pair| (pair.key, pair.value));
+        v0::Output {
+            redeem_script: self.redeem_script,
+            witness_script: self.witness_script,
+            bip32_derivation: self.bip32_derivations,
+            tap_internal_key: self.tap_internal_key,
+            tap_tree: self.tap_tree,
+            tap_key_origins: self.tap_key_origins,
+            proprietary: self.proprietaries.into_iter().map(|(k, v)| (k.into(), v)).collect(),
+            unknown: self.unknowns.into_iter().chain(untyped).map(|(k, v)| (k.into(), v)).collect(),
+        }
+    }
+
+    /// Pushes the key-value pairs for the fields of this output that are not typed in a v0
+    /// output (i.e., the MuSig2 and silent payment fields).
+    fn push_v0_untyped_pairs<S: PairSink>(&self, pairs: &mut S) {
+        v2_impl_psbt_get_pair! {
+            pairs.push_map(self.musig2_participant_pubkeys, PSBT_OUT_MUSIG2_PARTICIPANT_PUBKEYS)
+        }
+
+        v2_impl_psbt_get_pair! {
+            pairs.push(self.sp_v0_info, PSBT_OUT_SP_V0_INFO)
+        }
+
+        v2_impl_psbt_get_pair! {
+            pairs.push(self.sp_v0_label, PSBT_OUT_SP_V0_LABEL)
+        }
+    }
+
+    /// Returns true if this is a silent payment output whose script has not been computed yet.
+    pub fn is_silent_payment_pending(&self) -> bool {
+        self.sp_v0_info.is_some() && self.script_pubkey.is_empty()
+    }
 
     /// Creates the [`TxOut`] associated with this `Output`.
     pub(crate) fn tx_out(&self) -> TxOut {
@@ -91,24 +165,32 @@ impl Output {
     }
 
     pub(in crate::v2) fn decode<R: io::Read + ?Sized>(r: &mut R) -> Result<Self, DecodeError> {
-        // These are placeholder values that never exist in a encode `Output`.
-        let invalid = TxOut { value: Amount::ZERO, script_pubkey: ScriptBuf::default() };
-        let mut rv = Self::new(invalid);
+        // The required fields are tracked separately so that every value can be decoded.
+        let mut amount: Option<Amount> = None;
+        let mut script_pubkey: Option<ScriptBuf> = None;
+        let mut rv = Self::new(TxOut::NULL);
 
         loop {
             match raw::Pair::decode(r) {
-                Ok(pair) => rv.insert_pair(pair)?,
+                Ok(pair) => match pair.key.type_value {
+                    PSBT_OUT_AMOUNT =>
+                        insert_required_pair::<_, InsertPairError>(&mut amount, pair)?,
+                    PSBT_OUT_SCRIPT =>
+                        insert_required_pair::<_, InsertPairError>(&mut script_pubkey, pair)?,
+                    _ => rv.insert_pair(pair)?,
+                },
                 Err(serialize::Error::NoMorePairs) => break,
                 Err(e) => return Err(DecodeError::DeserPair(e)),
             }
         }
 
-        if rv.amount == Amount::ZERO {
-            return Err(DecodeError::MissingValue);
-        }
-        if rv.script_pubkey == ScriptBuf::default() {
-            return Err(DecodeError::MissingScriptPubkey);
-        }
+        rv.amount = amount.ok_or(DecodeError::MissingValue)?;
+        // The script of a silent payment output is omitted until it is computed.
+        rv.script_pubkey = match script_pubkey {
+            Some(script_pubkey) => script_pubkey,
+            None if rv.sp_v0_info.is_some() => ScriptBuf::new(),
+            None => return Err(DecodeError::MissingScriptPubkey),
+        };
         Ok(rv)
     }
 
@@ -116,21 +198,6 @@ impl Output {
         let raw::Pair { key: raw_key, value: raw_value } = pair;
 
         match raw_key.type_value {
-            PSBT_OUT_AMOUNT => {
-                if self.amount != Amount::ZERO {
-                    return Err(InsertPairError::DuplicateKey(raw_key));
-                }
-                let amount: Amount = Deserialize::deserialize(&raw_value)?;
-                self.amount = amount;
-            }
-            PSBT_OUT_SCRIPT => {
-                if self.script_pubkey != ScriptBuf::default() {
-                    return Err(InsertPairError::DuplicateKey(raw_key));
-                }
-                let script: ScriptBuf = Deserialize::deserialize(&raw_value)?;
-                self.script_pubkey = script;
-            }
-
             PSBT_OUT_REDEEM_SCRIPT => {
                 v2_impl_psbt_insert_pair! {
                     self.redeem_script <= <raw_key: _>|<raw_value: ScriptBuf>
@@ -171,6 +238,21 @@ impl Output {
                     self.tap_key_origins <= <raw_key: XOnlyPublicKey>|< raw_value: (Vec<TapLeafHash>, KeySource)>
                 }
             }
+            PSBT_OUT_MUSIG2_PARTICIPANT_PUBKEYS => {
+                v2_impl_psbt_insert_pair! {
+                    self.musig2_participant_pubkeys <= <raw_key: secp256k1::PublicKey>|<raw_value: Vec<secp256k1::PublicKey>>
+                }
+            }
+            PSBT_OUT_SP_V0_INFO => {
+                v2_impl_psbt_insert_pair! {
+                    self.sp_v0_info <= <raw_key: _>|<raw_value: silent_payments::SilentPaymentInfo>
+                }
+            }
+            PSBT_OUT_SP_V0_LABEL => {
+                v2_impl_psbt_insert_pair! {
+                    self.sp_v0_label <= <raw_key: _>|<raw_value: u32>
+                }
+            }
             // Note, PSBT v2 does not exclude any keys from the input map.
             _ => match self.unknowns.entry(raw_key) {
                 btree_map::Entry::Vacant(empty_key) => {
@@ -190,7 +272,13 @@ impl Output {
             return Err(CombineError::AmountMismatch { this: self.amount, that: other.amount });
         }
 
-        if self.script_pubkey != other.script_pubkey {
+        v2_combine_option!(sp_v0_info, self, other);
+        v2_combine_option!(sp_v0_label, self, other);
+
+        // A silent payment output script may only have been computed in one of the PSBTs.
+        if self.is_silent_payment_pending() {
+            self.script_pubkey = other.script_pubkey.clone();
+        } else if self.script_pubkey != other.script_pubkey && !other.is_silent_payment_pending() {
             return Err(CombineError::ScriptPubkeyMismatch {
                 this: self.script_pubkey.clone(),
                 that: other.script_pubkey,
@@ -203,6 +291,7 @@ impl Output {
         v2_combine_option!(tap_internal_key, self, other);
         v2_combine_option!(tap_tree, self, other);
         v2_combine_map!(tap_key_origins, self, other);
+        v2_combine_map!(musig2_participant_pubkeys, self, other);
         v2_combine_map!(proprietaries, self, other);
         v2_combine_map!(unknowns, self, other);
 
@@ -211,52 +300,46 @@ impl Output {
 }
 
 impl Map for Output {
-    fn get_pairs(&self) -> Vec<raw::Pair> {
-        let mut rv: Vec<raw::Pair> = Default::default();
-
-        rv.push(raw::Pair {
-            key: raw::Key { type_value: PSBT_OUT_AMOUNT, key: vec![] },
-            value: self.amount.serialize(),
-        });
+    fn push_pairs<S: PairSink>(&self, pairs: &mut S) {
+        pairs.push_pair(PSBT_OUT_AMOUNT, &[], &self.amount);
 
-        rv.push(raw::Pair {
-            key: raw::Key { type_value: PSBT_OUT_SCRIPT, key: vec![] },
-            value: self.script_pubkey.serialize(),
-        });
+        if !self.is_silent_payment_pending() {
+            pairs.push_pair(PSBT_OUT_SCRIPT, &[], &self.script_pubkey);
+        }
 
         v2_impl_psbt_get_pair! {
-            rv.push(self.redeem_script, PSBT_OUT_REDEEM_SCRIPT)
+            pairs.push(self.redeem_script, PSBT_OUT_REDEEM_SCRIPT)
         }
 
         v2_impl_psbt_get_pair! {
-            rv.push(self.witness_script, PSBT_OUT_WITNESS_SCRIPT)
+            pairs.push(self.witness_script, PSBT_OUT_WITNESS_SCRIPT)
         }
 
         v2_impl_psbt_get_pair! {
-            rv.push_map(self.bip32_derivations, PSBT_OUT_BIP32_DERIVATION)
+            pairs.push_map(self.bip32_derivations, PSBT_OUT_BIP32_DERIVATION)
         }
 
         v2_impl_psbt_get_pair! {
-            rv.push(self.tap_internal_key, PSBT_OUT_TAP_INTERNAL_KEY)
+            pairs.push(self.tap_internal_key, PSBT_OUT_TAP_INTERNAL_KEY)
         }
 
         v2_impl_psbt_get_pair! {
-            rv.push(self.tap_tree, PSBT_OUT_TAP_TREE)
+            pairs.push(self.tap_tree, PSBT_OUT_TAP_TREE)
         }
 
         v2_impl_psbt_get_pair! {
-            rv.push_map(self.tap_key_origins, PSBT_OUT_TAP_BIP32_DERIVATION)
+            pairs.push_map(self.tap_key_origins, PSBT_OUT_TAP_BIP32_DERIVATION)
         }
 
+        self.push_v0_untyped_pairs(pairs);
+
         for (key, value) in self.proprietaries.iter() {
-            rv.push(raw::Pair { key: key.to_key(), value: value.clone() });
+            pairs.push_pair(0xFC, key, value);
         }
 
         for (key, value) in self.unknowns.iter() {
-            rv.push(raw::Pair { key: key.clone(), value: value.clone() });
+            pairs.push_pair(key.type_value, &key.key, value);
         }
-
-        rv
     }
 }
 
@@ -268,10 +351,34 @@ impl OutputBuilder {
     /// Creates a new builder that can be used to build an [`Output`] around `utxo`.
     pub fn new(utxo: TxOut) -> Self { OutputBuilder(Output::new(utxo)) }
 
+    /// Creates a new builder for an [`Output`] paying `amount` to a silent payment address.
+    ///
+    /// The script of the output is computed once all ECDH shares are present, see
+    /// [`crate::v2::Updater::compute_silent_payment_scripts`].
+    pub fn silent_payment(amount: Amount, info: silent_payments::SilentPaymentInfo) -> Self {
+        let mut output = Output::new(TxOut { value: amount, script_pubkey: ScriptBuf::new() });
+        output.sp_v0_info = Some(info);
+        OutputBuilder(output)
+    }
+
+    /// Sets the label applied to the spend key of the silent payment address.
+    pub fn silent_payment_label(mut self, label: u32) -> Self {
+        self.0.sp_v0_label = Some(label);
+        self
+    }
+
     /// Build the [`Output`].
     pub fn build(self) -> Output { self.0 }
 }
 
+/// Returns true if `type_value` is the key type of a field that is not typed in a v0 output.
+fn is_v0_untyped_key_type(type_value: u8) -> bool {
+    matches!(
+        type_value,
+        PSBT_OUT_MUSIG2_PARTICIPANT_PUBKEYS | PSBT_OUT_SP_V0_INFO | PSBT_OUT_SP_V0_LABEL
+    )
+}
+
 /// An error while decoding.
 #[derive(Debug)]
 #[non_exhaustive]
@@ -358,6 +465,12 @@ impl From<serialize::Error> for InsertPairError {
     fn from(e: serialize::Error) -> Self { Self::Deser(e) }
 }
 
+impl InsertPairErrorExt for InsertPairError {
+    fn duplicate_key(key: raw::Key) -> Self { Self::DuplicateKey(key) }
+
+    fn invalid_key_data_not_empty(key: raw::Key) -> Self { Self::InvalidKeyDataNotEmpty(key) }
+}
+
 /// Error combining two output maps.
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[non_exhaustive]
@@ -419,7 +532,8 @@ mod tests {
     fn serialize_roundtrip() {
         let output = Output::new(tx_out());
 
-        let ser = output.serialize_map();
+        let mut ser = Vec::new();
+        output.encode_map(&mut ser).expect("in-memory writers don't error");
         let mut d = std::io::Cursor::new(ser);
 
         let decoded = Output::decode(&mut d).expect("failed to decode");
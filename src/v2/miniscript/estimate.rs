@@ -0,0 +1,407 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Estimating the weight and fee rate of the final transaction before the PSBT is finalized.
+//!
+//! The estimates are upper bounds, signatures are assumed to be of maximum size (73 bytes for ECDSA
+//! and 66 bytes for Schnorr, both including the push opcode and sighash type).
+
+use alloc::collections::BTreeMap;
+use core::fmt;
+
+use bitcoin::hashes::{hash160, Hash as _};
+use bitcoin::key::XOnlyPublicKey;
+use bitcoin::taproot::LeafVersion;
+use bitcoin::{FeeRate, Script, Weight};
+use miniscript::descriptor::{DefiniteDescriptorKey, Descriptor};
+use miniscript::{BareCtx, ExtParams, Legacy, Miniscript, MiniscriptKey, Segwitv0, Tap};
+
+use crate::error::{fee_rate, write_err, FeeError, FundingUtxoError};
+use crate::prelude::*;
+use crate::v2::map::input::Input;
+use crate::v2::miniscript::varint_len;
+use crate::v2::{DetermineLockTimeError, Psbt};
+
+/// The weight of the P2TR script pubkey of a silent payment output that is not yet computed.
+const SILENT_PAYMENT_SCRIPT_WEIGHT: u64 = 4 * 34;
+
+impl Psbt {
+    /// Estimates the weight of the final transaction using the scripts in the input maps.
+    ///
+    /// Finalized inputs use their final script sig and witness. Other inputs are satisfied using
+    /// the scripts and keys in the input map, see [`Input::max_weight_to_satisfy`].
+    pub fn estimate_weight(&self) -> Result<Weight, EstimateWeightError> {
+        let mut satisfactions = Vec::with_capacity(self.inputs.len());
+        for (input_index, input) in self.inputs.iter().enumerate() {
+            let satisfaction = input
+                .satisfaction()
+                .map_err(|error| EstimateWeightError::Input { input_index, error })?;
+            satisfactions.push(satisfaction);
+        }
+        self.estimate_weight_helper(&satisfactions)
+    }
+
+    /// Estimates the weight of the final transaction using a descriptor for each input.
+    ///
+    /// Finalized inputs use their final script sig and witness, the descriptor for a finalized
+    /// input is ignored.
+    pub fn estimate_weight_with_descriptors(
+        &self,
+        descriptors: &[Descriptor<DefiniteDescriptorKey>],
+    ) -> Result<Weight, EstimateWeightError> {
+        if descriptors.len() != self.inputs.len() {
+            return Err(EstimateWeightError::DescriptorCount {
+                inputs: self.inputs.len(),
+                descriptors: descriptors.len(),
+            });
+        }
+
+        let mut satisfactions = Vec::with_capacity(self.inputs.len());
+        for (input_index, (input, desc)) in self.inputs.iter().zip(descriptors).enumerate() {
+            let satisfaction = match input.final_satisfaction() {
+                Some(satisfaction) => satisfaction,
+                None => descriptor_satisfaction(desc)
+                    .map_err(|error| EstimateWeightError::Input { input_index, error })?,
+            };
+            satisfactions.push(satisfaction);
+        }
+        self.estimate_weight_helper(&satisfactions)
+    }
+
+    /// Estimates the fee rate of the final transaction using the scripts in the input maps.
+    ///
+    /// Since the weight is an upper bound the final fee rate is at least the returned fee rate.
+    pub fn estimate_fee_rate(&self) -> Result<FeeRate, EstimateFeeRateError> {
+        let weight = self.estimate_weight()?;
+        self.estimate_fee_rate_helper(weight)
+    }
+
+    /// Estimates the fee rate of the final transaction using a descriptor for each input.
+    ///
+    /// Since the weight is an upper bound the final fee rate is at least the returned fee rate.
+    pub fn estimate_fee_rate_with_descriptors(
+        &self,
+        descriptors: &[Descriptor<DefiniteDescriptorKey>],
+    ) -> Result<FeeRate, EstimateFeeRateError> {
+        let weight = self.estimate_weight_with_descriptors(descriptors)?;
+        self.estimate_fee_rate_helper(weight)
+    }
+
+    fn estimate_weight_helper(
+        &self,
+        satisfactions: &[Satisfaction],
+    ) -> Result<Weight, EstimateWeightError> {
+        // The unsigned transaction has empty script sigs and witnesses.
+        let mut weight = self.unsigned_tx()?.weight().to_wu();
+
+        if satisfactions.iter().any(|s| s.segwit) {
+            // Segwit marker and flag plus the empty witness stack length of each input.
+            weight += 2 + self.inputs.len() as u64;
+        }
+        weight += satisfactions.iter().map(|s| s.weight).sum::<u64>();
+
+        let pending = self.outputs.iter().filter(|output| output.is_silent_payment_pending());
+        weight += pending.count() as u64 * SILENT_PAYMENT_SCRIPT_WEIGHT;
+
+        Ok(Weight::from_wu(weight))
+    }
+
+    fn estimate_fee_rate_helper(&self, weight: Weight) -> Result<FeeRate, EstimateFeeRateError> {
+        let fee = self.fee()?;
+        Ok(fee_rate(fee, weight))
+    }
+}
+
+impl Input {
+    /// Returns an upper bound on the weight added to the transaction by satisfying this input.
+    ///
+    /// This is the difference in the segwit serialized weight of the input when satisfied and
+    /// when it has an empty script sig and witness (see `Descriptor::max_weight_to_satisfy`).
+    ///
+    /// For taproot inputs this is the largest of the key path spend and the script path spends
+    /// in `tap_scripts`. For other inputs the key for a key hash output is taken from
+    /// `bip32_derivations` or `partial_sigs`, and the witness or redeem script must be present.
+    pub fn max_weight_to_satisfy(&self) -> Result<Weight, EstimateInputError> {
+        self.satisfaction().map(|s| Weight::from_wu(s.weight))
+    }
+
+    fn satisfaction(&self) -> Result<Satisfaction, EstimateInputError> {
+        if let Some(satisfaction) = self.final_satisfaction() {
+            return Ok(satisfaction);
+        }
+
+        let spk = &self.funding_utxo()?.script_pubkey;
+        if spk.is_p2tr() {
+            return self.tap_satisfaction();
+        }
+        descriptor_satisfaction(&self.descriptor(spk)?)
+    }
+
+    /// Returns the weight of the final script sig and witness, if this input is finalized.
+    fn final_satisfaction(&self) -> Option<Satisfaction> {
+        if self.final_script_sig.is_none() && self.final_script_witness.is_none() {
+            return None;
+        }
+        let script_sig = self.final_script_sig.as_ref().map(|s| s.len()).unwrap_or(0);
+        let witness = self.final_script_witness.as_ref().map(|w| w.size()).unwrap_or(1);
+
+        let weight =
+            4 * (varint_len(script_sig) + script_sig - varint_len(0)) + witness - varint_len(0);
+        Some(Satisfaction { weight: weight as u64, segwit: witness > varint_len(0) })
+    }
+
+    fn tap_satisfaction(&self) -> Result<Satisfaction, EstimateInputError> {
+        // Key path: one stack item, the signature and sighash type with its length prefix.
+        let mut weight = varint_len(1) - varint_len(0) + 1 + 65;
+
+        for (control_block, (script, leaf_version)) in &self.tap_scripts {
+            if *leaf_version != LeafVersion::TapScript {
+                continue;
+            }
+            let ms =
+                Miniscript::<XOnlyPublicKey, Tap>::parse_with_ext(script, &ExtParams::allow_all())?;
+            let (elements, size) =
+                match (ms.max_satisfaction_witness_elements(), ms.max_satisfaction_size()) {
+                    (Ok(elements), Ok(size)) => (elements, size),
+                    // This leaf can not be satisfied, another one will be used.
+                    _ => continue,
+                };
+            let control_block = control_block.serialize().len();
+            let leaf = varint_len(elements + 1) - varint_len(0)
+                + size
+                + varint_len(script.len())
+                + script.len()
+                + varint_len(control_block)
+                + control_block;
+            weight = weight.max(leaf);
+        }
+        Ok(Satisfaction { weight: weight as u64, segwit: true })
+    }
+
+    /// Creates a descriptor from the scripts and keys in this input map.
+    fn descriptor(
+        &self,
+        spk: &Script,
+    ) -> Result<Descriptor<bitcoin::PublicKey>, EstimateInputError> {
+        let mut keys: BTreeMap<hash160::Hash, bitcoin::PublicKey> = BTreeMap::new();
+        for key in self.bip32_derivations.keys() {
+            let key = bitcoin::PublicKey::new(*key);
+            keys.insert(key.pubkey_hash().to_raw_hash(), key);
+        }
+        for key in self.partial_sigs.keys() {
+            keys.insert(key.pubkey_hash().to_raw_hash(), *key);
+        }
+        let key = |pubkey_hash: &[u8]| {
+            keys.values()
+                .find(|key| key.pubkey_hash().as_byte_array()[..] == *pubkey_hash)
+                .copied()
+                .ok_or(EstimateInputError::MissingPubkey)
+        };
+
+        if spk.is_p2pk() {
+            let pk = bitcoin::PublicKey::from_slice(&spk.as_bytes()[1..spk.len() - 1])
+                .map_err(|_| EstimateInputError::MissingPubkey)?;
+            Ok(Descriptor::new_pk(pk))
+        } else if spk.is_p2pkh() {
+            Ok(Descriptor::new_pkh(key(&spk.as_bytes()[3..23])?)?)
+        } else if spk.is_p2wpkh() {
+            Ok(Descriptor::new_wpkh(key(&spk.as_bytes()[2..22])?)?)
+        } else if spk.is_p2wsh() {
+            let witness_script =
+                self.witness_script.as_ref().ok_or(EstimateInputError::MissingWitnessScript)?;
+            let ms = Miniscript::<bitcoin::PublicKey, Segwitv0>::parse_with_ext(
+                witness_script,
+                &ExtParams::allow_all(),
+            )?;
+            Ok(Descriptor::new_wsh(ms.substitute_raw_pkh(&keys))?)
+        } else if spk.is_p2sh() {
+            let redeem_script =
+                self.redeem_script.as_ref().ok_or(EstimateInputError::MissingRedeemScript)?;
+            if redeem_script.is_p2wpkh() {
+                Ok(Descriptor::new_sh_wpkh(key(&redeem_script.as_bytes()[2..22])?)?)
+            } else if redeem_script.is_p2wsh() {
+                let witness_script =
+                    self.witness_script.as_ref().ok_or(EstimateInputError::MissingWitnessScript)?;
+                let ms = Miniscript::<bitcoin::PublicKey, Segwitv0>::parse_with_ext(
+                    witness_script,
+                    &ExtParams::allow_all(),
+                )?;
+                Ok(Descriptor::new_sh_wsh(ms.substitute_raw_pkh(&keys))?)
+            } else {
+                let ms = Miniscript::<bitcoin::PublicKey, Legacy>::parse_with_ext(
+                    redeem_script,
+                    &ExtParams::allow_all(),
+                )?;
+                Ok(Descriptor::new_sh(ms.substitute_raw_pkh(&keys))?)
+            }
+        } else {
+            let ms = Miniscript::<bitcoin::PublicKey, BareCtx>::parse_with_ext(
+                spk,
+                &ExtParams::allow_all(),
+            )?;
+            Ok(Descriptor::new_bare(ms.substitute_raw_pkh(&keys))?)
+        }
+    }
+}
+
+/// The weight added to the transaction by satisfying an input.
+struct Satisfaction {
+    /// The weight added to the segwit serialized input.
+    weight: u64,
+    /// True if the input is satisfied using a witness.
+    segwit: bool,
+}
+
+fn descriptor_satisfaction<Pk: MiniscriptKey>(
+    desc: &Descriptor<Pk>,
+) -> Result<Satisfaction, EstimateInputError> {
+    let weight = desc.max_weight_to_satisfy()?;
+    Ok(Satisfaction { weight: weight as u64, segwit: desc.desc_type().segwit_version().is_some() })
+}
+
+/// Error estimating the weight of the final transaction.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum EstimateWeightError {
+    /// Failed to determine lock time for the unsigned transaction.
+    DetermineLockTime(DetermineLockTimeError),
+    /// The number of descriptors does not match the number of inputs.
+    DescriptorCount {
+        /// The number of inputs.
+        inputs: usize,
+        /// The number of descriptors.
+        descriptors: usize,
+    },
+    /// Failed to estimate the satisfaction weight of an input.
+    Input {
+        /// Index of the input causing this error.
+        input_index: usize,
+        /// The error estimating the input.
+        error: EstimateInputError,
+    },
+}
+
+impl fmt::Display for EstimateWeightError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use EstimateWeightError::*;
+
+        match *self {
+            DetermineLockTime(ref e) => write_err!(f, "estimate weight determine locktime"; e),
+            DescriptorCount { inputs, descriptors } => write!(
+                f,
+                "got {} descriptors for {} inputs, need one descriptor per input",
+                descriptors, inputs
+            ),
+            Input { input_index, ref error } =>
+                write_err!(f, "failed to estimate satisfaction weight of input {}", input_index; error),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for EstimateWeightError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use EstimateWeightError::*;
+
+        match *self {
+            DetermineLockTime(ref e) => Some(e),
+            Input { ref error, .. } => Some(error),
+            DescriptorCount { .. } => None,
+        }
+    }
+}
+
+impl From<DetermineLockTimeError> for EstimateWeightError {
+    fn from(e: DetermineLockTimeError) -> Self { Self::DetermineLockTime(e) }
+}
+
+/// Error estimating the satisfaction weight of an input.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum EstimateInputError {
+    /// The input has no funding utxo.
+    FundingUtxo(FundingUtxoError),
+    /// The public key for a key hash output is not in the input map.
+    MissingPubkey,
+    /// Missing redeem script for p2sh.
+    MissingRedeemScript,
+    /// Missing witness script for p2wsh.
+    MissingWitnessScript,
+    /// Failed to parse a script or to compute its satisfaction weight.
+    Miniscript(miniscript::Error),
+}
+
+impl fmt::Display for EstimateInputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use EstimateInputError::*;
+
+        match *self {
+            FundingUtxo(ref e) => write_err!(f, "funding utxo error"; e),
+            MissingPubkey => f.write_str("missing the public key for a key hash output"),
+            MissingRedeemScript => f.write_str("missing redeem script for p2sh output"),
+            MissingWitnessScript => f.write_str("missing witness script for p2wsh output"),
+            Miniscript(ref e) => write_err!(f, "miniscript"; e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for EstimateInputError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use EstimateInputError::*;
+
+        match *self {
+            FundingUtxo(ref e) => Some(e),
+            Miniscript(ref e) => Some(e),
+            MissingPubkey | MissingRedeemScript | MissingWitnessScript => None,
+        }
+    }
+}
+
+impl From<FundingUtxoError> for EstimateInputError {
+    fn from(e: FundingUtxoError) -> Self { Self::FundingUtxo(e) }
+}
+
+impl From<miniscript::Error> for EstimateInputError {
+    fn from(e: miniscript::Error) -> Self { Self::Miniscript(e) }
+}
+
+/// Error estimating the fee rate of the final transaction.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum EstimateFeeRateError {
+    /// Failed to estimate the weight.
+    EstimateWeight(EstimateWeightError),
+    /// Failed to calculate the fee.
+    Fee(FeeError),
+}
+
+impl fmt::Display for EstimateFeeRateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use EstimateFeeRateError::*;
+
+        match *self {
+            EstimateWeight(ref e) => write_err!(f, "failed to estimate weight"; e),
+            Fee(ref e) => write_err!(f, "failed to calculate fee"; e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for EstimateFeeRateError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use EstimateFeeRateError::*;
+
+        match *self {
+            EstimateWeight(ref e) => Some(e),
+            Fee(ref e) => Some(e),
+        }
+    }
+}
+
+impl From<EstimateWeightError> for EstimateFeeRateError {
+    fn from(e: EstimateWeightError) -> Self { Self::EstimateWeight(e) }
+}
+
+impl From<FeeError> for EstimateFeeRateError {
+    fn from(e: FeeError) -> Self { Self::Fee(e) }
+}
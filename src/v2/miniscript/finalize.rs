@@ -9,8 +9,9 @@ use core::fmt;
 
 use bitcoin::hashes::hash160;
 use bitcoin::secp256k1::{Secp256k1, Verification};
+use bitcoin::sighash::Prevouts;
 use bitcoin::taproot::LeafVersion;
-use bitcoin::{sighash, Address, Network, Script, ScriptBuf, Txid, Witness, XOnlyPublicKey};
+use bitcoin::{sighash, Address, Network, Script, ScriptBuf, TxOut, Txid, Witness, XOnlyPublicKey};
 use miniscript::{
     interpreter, BareCtx, Descriptor, ExtParams, Legacy, Miniscript, Satisfier, Segwitv0, SigType,
     Tap, ToPublicKey,
@@ -20,8 +21,11 @@ use crate::error::{write_err, FundingUtxoError};
 use crate::prelude::*;
 use crate::v2::map::input::{self, Input};
 use crate::v2::miniscript::satisfy::InputSatisfier;
-use crate::v2::miniscript::InterpreterCheckError;
-use crate::v2::{DetermineLockTimeError, PartialSigsSighashTypeError, Psbt};
+use crate::v2::miniscript::{InterpreterCheckError, InterpreterCheckInputError};
+use crate::v2::{
+    DetermineLockTimeError, IndexOutOfBoundsError, Musig2AggregateError,
+    PartialSigsSighashTypeError, Psbt,
+};
 
 /// Implements the BIP-370 Finalized role.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -33,7 +37,7 @@ impl Finalizer {
     /// Creates an `Finalizer`.
     ///
     /// A finalizer can only be created if all inputs have a funding UTXO.
-    pub fn new(psbt: Psbt) -> Result<Self, Error> {
+    pub fn new(psbt: Psbt) -> Result<Self, NewFinalizerError> {
         // TODO: Consider doing this with combinators.
         for input in psbt.inputs.iter() {
             let _ = input.funding_utxo()?;
@@ -49,18 +53,50 @@ impl Finalizer {
         self.0.id().expect("Finalizer guarantees lock time can be determined")
     }
 
-    /// Finalize the PSBT.
+    /// Finalize the PSBT using non-malleable satisfactions.
+    ///
+    /// Inputs that are already finalized are left as they are. Fails if an input only has a
+    /// malleable satisfaction, use [`Self::finalize_mall`] to allow those.
     ///
     /// # Returns
     ///
     /// Returns the finalized PSBT without modifying the original.
     #[must_use = "returns the finalized PSBT without modifying the original"]
     pub fn finalize<C: Verification>(&self, secp: &Secp256k1<C>) -> Result<Psbt, FinalizeError> {
+        self.finalize_helper(secp, false)
+    }
+
+    /// Finalize the PSBT allowing malleable satisfactions.
+    ///
+    /// Malleable satisfactions may be cheaper than non-malleable ones but the witness can be
+    /// changed by a third party without invalidating the transaction, prefer [`Self::finalize`].
+    ///
+    /// Inputs that are already finalized are left as they are.
+    ///
+    /// # Returns
+    ///
+    /// Returns the finalized PSBT without modifying the original.
+    #[must_use = "returns the finalized PSBT without modifying the original"]
+    pub fn finalize_mall<C: Verification>(
+        &self,
+        secp: &Secp256k1<C>,
+    ) -> Result<Psbt, FinalizeError> {
+        self.finalize_helper(secp, true)
+    }
+
+    fn finalize_helper<C: Verification>(
+        &self,
+        secp: &Secp256k1<C>,
+        allow_mall: bool,
+    ) -> Result<Psbt, FinalizeError> {
         let mut inputs = vec![];
-        for (input_index, input) in self.0.inputs.iter().enumerate() {
-            match self.finalize_input(input) {
+        for input_index in 0..self.0.inputs.len() {
+            if self.0.inputs[input_index].is_finalized() {
+                inputs.push(self.0.inputs[input_index].clone());
+                continue;
+            }
+            match self.finalized_input(input_index, secp, allow_mall) {
                 Ok(input) => inputs.push(input),
-                // TODO: Do we want to continue loop and return a vector of errors?
                 Err(error) => return Err(FinalizeError::FinalizeInput { input_index, error }),
             }
         }
@@ -72,12 +108,167 @@ impl Finalizer {
         Ok(finalized)
     }
 
-    // `index` must be the input index of `input` which references an input in `self` - this gets rid of out-of-bounds error path.
-    fn finalize_input(&self, input: &Input) -> Result<Input, FinalizeInputError> {
-        let allow_mall = true; // TODO: Add mall and no-mall versions.
-        let (script_sig, witness) = self.final_script_sig_and_witness(input, allow_mall)?;
+    /// Finalize the input at `input_index` using a non-malleable satisfaction.
+    ///
+    /// Useful when inputs become finalizable at different times, e.g., in multi-party flows. If the
+    /// input is already finalized it is left as it is.
+    ///
+    /// # Returns
+    ///
+    /// Returns the PSBT with the input finalized without modifying the original.
+    #[must_use = "returns the PSBT with the input finalized without modifying the original"]
+    pub fn finalize_input<C: Verification>(
+        &self,
+        input_index: usize,
+        secp: &Secp256k1<C>,
+    ) -> Result<Psbt, FinalizeError> {
+        self.finalize_input_helper(input_index, secp, false)
+    }
+
+    /// Finalize the input at `input_index` allowing a malleable satisfaction.
+    ///
+    /// # Returns
+    ///
+    /// Returns the PSBT with the input finalized without modifying the original.
+    #[must_use = "returns the PSBT with the input finalized without modifying the original"]
+    pub fn finalize_input_mall<C: Verification>(
+        &self,
+        input_index: usize,
+        secp: &Secp256k1<C>,
+    ) -> Result<Psbt, FinalizeError> {
+        self.finalize_input_helper(input_index, secp, true)
+    }
+
+    fn finalize_input_helper<C: Verification>(
+        &self,
+        input_index: usize,
+        secp: &Secp256k1<C>,
+        allow_mall: bool,
+    ) -> Result<Psbt, FinalizeError> {
+        self.0.check_input_index(input_index)?;
+        if self.0.inputs[input_index].is_finalized() {
+            return Ok(self.0.clone());
+        }
+
+        let input = self
+            .finalize_and_check_input(input_index, secp, allow_mall)
+            .map_err(|error| FinalizeError::FinalizeInput { input_index, error })?;
+
+        let mut psbt = self.0.clone();
+        psbt.inputs[input_index] = input;
+        Ok(psbt)
+    }
+
+    /// Finalize every input that can be finalized using non-malleable satisfactions.
+    ///
+    /// Inputs that are already finalized are left as they are.
+    ///
+    /// # Returns
+    ///
+    /// Returns the (possibly partially) finalized PSBT without modifying the original, along with
+    /// the errors for each input that could not be finalized, keyed by input index.
+    #[must_use = "returns the finalized PSBT without modifying the original"]
+    pub fn finalize_best_effort<C: Verification>(
+        &self,
+        secp: &Secp256k1<C>,
+    ) -> (Psbt, BTreeMap<usize, FinalizeInputError>) {
+        self.finalize_best_effort_helper(secp, false)
+    }
+
+    /// Finalize every input that can be finalized allowing malleable satisfactions.
+    ///
+    /// Inputs that are already finalized are left as they are.
+    ///
+    /// # Returns
+    ///
+    /// Returns the (possibly partially) finalized PSBT without modifying the original, along with
+    /// the errors for each input that could not be finalized, keyed by input index.
+    #[must_use = "returns the finalized PSBT without modifying the original"]
+    pub fn finalize_best_effort_mall<C: Verification>(
+        &self,
+        secp: &Secp256k1<C>,
+    ) -> (Psbt, BTreeMap<usize, FinalizeInputError>) {
+        self.finalize_best_effort_helper(secp, true)
+    }
+
+    fn finalize_best_effort_helper<C: Verification>(
+        &self,
+        secp: &Secp256k1<C>,
+        allow_mall: bool,
+    ) -> (Psbt, BTreeMap<usize, FinalizeInputError>) {
+        let mut psbt = self.0.clone();
+        let mut errors = BTreeMap::new();
+
+        for input_index in 0..psbt.inputs.len() {
+            if psbt.inputs[input_index].is_finalized() {
+                continue;
+            }
+            match self.finalize_and_check_input(input_index, secp, allow_mall) {
+                Ok(input) => psbt.inputs[input_index] = input,
+                Err(error) => {
+                    errors.insert(input_index, error);
+                }
+            }
+        }
+
+        (psbt, errors)
+    }
 
-        Ok(input.finalize(script_sig, witness)?.clone())
+    /// Finalizes the input at `input_index` and runs the interpreter checks on it.
+    ///
+    /// `input_index` must be a valid index into `self.0.inputs`.
+    fn finalize_and_check_input<C: Verification>(
+        &self,
+        input_index: usize,
+        secp: &Secp256k1<C>,
+        allow_mall: bool,
+    ) -> Result<Input, FinalizeInputError> {
+        let input = self.finalized_input(input_index, secp, allow_mall)?;
+
+        let unsigned_tx =
+            self.0.unsigned_tx().expect("Finalizer guarantees lock time can be determined");
+        let utxos: Vec<&TxOut> = self
+            .0
+            .iter_funding_utxos()
+            .map(|res| res.expect("Finalizer guarantees funding utxos"))
+            .collect();
+
+        self.0.interpreter_check_input(
+            secp,
+            &unsigned_tx,
+            input_index,
+            &input,
+            &Prevouts::All(&utxos),
+            input.final_script_witness.as_ref().unwrap_or(&Witness::default()),
+            input.final_script_sig.as_ref().expect("finalized input has a script_sig"),
+        )?;
+
+        Ok(input)
+    }
+
+    /// Returns the finalized input at `input_index`.
+    ///
+    /// If the input has MuSig2 partial signatures for a key path spend but no `tap_key_sig` the
+    /// partial signatures are aggregated first.
+    ///
+    /// `input_index` must be a valid index into `self.0.inputs`.
+    fn finalized_input<C: Verification>(
+        &self,
+        input_index: usize,
+        secp: &Secp256k1<C>,
+        allow_mall: bool,
+    ) -> Result<Input, FinalizeInputError> {
+        let mut input = Cow::Borrowed(&self.0.inputs[input_index]);
+        if input.tap_key_sig.is_none()
+            && input.musig2_partial_sigs.keys().any(|(_, _, leaf_hash)| leaf_hash.is_none())
+        {
+            let sig = self.0.musig2_key_sig(input_index, secp)?;
+            input.to_mut().tap_key_sig = Some(sig);
+        }
+
+        let (script_sig, witness) = self.final_script_sig_and_witness(&input, allow_mall)?;
+
+        Ok(input.finalize(script_sig, witness)?)
     }
 
     /// Returns the final script_sig and final witness for this input.
@@ -127,8 +318,6 @@ impl Finalizer {
         };
 
         let witness = Witness::from_slice(&witness);
-        println!("{:#?}", script_sig);
-        println!("{:#?}", witness);
         Ok((script_sig, witness))
     }
 
@@ -285,6 +474,22 @@ impl Finalizer {
     }
 }
 
+impl Psbt {
+    /// Returns a [`Finalizer`] for this signed PSBT.
+    ///
+    /// This is the checked transition out of the Signer role, every input must either have
+    /// signature data or already be finalized. Use [`Finalizer::new`] directly to finalize a
+    /// partially signed PSBT on a best-effort basis.
+    pub fn into_finalizer(self) -> Result<Finalizer, NewFinalizerError> {
+        if let Some(input_index) =
+            self.inputs.iter().position(|input| !(input.has_sig_data() || input.is_finalized()))
+        {
+            return Err(NewFinalizerError::MissingSigData { input_index });
+        }
+        Finalizer::new(self)
+    }
+}
+
 // Satisfy the taproot descriptor. It is not possible to infer the complete descriptor from psbt
 // because the information about all the scripts might not be present. Also, currently the spec does
 // not support hidden branches, so inferring a descriptor is not possible.
@@ -364,18 +569,23 @@ fn construct_tap_witness(
 
 /// Error constructing a [`Finalizer`].
 #[derive(Debug)]
-pub enum Error {
+pub enum NewFinalizerError {
     /// An input is missing its funding UTXO.
     FundingUtxo(FundingUtxoError),
     /// Finalizer must be able to determine the lock time.
     DetermineLockTime(DetermineLockTimeError),
     /// An input has incorrect sighash type for its partial sigs (ECDSA).
     PartialSigsSighashType(PartialSigsSighashTypeError),
+    /// An input has neither been signed nor finalized.
+    MissingSigData {
+        /// The index of the input without signatures.
+        input_index: usize,
+    },
 }
 
-impl fmt::Display for Error {
+impl fmt::Display for NewFinalizerError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        use Error::*;
+        use NewFinalizerError::*;
 
         match *self {
             // TODO: Loads of error messages are capitalized, they should not be.
@@ -383,32 +593,35 @@ impl fmt::Display for Error {
             DetermineLockTime(ref e) =>
                 write_err!(f, "finalizer must be able to determine the lock time"; e),
             PartialSigsSighashType(ref e) => write_err!(f, "Finalizer sighash type error"; e),
+            MissingSigData { input_index } =>
+                write!(f, "input {} has not been signed or finalized", input_index),
         }
     }
 }
 
 #[cfg(feature = "std")]
-impl std::error::Error for Error {
+impl std::error::Error for NewFinalizerError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-        use Error::*;
+        use NewFinalizerError::*;
 
         match *self {
             FundingUtxo(ref e) => Some(e),
             DetermineLockTime(ref e) => Some(e),
             PartialSigsSighashType(ref e) => Some(e),
+            MissingSigData { .. } => None,
         }
     }
 }
 
-impl From<FundingUtxoError> for Error {
+impl From<FundingUtxoError> for NewFinalizerError {
     fn from(e: FundingUtxoError) -> Self { Self::FundingUtxo(e) }
 }
 
-impl From<DetermineLockTimeError> for Error {
+impl From<DetermineLockTimeError> for NewFinalizerError {
     fn from(e: DetermineLockTimeError) -> Self { Self::DetermineLockTime(e) }
 }
 
-impl From<PartialSigsSighashTypeError> for Error {
+impl From<PartialSigsSighashTypeError> for NewFinalizerError {
     fn from(e: PartialSigsSighashTypeError) -> Self { Self::PartialSigsSighashType(e) }
 }
 
@@ -424,6 +637,8 @@ pub enum FinalizeError {
     },
     /// Error running the interpreter checks.
     InterpreterCheck(InterpreterCheckError),
+    /// Input index out of bounds.
+    IndexOutOfBounds(IndexOutOfBoundsError),
 }
 
 impl fmt::Display for FinalizeError {
@@ -434,6 +649,7 @@ impl fmt::Display for FinalizeError {
             FinalizeInput { input_index, ref error } =>
                 write_err!(f, "failed to finalize input at index {}", input_index; error),
             InterpreterCheck(ref e) => write_err!(f, "error running the interpreter checks"; e),
+            IndexOutOfBounds(ref e) => write_err!(f, "index out of bounds"; e),
         }
     }
 }
@@ -446,6 +662,7 @@ impl std::error::Error for FinalizeError {
         match *self {
             FinalizeInput { input_index: _, ref error } => Some(error),
             InterpreterCheck(ref error) => Some(error),
+            IndexOutOfBounds(ref error) => Some(error),
         }
     }
 }
@@ -454,6 +671,10 @@ impl From<InterpreterCheckError> for FinalizeError {
     fn from(e: InterpreterCheckError) -> Self { Self::InterpreterCheck(e) }
 }
 
+impl From<IndexOutOfBoundsError> for FinalizeError {
+    fn from(e: IndexOutOfBoundsError) -> Self { Self::IndexOutOfBounds(e) }
+}
+
 /// Error finalizing an input.
 #[derive(Debug)]
 pub enum FinalizeInputError {
@@ -461,6 +682,10 @@ pub enum FinalizeInputError {
     Final(InputError),
     /// Failed to create a finalized input from final fields.
     Input(input::FinalizeError),
+    /// Error running the interpreter checks on the finalized input.
+    InterpreterCheck(InterpreterCheckInputError),
+    /// Failed to aggregate the MuSig2 partial signatures.
+    Musig2(Musig2AggregateError),
 }
 
 impl fmt::Display for FinalizeInputError {
@@ -470,6 +695,8 @@ impl fmt::Display for FinalizeInputError {
         match *self {
             Final(ref e) => write_err!(f, "final"; e),
             Input(ref e) => write_err!(f, "input"; e),
+            InterpreterCheck(ref e) => write_err!(f, "interpreter check"; e),
+            Musig2(ref e) => write_err!(f, "MuSig2 aggregation"; e),
         }
     }
 }
@@ -482,6 +709,8 @@ impl std::error::Error for FinalizeInputError {
         match *self {
             Final(ref e) => Some(e),
             Input(ref e) => Some(e),
+            InterpreterCheck(ref e) => Some(e),
+            Musig2(ref e) => Some(e),
         }
     }
 }
@@ -494,6 +723,14 @@ impl From<input::FinalizeError> for FinalizeInputError {
     fn from(e: input::FinalizeError) -> Self { Self::Input(e) }
 }
 
+impl From<InterpreterCheckInputError> for FinalizeInputError {
+    fn from(e: InterpreterCheckInputError) -> Self { Self::InterpreterCheck(e) }
+}
+
+impl From<Musig2AggregateError> for FinalizeInputError {
+    fn from(e: Musig2AggregateError) -> Self { Self::Musig2(e) }
+}
+
 /// Error type for Pbst Input
 #[derive(Debug)]
 pub enum InputError {
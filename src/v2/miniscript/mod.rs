@@ -18,8 +18,10 @@
 
 //! [BIP-174]: <https://github.com/bitcoin/bips/blob/master/bip-0174.mediawiki>
 
+mod estimate;
 mod finalize;
 mod satisfy;
+mod update;
 
 use core::fmt;
 
@@ -36,7 +38,11 @@ use crate::v2::map::input::Input;
 use crate::v2::{DetermineLockTimeError, Psbt};
 
 #[rustfmt::skip]                // Keep public exports separate.
-pub use self::finalize::{InputError, Finalizer, FinalizeError, FinalizeInputError};
+pub use self::estimate::{EstimateFeeRateError, EstimateInputError, EstimateWeightError};
+pub use self::finalize::{
+    FinalizeError, FinalizeInputError, Finalizer, InputError, NewFinalizerError,
+};
+pub use self::update::{UpdateInputError, UpdateOutputError};
 
 impl Psbt {
     // TODO: Should this be on a Role? Finalizer/Extractor? Then we can remove the debug_assert
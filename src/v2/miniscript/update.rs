@@ -0,0 +1,338 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Implementation of the Updater role using output descriptors.
+//!
+//! > The Updater ... adds information to the PSBT that it has access to. If it has the UTXO for an
+//! > input, it should add it to the PSBT. The Updater should also add redeemScripts,
+//! > witnessScripts, and BIP 32 derivation paths to the input and output data if it knows them.
+//!
+//! With a [`Descriptor`] all of this information can be derived, this module provides the
+//! functions to do so.
+
+use core::fmt;
+
+use bitcoin::bip32::KeySource;
+use bitcoin::key::XOnlyPublicKey;
+use bitcoin::taproot::{ControlBlock, LeafVersion, TapLeafHash, TapNodeHash, TapTree};
+use bitcoin::{secp256k1, ScriptBuf};
+use miniscript::descriptor::{self, DefiniteDescriptorKey, Descriptor};
+
+use crate::error::{write_err, FundingUtxoError};
+use crate::prelude::*;
+use crate::v0::miniscript::{update_item_with_descriptor_helper, PsbtFields};
+use crate::v2::map::input::Input;
+use crate::v2::map::output::Output;
+use crate::v2::{IndexOutOfBoundsError, Psbt, Updater};
+
+impl Updater {
+    /// Updater role, update the input at `input_index` using the descriptor of the UTXO it spends.
+    ///
+    /// See [`Psbt::update_input_with_descriptor`] for details.
+    pub fn update_input_with_descriptor(
+        mut self,
+        input_index: usize,
+        desc: &Descriptor<DefiniteDescriptorKey>,
+    ) -> Result<Updater, UpdateInputError> {
+        self.0.update_input_with_descriptor(input_index, desc)?;
+        Ok(self)
+    }
+
+    /// Updater role, update the output at `output_index` using the descriptor of its `script_pubkey`.
+    ///
+    /// See [`Psbt::update_output_with_descriptor`] for details.
+    pub fn update_output_with_descriptor(
+        mut self,
+        output_index: usize,
+        desc: &Descriptor<DefiniteDescriptorKey>,
+    ) -> Result<Updater, UpdateOutputError> {
+        self.0.update_output_with_descriptor(output_index, desc)?;
+        Ok(self)
+    }
+}
+
+impl Psbt {
+    /// Updates the input at `input_index` using the descriptor of the UTXO it spends.
+    ///
+    /// Populates the `redeem_script`, `witness_script` and `bip32_derivations` fields or, for
+    /// Taproot descriptors, the `tap_internal_key`, `tap_merkle_root`, `tap_scripts` and
+    /// `tap_key_origins` fields, i.e., everything the Signer and Finalizer need.
+    ///
+    /// The descriptor *can* (and should) have extended keys in it so the key origin fields can be
+    /// populated.
+    ///
+    /// # Errors
+    ///
+    /// - If the funding UTXO of the input is missing or inconsistent.
+    /// - If the `script_pubkey` of the funding UTXO does not match the descriptor.
+    /// - If the descriptor can not be transformed into a concrete descriptor.
+    pub fn update_input_with_descriptor(
+        &mut self,
+        input_index: usize,
+        desc: &Descriptor<DefiniteDescriptorKey>,
+    ) -> Result<(), UpdateInputError> {
+        use UpdateInputError::*;
+
+        let input = self.checked_input_mut(input_index)?;
+
+        if let Some(ref tx) = input.non_witness_utxo {
+            if tx.txid() != input.previous_txid {
+                return Err(NonWitnessUtxoTxidMismatch);
+            }
+        }
+
+        let expected_spk = match (&input.witness_utxo, &input.non_witness_utxo) {
+            (Some(witness_utxo), None) => {
+                if desc.desc_type().segwit_version().is_none() {
+                    return Err(MissingNonWitnessUtxo);
+                }
+                witness_utxo.script_pubkey.clone()
+            }
+            (witness_utxo, Some(tx)) => {
+                let vout = input.spent_output_index as usize;
+                let utxo = tx
+                    .output
+                    .get(vout)
+                    .ok_or(FundingUtxoError::OutOfBounds { vout, len: tx.output.len() })?;
+                if let Some(witness_utxo) = witness_utxo {
+                    if witness_utxo != utxo {
+                        return Err(WitnessUtxoMismatch);
+                    }
+                }
+                utxo.script_pubkey.clone()
+            }
+            (None, None) => return Err(FundingUtxoError::MissingUtxo.into()),
+        };
+
+        let (_, spk_check_passed) =
+            update_item_with_descriptor_helper(input, desc, Some(&expected_spk))
+                .map_err(Conversion)?;
+
+        if !spk_check_passed {
+            return Err(MismatchedScriptPubkey);
+        }
+
+        Ok(())
+    }
+
+    /// Updates the output at `output_index` using the descriptor of its `script_pubkey`.
+    ///
+    /// Populates the `redeem_script`, `witness_script` and `bip32_derivations` fields or, for
+    /// Taproot descriptors, the `tap_internal_key`, `tap_tree` and `tap_key_origins` fields. These
+    /// are the fields signing devices use to recognise change outputs.
+    ///
+    /// The descriptor *can* (and should) have extended keys in it so the key origin fields can be
+    /// populated.
+    ///
+    /// # Errors
+    ///
+    /// - If the `script_pubkey` of the output does not match the descriptor.
+    /// - If the descriptor can not be transformed into a concrete descriptor.
+    pub fn update_output_with_descriptor(
+        &mut self,
+        output_index: usize,
+        desc: &Descriptor<DefiniteDescriptorKey>,
+    ) -> Result<(), UpdateOutputError> {
+        use UpdateOutputError::*;
+
+        let output = self.checked_output_mut(output_index)?;
+        let expected_spk = output.script_pubkey.clone();
+
+        let (_, spk_check_passed) =
+            update_item_with_descriptor_helper(output, desc, Some(&expected_spk))
+                .map_err(Conversion)?;
+
+        if !spk_check_passed {
+            return Err(MismatchedScriptPubkey);
+        }
+
+        Ok(())
+    }
+}
+
+impl Input {
+    /// Given the descriptor for a UTXO being spent populate the input's fields so it can be signed.
+    ///
+    /// If the descriptor contains wildcards or otherwise cannot be transformed into a concrete
+    /// descriptor an error will be returned. The descriptor *can* (and should) have extended keys
+    /// in it so fields like `bip32_derivations` and `tap_key_origins` can be populated.
+    ///
+    /// Note that this function doesn't check that the `witness_utxo` or `non_witness_utxo` is
+    /// consistent with the descriptor. To do that see [`Psbt::update_input_with_descriptor`].
+    ///
+    /// # Returns
+    ///
+    /// For convenience, this returns the concrete descriptor that is computed internally to fill
+    /// out the input fields. This can be used to manually check that the `script_pubkey` of the
+    /// funding UTXO is consistent with the descriptor.
+    pub fn update_with_descriptor_unchecked(
+        &mut self,
+        desc: &Descriptor<DefiniteDescriptorKey>,
+    ) -> Result<Descriptor<bitcoin::PublicKey>, descriptor::ConversionError> {
+        let (derived, _) = update_item_with_descriptor_helper(self, desc, None)?;
+        Ok(derived)
+    }
+}
+
+impl Output {
+    /// Given the descriptor for this output populate the output's fields.
+    ///
+    /// If the descriptor contains wildcards or otherwise cannot be transformed into a concrete
+    /// descriptor an error will be returned. The descriptor *can* (and should) have extended keys
+    /// in it so fields like `bip32_derivations` and `tap_key_origins` can be populated.
+    ///
+    /// Note that this function doesn't check that the `script_pubkey` of the output is consistent
+    /// with the descriptor. To do that see [`Psbt::update_output_with_descriptor`].
+    ///
+    /// # Returns
+    ///
+    /// For convenience, this returns the concrete descriptor that is computed internally to fill
+    /// out the output fields. This can be used to manually check that the `script_pubkey` is
+    /// consistent with the descriptor.
+    pub fn update_with_descriptor_unchecked(
+        &mut self,
+        desc: &Descriptor<DefiniteDescriptorKey>,
+    ) -> Result<Descriptor<bitcoin::PublicKey>, descriptor::ConversionError> {
+        let (derived, _) = update_item_with_descriptor_helper(self, desc, None)?;
+        Ok(derived)
+    }
+}
+
+impl PsbtFields for Input {
+    fn redeem_script(&mut self) -> &mut Option<ScriptBuf> { &mut self.redeem_script }
+    fn witness_script(&mut self) -> &mut Option<ScriptBuf> { &mut self.witness_script }
+    fn bip32_derivation(&mut self) -> &mut BTreeMap<secp256k1::PublicKey, KeySource> {
+        &mut self.bip32_derivations
+    }
+    fn tap_internal_key(&mut self) -> &mut Option<XOnlyPublicKey> { &mut self.tap_internal_key }
+    fn tap_key_origins(&mut self) -> &mut BTreeMap<XOnlyPublicKey, (Vec<TapLeafHash>, KeySource)> {
+        &mut self.tap_key_origins
+    }
+
+    fn tap_scripts(&mut self) -> Option<&mut BTreeMap<ControlBlock, (ScriptBuf, LeafVersion)>> {
+        Some(&mut self.tap_scripts)
+    }
+    fn tap_merkle_root(&mut self) -> Option<&mut Option<TapNodeHash>> {
+        Some(&mut self.tap_merkle_root)
+    }
+}
+
+impl PsbtFields for Output {
+    fn redeem_script(&mut self) -> &mut Option<ScriptBuf> { &mut self.redeem_script }
+    fn witness_script(&mut self) -> &mut Option<ScriptBuf> { &mut self.witness_script }
+    fn bip32_derivation(&mut self) -> &mut BTreeMap<secp256k1::PublicKey, KeySource> {
+        &mut self.bip32_derivations
+    }
+    fn tap_internal_key(&mut self) -> &mut Option<XOnlyPublicKey> { &mut self.tap_internal_key }
+    fn tap_key_origins(&mut self) -> &mut BTreeMap<XOnlyPublicKey, (Vec<TapLeafHash>, KeySource)> {
+        &mut self.tap_key_origins
+    }
+
+    fn tap_tree(&mut self) -> Option<&mut Option<TapTree>> { Some(&mut self.tap_tree) }
+}
+
+/// Error updating an input using a descriptor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum UpdateInputError {
+    /// Input index out of bounds.
+    IndexOutOfBounds(IndexOutOfBoundsError),
+    /// The funding UTXO is missing or invalid.
+    FundingUtxo(FundingUtxoError),
+    /// The `non_witness_utxo` txid does not match the input's `previous_txid`.
+    NonWitnessUtxoTxidMismatch,
+    /// The `witness_utxo` does not match the output of the `non_witness_utxo` being spent.
+    WitnessUtxoMismatch,
+    /// Only a `witness_utxo` was provided but the descriptor is not segwit.
+    MissingNonWitnessUtxo,
+    /// Error converting the descriptor into a concrete descriptor.
+    Conversion(descriptor::ConversionError),
+    /// The `script_pubkey` of the funding UTXO does not match the descriptor.
+    MismatchedScriptPubkey,
+}
+
+impl fmt::Display for UpdateInputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use UpdateInputError::*;
+
+        match *self {
+            IndexOutOfBounds(ref e) => write_err!(f, "index out of bounds"; e),
+            FundingUtxo(ref e) => write_err!(f, "input funding utxo error"; e),
+            NonWitnessUtxoTxidMismatch =>
+                f.write_str("non_witness_utxo txid does not match the input previous txid"),
+            WitnessUtxoMismatch =>
+                f.write_str("witness_utxo does not match the non_witness_utxo output being spent"),
+            MissingNonWitnessUtxo =>
+                f.write_str("non-segwit descriptor requires the input to have a non_witness_utxo"),
+            Conversion(ref e) => write_err!(f, "descriptor conversion error"; e),
+            MismatchedScriptPubkey =>
+                f.write_str("the funding utxo script pubkey does not match the descriptor"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for UpdateInputError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use UpdateInputError::*;
+
+        match *self {
+            IndexOutOfBounds(ref e) => Some(e),
+            FundingUtxo(ref e) => Some(e),
+            Conversion(ref e) => Some(e),
+            NonWitnessUtxoTxidMismatch
+            | WitnessUtxoMismatch
+            | MissingNonWitnessUtxo
+            | MismatchedScriptPubkey => None,
+        }
+    }
+}
+
+impl From<IndexOutOfBoundsError> for UpdateInputError {
+    fn from(e: IndexOutOfBoundsError) -> Self { Self::IndexOutOfBounds(e) }
+}
+
+impl From<FundingUtxoError> for UpdateInputError {
+    fn from(e: FundingUtxoError) -> Self { Self::FundingUtxo(e) }
+}
+
+/// Error updating an output using a descriptor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum UpdateOutputError {
+    /// Output index out of bounds.
+    IndexOutOfBounds(IndexOutOfBoundsError),
+    /// Error converting the descriptor into a concrete descriptor.
+    Conversion(descriptor::ConversionError),
+    /// The output's `script_pubkey` does not match the descriptor.
+    MismatchedScriptPubkey,
+}
+
+impl fmt::Display for UpdateOutputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use UpdateOutputError::*;
+
+        match *self {
+            IndexOutOfBounds(ref e) => write_err!(f, "index out of bounds"; e),
+            Conversion(ref e) => write_err!(f, "descriptor conversion error"; e),
+            MismatchedScriptPubkey =>
+                f.write_str("the output script pubkey does not match the descriptor"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for UpdateOutputError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use UpdateOutputError::*;
+
+        match *self {
+            IndexOutOfBounds(ref e) => Some(e),
+            Conversion(ref e) => Some(e),
+            MismatchedScriptPubkey => None,
+        }
+    }
+}
+
+impl From<IndexOutOfBoundsError> for UpdateOutputError {
+    fn from(e: IndexOutOfBoundsError) -> Self { Self::IndexOutOfBounds(e) }
+}
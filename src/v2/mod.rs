@@ -20,14 +20,39 @@
 //!
 //! To combine PSBTs use either `psbt.combine_with(other)` or `v2::combine(this, that)`.
 //!
+//! # Role transitions
+//!
+//! Each role type wraps a [`Psbt`] and the transitions between them consume the current role, so
+//! roles can only be carried out in order:
+//!
+//! ```text
+//! Creator -> Constructor -> Updater -> Signer -> Finalizer -> Extractor
+//! ```
+//!
+//! - `Creator::constructor_modifiable` (and friends) starts construction.
+//! - [`Constructor::updater`] ends construction, the lock time must be determinable and every
+//!   silent payment output must have its script.
+//! - [`Updater::signer`] ends updating, the PSBT must pass [`Psbt::signer_checks`].
+//! - [`Signer::sign`] returns the signed [`Psbt`], use `Psbt::into_finalizer` (requires
+//!   "miniscript" feature) which requires every input to have signature data or already be
+//!   finalized.
+//! - `Finalizer::finalize` returns the finalized [`Psbt`], use [`Psbt::into_extractor`].
+//!
+//! A [`Psbt`] received from another party can enter at any role using `Psbt::into_updater` etc.,
+//! these run the same checks as the role's `new` function.
+//!
 //! [BIP-174]: <https://github.com/bitcoin/bips/blob/master/bip-0174.mediawiki>
 //! [BIP-370]: <https://github.com/bitcoin/bips/blob/master/bip-0370.mediawiki>
 
+mod bump;
+mod cpfp;
 mod error;
 mod extract;
 mod map;
 #[cfg(feature = "miniscript")]
 mod miniscript;
+pub mod payjoin;
+mod psbt_ref;
 
 use core::fmt;
 use core::marker::PhantomData;
@@ -36,23 +61,37 @@ use std::collections::{HashMap, HashSet};
 
 use bitcoin::bip32::{self, KeySource, Xpriv};
 use bitcoin::hashes::Hash;
-use bitcoin::key::{PrivateKey, PublicKey};
+use bitcoin::key::{Keypair, PrivateKey, PublicKey, TapTweak};
 use bitcoin::locktime::absolute;
-use bitcoin::secp256k1::{Message, Secp256k1, Signing};
-use bitcoin::sighash::{EcdsaSighashType, SighashCache};
-use bitcoin::{ecdsa, transaction, Amount, Sequence, Transaction, TxOut, Txid};
+use bitcoin::secp256k1::{self, Message, Scalar, Secp256k1, Signing, Verification};
+use bitcoin::sighash::{
+    EcdsaSighashType, LegacySighash, Prevouts, SegwitV0Sighash, SighashCache, TapSighash,
+    TapSighashType,
+};
+use bitcoin::taproot::{self, TapLeafHash, TapTweakHash};
+use bitcoin::{
+    consensus, ecdsa, transaction, Amount, OutPoint, Script, ScriptBuf, Sequence, Transaction,
+    TxOut, Txid,
+};
 
 use crate::error::{write_err, FeeError, FundingUtxoError};
+use crate::io::{self, Read as _};
+use crate::io_ext::CountingReader;
 use crate::prelude::*;
 use crate::v2::map::Map;
+use crate::{musig2, silent_payments, v0};
 
 #[rustfmt::skip]                // Keep public exports separate.
 #[doc(inline)]
 pub use self::{
+    bump::{BumpFeeError, FeeBumper, NewFeeBumperError},
+    cpfp::{BuildChildError, ChildBuilder, NewChildBuilderError},
     error::{
-        DeserializeError, DetermineLockTimeError, IndexOutOfBoundsError, InputsNotModifiableError,
+        DeserializeError, DetermineLockTimeError, EndConstructionError, FromV0Error,
+        IndexOutOfBoundsError, InputsNotModifiableError, Musig2AggregateError, NewSignerError,
         NotUnsignedError, OutputsNotModifiableError, PartialSigsSighashTypeError,
-        PsbtNotModifiableError, SignError,
+        PsbtNotModifiableError, RemoveError, SignError, SignerChecksError, SilentPaymentError,
+        SilentPaymentPendingError,
     },
     extract::{Extractor, ExtractError, ExtractTxError, ExtractTxFeeRateError},
     map::{
@@ -61,20 +100,47 @@ pub use self::{
         input::{self, Input, InputBuilder},
         output::{self, Output, OutputBuilder},
     },
+    psbt_ref::{FieldError, InputRef, MapRef, OutputRef, PairRef, Pairs, PsbtRef, PsbtRefError},
 };
 #[cfg(feature = "base64")]
 pub use self::display_from_str::ParsePsbtError;
 #[cfg(feature = "miniscript")]
 pub use self::miniscript::{
-    FinalizeError, FinalizeInputError, Finalizer, InputError, InterpreterCheckError,
-    InterpreterCheckInputError,
+    EstimateFeeRateError, EstimateInputError, EstimateWeightError, FinalizeError,
+    FinalizeInputError, Finalizer, InputError, InterpreterCheckError, InterpreterCheckInputError,
+    NewFinalizerError, UpdateInputError, UpdateOutputError,
 };
 
+pub(crate) const MAGIC_BYTES: &[u8] = b"psbt";
+pub(crate) const PSBT_SERPARATOR: u8 = 0xff_u8;
+
 /// Combines these two PSBTs as described by BIP-174 (i.e. combine is the same for BIP-370).
 ///
 /// This function is commutative `combine(this, that) = combine(that, this)`.
 pub fn combine(this: Psbt, that: Psbt) -> Result<Psbt, CombineError> { this.combine_with(that) }
-// TODO: Consider adding an iterator API that combines a list of PSBTs.
+
+/// Combines all the `psbts` as described by BIP-174 (i.e. combine is the same for BIP-370).
+///
+/// Useful for coordinators that collect many cosigner responses. Errors if `psbts` is empty.
+pub fn combine_all<I: IntoIterator<Item = Psbt>>(psbts: I) -> Result<Psbt, CombineError> {
+    let mut psbts = psbts.into_iter();
+    let first = psbts.next().ok_or(CombineError::NoPsbts)?;
+    psbts.try_fold(first, Psbt::combine_with)
+}
+
+/// Joins the inputs and outputs of two PSBTs built by separate Constructors.
+///
+/// See [`Psbt::join_with`].
+pub fn join(this: Psbt, that: Psbt) -> Result<Psbt, JoinError> { this.join_with(that) }
+
+/// Joins the inputs and outputs of all the `psbts`, in order. Errors if `psbts` is empty.
+///
+/// Useful for coordinators that collect contributions from many Constructors.
+pub fn join_all<I: IntoIterator<Item = Psbt>>(psbts: I) -> Result<Psbt, JoinError> {
+    let mut psbts = psbts.into_iter();
+    let first = psbts.next().ok_or(JoinError::NoPsbts)?;
+    psbts.try_fold(first, Psbt::join_with)
+}
 
 /// Implements the BIP-370 Creator role.
 ///
@@ -264,14 +330,26 @@ impl<T: Mod> Constructor<T> {
     }
 
     /// Marks that the `Psbt` can not have any more outputs added to it.
-    pub fn no_more_outputs(mut self) -> Self {
+    ///
+    /// Fails if a silent payment output does not have its script yet, the outputs are marked as
+    /// not modifiable when the scripts are computed (see [`Updater::compute_silent_payment_scripts`]).
+    pub fn no_more_outputs(mut self) -> Result<Self, SilentPaymentPendingError> {
+        if let Some(output_index) =
+            self.0.outputs.iter().position(|output| output.is_silent_payment_pending())
+        {
+            return Err(SilentPaymentPendingError { output_index });
+        }
         self.0.global.clear_outputs_modifiable_flag();
-        self
+        Ok(self)
     }
 
     /// Returns a PSBT [`Updater`] once construction is completed.
-    pub fn updater(self) -> Result<Updater, DetermineLockTimeError> {
-        self.no_more_inputs().no_more_outputs().psbt().map(Updater)
+    ///
+    /// Fails if a silent payment output does not have its script yet, in which case use
+    /// `no_more_inputs` and [`Self::psbt`] to get the [`Psbt`] the scripts are computed for.
+    pub fn updater(self) -> Result<Updater, EndConstructionError> {
+        let psbt = self.no_more_inputs().no_more_outputs()?.psbt()?;
+        Ok(Updater(psbt))
     }
 
     /// Returns the [`Psbt`] in its current state.
@@ -302,18 +380,71 @@ impl Constructor<Modifiable> {
     }
 
     /// Adds an input to the PSBT.
+    ///
+    /// The input is appended so the index of existing inputs, and hence the pairing of any input
+    /// signed using `SIGHASH_SINGLE`, does not change.
     pub fn input(mut self, input: Input) -> Self {
         self.0.inputs.push(input);
         self.0.global.input_count += 1;
         self
     }
 
+    /// Removes the input at `input_index` from the PSBT.
+    ///
+    /// Fails if removing the input would change the index of an input signed using
+    /// `SIGHASH_SINGLE`, since that would break the pairing with its output.
+    pub fn remove_input(mut self, input_index: usize) -> Result<Self, RemoveError> {
+        self.0.remove_input(input_index)?;
+        Ok(self)
+    }
+
+    /// Removes all inputs spending `out_point` from the PSBT, if there are none this is a no-op.
+    ///
+    /// Fails if removing the inputs would change the index of an input signed using
+    /// `SIGHASH_SINGLE`, since that would break the pairing with its output.
+    pub fn remove_inputs_spending(mut self, out_point: &OutPoint) -> Result<Self, RemoveError> {
+        self.0.remove_inputs_spending(out_point)?;
+        Ok(self)
+    }
+
     /// Adds an output to the PSBT.
+    ///
+    /// The output is appended so the index of existing outputs, and hence the pairing of any input
+    /// signed using `SIGHASH_SINGLE`, does not change.
     pub fn output(mut self, output: Output) -> Self {
         self.0.outputs.push(output);
         self.0.global.output_count += 1;
         self
     }
+
+    /// Removes the output at `output_index` from the PSBT.
+    ///
+    /// Fails if the output is paired with, or removing it would change the index of the output
+    /// paired with, an input signed using `SIGHASH_SINGLE`.
+    pub fn remove_output(mut self, output_index: usize) -> Result<Self, RemoveError> {
+        self.0.remove_output(output_index)?;
+        Ok(self)
+    }
+
+    /// Removes all outputs paying to `script_pubkey` from the PSBT, if there are none this is a no-op.
+    ///
+    /// Fails if any of the outputs are paired with, or removing them would change the index of the
+    /// output paired with, an input signed using `SIGHASH_SINGLE`.
+    pub fn remove_outputs_paying_to(mut self, script_pubkey: &Script) -> Result<Self, RemoveError> {
+        self.0.remove_outputs_paying_to(script_pubkey)?;
+        Ok(self)
+    }
+
+    /// Adds an input and an output to the PSBT at the same index.
+    ///
+    /// Use this to add an input that is to be signed using `SIGHASH_SINGLE` along with the output
+    /// it commits to. As described in BIP-370 the pair is inserted such that the same number of
+    /// inputs and outputs come before any existing input signed using `SIGHASH_SINGLE` and its
+    /// output, i.e., all existing pairings remain valid.
+    pub fn input_output_pair(mut self, input: Input, output: Output) -> Self {
+        self.0.insert_input_output_pair(input, output);
+        self
+    }
 }
 // Useful if the Creator and Constructor are a single entity.
 impl Default for Constructor<Modifiable> {
@@ -335,11 +466,32 @@ impl Constructor<InputsOnlyModifiable> {
     }
 
     /// Adds an input to the PSBT.
+    ///
+    /// The input is appended so the index of existing inputs, and hence the pairing of any input
+    /// signed using `SIGHASH_SINGLE`, does not change.
     pub fn input(mut self, input: Input) -> Self {
         self.0.inputs.push(input);
         self.0.global.input_count += 1;
         self
     }
+
+    /// Removes the input at `input_index` from the PSBT.
+    ///
+    /// Fails if removing the input would change the index of an input signed using
+    /// `SIGHASH_SINGLE`, since that would break the pairing with its output.
+    pub fn remove_input(mut self, input_index: usize) -> Result<Self, RemoveError> {
+        self.0.remove_input(input_index)?;
+        Ok(self)
+    }
+
+    /// Removes all inputs spending `out_point` from the PSBT, if there are none this is a no-op.
+    ///
+    /// Fails if removing the inputs would change the index of an input signed using
+    /// `SIGHASH_SINGLE`, since that would break the pairing with its output.
+    pub fn remove_inputs_spending(mut self, out_point: &OutPoint) -> Result<Self, RemoveError> {
+        self.0.remove_inputs_spending(out_point)?;
+        Ok(self)
+    }
 }
 
 // Useful if the Creator and Constructor are a single entity.
@@ -362,11 +514,32 @@ impl Constructor<OutputsOnlyModifiable> {
     }
 
     /// Adds an output to the PSBT.
+    ///
+    /// The output is appended so the index of existing outputs, and hence the pairing of any input
+    /// signed using `SIGHASH_SINGLE`, does not change.
     pub fn output(mut self, output: Output) -> Self {
         self.0.outputs.push(output);
         self.0.global.output_count += 1;
         self
     }
+
+    /// Removes the output at `output_index` from the PSBT.
+    ///
+    /// Fails if the output is paired with, or removing it would change the index of the output
+    /// paired with, an input signed using `SIGHASH_SINGLE`.
+    pub fn remove_output(mut self, output_index: usize) -> Result<Self, RemoveError> {
+        self.0.remove_output(output_index)?;
+        Ok(self)
+    }
+
+    /// Removes all outputs paying to `script_pubkey` from the PSBT, if there are none this is a no-op.
+    ///
+    /// Fails if any of the outputs are paired with, or removing them would change the index of the
+    /// output paired with, an input signed using `SIGHASH_SINGLE`.
+    pub fn remove_outputs_paying_to(mut self, script_pubkey: &Script) -> Result<Self, RemoveError> {
+        self.0.remove_outputs_paying_to(script_pubkey)?;
+        Ok(self)
+    }
 }
 
 // Useful if the Creator and Constructor are a single entity.
@@ -405,18 +578,31 @@ impl Updater {
         Ok(self)
     }
 
-    // /// Converts the inner PSBT v2 to a PSBT v0.
-    // pub fn into_psbt_v0(self) -> v0::Psbt {
-    //     let unsigned_tx =
-    //         self.0.unsigned_tx().expect("Updater guarantees lock time can be determined");
-    //     let psbt = self.psbt();
+    /// Updater role, computes the scripts of the silent payment outputs.
+    ///
+    /// Requires the ECDH shares of all eligible inputs (or a global share) for every scan key, each
+    /// with a valid DLEQ proof. The inputs and outputs are marked as not modifiable once the
+    /// scripts are computed.
+    pub fn compute_silent_payment_scripts<C: Verification>(
+        mut self,
+        secp: &Secp256k1<C>,
+    ) -> Result<Updater, SilentPaymentError> {
+        self.0.compute_silent_payment_scripts(secp)?;
+        Ok(self)
+    }
 
-    //     let global = psbt.global.into_v0(unsigned_tx);
-    //     let inputs = psbt.inputs.into_iter().map(|input| input.into_v0()).collect();
-    //     let outputs = psbt.outputs.into_iter().map(|output| output.into_v0()).collect();
+    /// Converts the inner PSBT v2 to a PSBT v0.
+    pub fn into_psbt_v0(self) -> v0::Psbt {
+        self.0.into_v0().expect("Updater guarantees lock time can be determined")
+    }
 
-    //     v0::Psbt { global, inputs, outputs }
-    // }
+    /// Returns a PSBT [`Signer`] once updating is completed.
+    ///
+    /// Fails if the updated PSBT does not pass [`Psbt::signer_checks`].
+    pub fn signer(self) -> Result<Signer, SignerChecksError> {
+        self.0.signer_checks()?;
+        Ok(Signer(self.0))
+    }
 
     /// Returns the inner [`Psbt`].
     pub fn psbt(self) -> Psbt { self.0 }
@@ -437,9 +623,11 @@ pub struct Signer(Psbt);
 impl Signer {
     /// Creates a `Signer`.
     ///
-    /// An updater can only update a PSBT that has a valid combination of lock times.
-    pub fn new(psbt: Psbt) -> Result<Self, DetermineLockTimeError> {
+    /// A signer can only sign a PSBT that has a valid combination of lock times and that passes
+    /// [`Psbt::signer_checks`].
+    pub fn new(psbt: Psbt) -> Result<Self, NewSignerError> {
         let _ = psbt.determine_lock_time()?;
+        psbt.signer_checks()?;
         Ok(Self(psbt))
     }
 
@@ -453,11 +641,13 @@ impl Signer {
 
     /// Attempts to create _all_ the required signatures for this PSBT using `k`.
     ///
-    /// **NOTE**: Taproot inputs are, as yet, not supported by this function. We currently only
-    /// attempt to sign ECDSA inputs.
+    /// ECDSA inputs are signed using the keys in `bip32_derivations`. Taproot inputs are signed
+    /// using the keys in `tap_key_origins`, a key path signature is created for `tap_internal_key`
+    /// (tweaked with `tap_merkle_root`) and script path signatures are created for every leaf hash
+    /// associated with a key.
     ///
-    /// If you just want to sign an input with one specific key consider using `sighash_ecdsa`. This
-    /// function does not support scripts that contain `OP_CODESEPARATOR`.
+    /// If you just want to sign an input with one specific key consider using `sighash_ecdsa` or
+    /// `sighash_taproot`. This function does not support scripts that contain `OP_CODESEPARATOR`.
     ///
     /// # Returns
     ///
@@ -473,7 +663,7 @@ impl Signer {
         secp: &Secp256k1<C>,
     ) -> Result<(Psbt, SigningKeys), (SigningKeys, SigningErrors)>
     where
-        C: Signing,
+        C: Signing + Verification,
         K: GetKey,
     {
         let tx = self.unsigned_tx();
@@ -490,6 +680,14 @@ impl Signer {
         self.0.clear_tx_modifiable(ty as u8)
     }
 
+    /// Sets the PSBT_GLOBAL_TX_MODIFIABLE as required after signing a Taproot input.
+    ///
+    /// > For PSBTv2s, a signer must update the PSBT_GLOBAL_TX_MODIFIABLE field after signing
+    /// > inputs so that it accurately reflects the state of the PSBT.
+    pub fn taproot_clear_tx_modifiable(&mut self, ty: TapSighashType) {
+        self.0.clear_tx_modifiable(ty as u8)
+    }
+
     /// Returns the inner [`Psbt`].
     pub fn psbt(self) -> Psbt { self.0 }
 }
@@ -508,7 +706,14 @@ pub struct Psbt {
 }
 
 impl Psbt {
-    // TODO: Add inherent methods to get each of the role types.
+    /// Returns an [`Updater`] for this PSBT, see [`Updater::new`].
+    pub fn into_updater(self) -> Result<Updater, DetermineLockTimeError> { Updater::new(self) }
+
+    /// Returns a [`Signer`] for this PSBT, see [`Signer::new`].
+    pub fn into_signer(self) -> Result<Signer, NewSignerError> { Signer::new(self) }
+
+    /// Returns an [`Extractor`] for this PSBT, see [`Extractor::new`].
+    pub fn into_extractor(self) -> Result<Extractor, ExtractError> { Extractor::new(self) }
 
     /// Returns this PSBT's unique identification.
     fn id(&self) -> Result<Txid, DetermineLockTimeError> {
@@ -534,6 +739,76 @@ impl Psbt {
         })
     }
 
+    /// Creates a PSBT v2 from a PSBT v0.
+    ///
+    /// The unsigned transaction is split into the per-input and per-output fields, its lock time
+    /// becomes the fallback lock time. All other input and output fields (including any signatures)
+    /// are moved across as is.
+    ///
+    /// PSBT v0 has no equivalent of `PSBT_GLOBAL_TX_MODIFIABLE` so the caller must provide the
+    /// `tx_modifiable_flags` as defined by BIP-370 e.g., `0x01` if inputs can be added or `0x00` if
+    /// the transaction is not modifiable. The flags are then updated for the sighash type of every signature already
+    /// present, as a Signer does when adding them, so existing signatures can not be invalidated.
+    ///
+    /// # Errors
+    ///
+    /// If the number of input or output maps does not match the unsigned transaction.
+    pub fn from_v0(psbt: v0::Psbt, tx_modifiable_flags: u8) -> Result<Self, FromV0Error> {
+        let tx_inputs = psbt.unsigned_tx.input.len();
+        if psbt.inputs.len() != tx_inputs {
+            return Err(FromV0Error::InputCountMismatch {
+                tx_inputs,
+                input_maps: psbt.inputs.len(),
+            });
+        }
+        let tx_outputs = psbt.unsigned_tx.output.len();
+        if psbt.outputs.len() != tx_outputs {
+            return Err(FromV0Error::OutputCountMismatch {
+                tx_outputs,
+                output_maps: psbt.outputs.len(),
+            });
+        }
+
+        let v0::Psbt { unsigned_tx, xpub, proprietary, unknown, inputs, outputs, .. } = psbt;
+        let global = Global::from_v0(&unsigned_tx, xpub, proprietary, unknown, tx_modifiable_flags);
+
+        let inputs = inputs
+            .into_iter()
+            .zip(unsigned_tx.input.iter())
+            .map(|(input, tx_in)| Input::from_v0(input, tx_in))
+            .collect();
+        let outputs = outputs
+            .into_iter()
+            .zip(unsigned_tx.output.iter())
+            .map(|(output, tx_out)| Output::from_v0(output, tx_out))
+            .collect();
+
+        let mut psbt = Psbt { global, inputs, outputs };
+        let sighash_types: Vec<u8> = psbt.inputs.iter().flat_map(Input::sighash_types).collect();
+        for ty in sighash_types {
+            psbt.clear_tx_modifiable(ty);
+        }
+        Ok(psbt)
+    }
+
+    /// Converts this PSBT v2 to a PSBT v0.
+    ///
+    /// The unsigned transaction is built using the lock time returned by
+    /// [`Self::determine_lock_time`], all other input and output fields are moved across as is.
+    /// Fields that have no v0 equivalent (e.g. `tx_modifiable_flags`) are dropped.
+    ///
+    /// # Errors
+    ///
+    /// If the lock time can not be determined for this PSBT.
+    pub fn into_v0(self) -> Result<v0::Psbt, DetermineLockTimeError> {
+        let unsigned_tx = self.unsigned_tx()?;
+
+        let inputs = self.inputs.into_iter().map(|input| input.into_v0()).collect();
+        let outputs = self.outputs.into_iter().map(|output| output.into_v0()).collect();
+
+        Ok(self.global.into_v0(unsigned_tx, inputs, outputs))
+    }
+
     /// Determines the lock time as specified in [BIP-370] if it is possible to do so.
     ///
     /// [BIP-370]: <https://github.com/bitcoin/bips/blob/master/bip-0370.mediawiki#determining-lock-time>
@@ -593,49 +868,77 @@ impl Psbt {
     /// Serialize as raw binary data
     pub fn serialize(&self) -> Vec<u8> {
         let mut buf: Vec<u8> = Vec::new();
+        self.encode(&mut buf).expect("in-memory writers don't error");
+        buf
+    }
 
+    /// Encodes this PSBT to `w`, writing each key-value pair directly to the writer.
+    ///
+    /// Returns the number of bytes written.
+    pub fn encode<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<usize> {
         //  <magic>
-        buf.extend_from_slice(b"psbt");
-
-        buf.push(0xff_u8);
+        w.write_all(MAGIC_BYTES)?;
+        w.write_all(&[PSBT_SERPARATOR])?;
+        let mut len = MAGIC_BYTES.len() + 1;
 
-        buf.extend(self.global.serialize_map());
+        len += self.global.encode_map(w)?;
 
         for i in &self.inputs {
-            buf.extend(i.serialize_map());
+            len += i.encode_map(w)?;
         }
 
         for i in &self.outputs {
-            buf.extend(i.serialize_map());
+            len += i.encode_map(w)?;
         }
 
-        buf
+        Ok(len)
     }
 
     /// Deserialize a value from raw binary data.
     pub fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
         use DeserializeError::*;
 
-        const MAGIC_BYTES: &[u8] = b"psbt";
         if bytes.get(0..MAGIC_BYTES.len()) != Some(MAGIC_BYTES) {
             return Err(InvalidMagic);
         }
-
-        const PSBT_SERPARATOR: u8 = 0xff_u8;
         if bytes.get(MAGIC_BYTES.len()) != Some(&PSBT_SERPARATOR) {
             return Err(InvalidSeparator);
         }
 
-        let mut d = bytes.get(5..).ok_or(NoMorePairs)?;
+        let mut d = bytes;
+        Psbt::decode(&mut d).map(|(psbt, _)| psbt)
+    }
 
-        let global = Global::decode(&mut d)?;
+    /// Decodes a PSBT from `r`, reading no further than the end of the last output map.
+    ///
+    /// Returns the PSBT along with the number of bytes consumed from `r`, allowing a PSBT to be
+    /// parsed from a stream or from a larger buffer without knowing its length up front.
+    pub fn decode<R: io::Read + ?Sized>(r: &mut R) -> Result<(Self, usize), DeserializeError> {
+        use DeserializeError::*;
 
+        let mut r = CountingReader::new(r);
+
+        let mut magic = [0_u8; 4];
+        r.read_exact(&mut magic)?;
+        if magic[..] != *MAGIC_BYTES {
+            return Err(InvalidMagic);
+        }
+
+        let mut separator = [0_u8; 1];
+        r.read_exact(&mut separator)?;
+        if separator[0] != PSBT_SERPARATOR {
+            return Err(InvalidSeparator);
+        }
+
+        let global = Global::decode(&mut r)?;
+
+        // Do not trust the counts read from the stream for allocation.
         let inputs: Vec<Input> = {
             let inputs_len: usize = global.input_count;
-            let mut inputs: Vec<Input> = Vec::with_capacity(inputs_len);
+            let mut inputs: Vec<Input> = vec![];
 
             for _ in 0..inputs_len {
-                inputs.push(Input::decode(&mut d)?);
+                inputs.push(Input::decode(&mut r)?);
             }
 
             inputs
@@ -643,16 +946,16 @@ impl Psbt {
 
         let outputs: Vec<Output> = {
             let outputs_len: usize = global.output_count;
-            let mut outputs: Vec<Output> = Vec::with_capacity(outputs_len);
+            let mut outputs: Vec<Output> = vec![];
 
             for _ in 0..outputs_len {
-                outputs.push(Output::decode(&mut d)?)
+                outputs.push(Output::decode(&mut r)?)
             }
 
             outputs
         };
 
-        Ok(Psbt { global, inputs, outputs })
+        Ok((Psbt { global, inputs, outputs }, r.count()))
     }
 
     /// Returns an iterator for the funding UTXOs of the psbt
@@ -669,12 +972,33 @@ impl Psbt {
 
     /// Combines this [`Psbt`] with `other` PSBT as described by BIP-174.
     ///
-    /// BIP-370 does not include any additional requirements for the Combiner role.
+    /// BIP-370 does not include any additional requirements for the Combiner role, however both
+    /// PSBTs must describe the same transaction. We check that the input and output counts match
+    /// and that both PSBTs have the same unique identification.
     ///
     /// This function is commutative `A.combine_with(B) = B.combine_with(A)`.
     ///
     /// See [`combine()`] for a non-consuming version of this function.
     pub fn combine_with(mut self, other: Self) -> Result<Psbt, CombineError> {
+        use CombineError::*;
+
+        self.check_map_counts()?;
+        other.check_map_counts()?;
+
+        let (this, that) = (self.inputs.len(), other.inputs.len());
+        if this != that {
+            return Err(InputCountMismatch { this, that });
+        }
+        let (this, that) = (self.outputs.len(), other.outputs.len());
+        if this != that {
+            return Err(OutputCountMismatch { this, that });
+        }
+
+        let (this, that) = (self.id()?, other.id()?);
+        if this != that {
+            return Err(IdMismatch { this, that });
+        }
+
         self.global.combine(other.global)?;
 
         for (self_input, other_input) in self.inputs.iter_mut().zip(other.inputs.into_iter()) {
@@ -688,6 +1012,137 @@ impl Psbt {
         Ok(self)
     }
 
+    /// Checks that the global input and output counts match the number of maps.
+    fn check_map_counts(&self) -> Result<(), CombineError> {
+        let (count, maps) = (self.global.input_count, self.inputs.len());
+        if count != maps {
+            return Err(CombineError::InputMapCountMismatch {
+                input_count: count,
+                input_maps: maps,
+            });
+        }
+        let (count, maps) = (self.global.output_count, self.outputs.len());
+        if count != maps {
+            return Err(CombineError::OutputMapCountMismatch {
+                output_count: count,
+                output_maps: maps,
+            });
+        }
+        Ok(())
+    }
+
+    /// Joins the inputs and outputs of `other` into this PSBT.
+    ///
+    /// BIP-370 allows several Constructors to add inputs and outputs independently, unlike
+    /// [`Psbt::combine_with`] the PSBTs need not describe the same transaction. The inputs and
+    /// outputs of `other` are appended, inputs spending an outpoint already spent by this PSBT are
+    /// combined instead. Inputs of `other` signed using `SIGHASH_SINGLE` are inserted along with
+    /// their outputs so that all pairings remain valid, every such input of either PSBT must
+    /// have an output at the same index.
+    ///
+    /// If either PSBT gains inputs (outputs) it must have the inputs (outputs) modifiable flag set.
+    /// The joined PSBT is only modifiable if both are. The transaction versions must be equal and
+    /// the fallback lock times must be equal if both are set.
+    ///
+    /// Global silent payment ECDH shares are removed if the inputs change since they no longer
+    /// cover all inputs.
+    pub fn join_with(mut self, mut other: Self) -> Result<Psbt, JoinError> {
+        use JoinError::*;
+
+        if self.global.tx_version != other.global.tx_version {
+            return Err(TxVersionMismatch {
+                this: self.global.tx_version,
+                that: other.global.tx_version,
+            });
+        }
+        let fallback_lock_time =
+            match (self.global.fallback_lock_time, other.global.fallback_lock_time) {
+                (Some(this), Some(that)) if this != that =>
+                    return Err(FallbackLockTimeMismatch { this, that }),
+                (this, that) => this.or(that),
+            };
+
+        // The pairs are inserted before the first input without an output, every input signed
+        // using SIGHASH_SINGLE must come before it to keep its index.
+        if let Some(input_index) =
+            self.sighash_single_inputs().find(|&index| index >= self.outputs.len())
+        {
+            return Err(MissingSighashSingleOutput { input_index, in_this: true });
+        }
+        let other_sighash_single: Vec<usize> = other.sighash_single_inputs().collect();
+        if let Some(&input_index) =
+            other_sighash_single.iter().find(|&&index| index >= other.outputs.len())
+        {
+            return Err(MissingSighashSingleOutput { input_index, in_this: false });
+        }
+        let (this_count, that_count) = (self.inputs.len(), other.inputs.len());
+
+        let (mut inputs, mut pairs) = (vec![], vec![]);
+        for (index, input) in other.inputs.drain(..).enumerate() {
+            let out_point = input.out_point();
+            match self.inputs.iter().position(|input| input.out_point() == out_point) {
+                Some(_) if other_sighash_single.contains(&index) =>
+                    return Err(DuplicateSighashSingleInput { input_index: index }),
+                Some(i) => self.inputs[i].combine(input)?,
+                None if other_sighash_single.contains(&index) => pairs.push((index, input)),
+                None => inputs.push(input),
+            }
+        }
+        let added = inputs.len() + pairs.len();
+        let duplicates = that_count - added;
+
+        // This PSBT gains the inputs of `other` that are not duplicates and vice versa.
+        let this_gains_inputs = added > 0;
+        let that_gains_inputs = this_count > duplicates;
+        if (this_gains_inputs && !self.global.is_inputs_modifiable())
+            || (that_gains_inputs && !other.global.is_inputs_modifiable())
+        {
+            return Err(InputsNotModifiable(InputsNotModifiableError));
+        }
+        if (!other.outputs.is_empty() && !self.global.is_outputs_modifiable())
+            || (!self.outputs.is_empty() && !other.global.is_outputs_modifiable())
+        {
+            return Err(OutputsNotModifiable(OutputsNotModifiableError));
+        }
+
+        let mut outputs: Vec<Option<Output>> = other.outputs.drain(..).map(Some).collect();
+        for (index, input) in pairs {
+            let output = outputs[index].take().expect("output index checked above");
+            self.insert_input_output_pair(input, output);
+        }
+        self.global.input_count += inputs.len();
+        self.inputs.extend(inputs);
+        let outputs: Vec<Output> = outputs.into_iter().flatten().collect();
+        self.global.output_count += outputs.len();
+        self.outputs.extend(outputs);
+
+        let inputs_modifiable =
+            self.global.is_inputs_modifiable() && other.global.is_inputs_modifiable();
+        let outputs_modifiable =
+            self.global.is_outputs_modifiable() && other.global.is_outputs_modifiable();
+        let sighash_single = self.global.has_sighash_single() || other.global.has_sighash_single();
+        if this_gains_inputs || that_gains_inputs {
+            self.global.sp_ecdh_shares.clear();
+            self.global.sp_dleq_proofs.clear();
+            other.global.sp_ecdh_shares.clear();
+            other.global.sp_dleq_proofs.clear();
+        }
+        self.global.combine(other.global)?;
+        self.global.fallback_lock_time = fallback_lock_time;
+        if !inputs_modifiable {
+            self.global.clear_inputs_modifiable_flag();
+        }
+        if !outputs_modifiable {
+            self.global.clear_outputs_modifiable_flag();
+        }
+        if sighash_single {
+            self.global.set_sighash_single_flag();
+        }
+
+        self.determine_lock_time()?;
+        Ok(self)
+    }
+
     /// Sets the PSBT_GLOBAL_TX_MODIFIABLE as required after signing.
     // TODO: Consider using consts instead of magic numbers.
     fn clear_tx_modifiable(&mut self, sighash_type: u8) {
@@ -711,13 +1166,287 @@ impl Psbt {
         }
     }
 
+    /// Checks that the inputs of this PSBT are consistent before signing them.
+    ///
+    /// For each input checks:
+    ///
+    /// - The `non_witness_utxo` txid matches the input's `previous_txid`.
+    /// - The `witness_utxo` matches the output of the `non_witness_utxo` being spent.
+    /// - An input with only a `witness_utxo` does not require a non-witness signature.
+    /// - The `redeem_script` and `witness_script` hash to the funding `scriptPubkey`.
+    /// - The `tap_internal_key` tweaked with `tap_merkle_root` matches the funding `scriptPubkey`.
+    ///
+    /// Inputs without a funding utxo are only rejected if they have scripts or keys to check. Also
+    /// checks that no silent payment output is waiting for its script, see
+    /// [`Updater::compute_silent_payment_scripts`].
+    pub fn signer_checks(&self) -> Result<(), SignerChecksError> {
+        use SignerChecksError::*;
+
+        if let Some(output_index) =
+            self.outputs.iter().position(|output| output.is_silent_payment_pending())
+        {
+            return Err(SilentPaymentPending(SilentPaymentPendingError { output_index }));
+        }
+
+        let secp = Secp256k1::verification_only();
+
+        for (input_index, input) in self.inputs.iter().enumerate() {
+            if let Some(ref tx) = input.non_witness_utxo {
+                if tx.txid() != input.previous_txid {
+                    return Err(NonWitnessUtxoTxidMismatch { input_index });
+                }
+            }
+
+            let utxo = match input.funding_utxo() {
+                Ok(utxo) => utxo,
+                Err(FundingUtxoError::MissingUtxo)
+                    if input.redeem_script.is_none()
+                        && input.witness_script.is_none()
+                        && input.tap_internal_key.is_none()
+                        && input.tap_merkle_root.is_none() =>
+                    continue,
+                Err(error) => return Err(FundingUtxo { input_index, error }),
+            };
+            let spk = &utxo.script_pubkey;
+
+            match input.non_witness_utxo {
+                Some(ref tx) => {
+                    let vout = input.spent_output_index as usize;
+                    match tx.output.get(vout) {
+                        Some(spent) if spent == utxo => {}
+                        Some(_) => return Err(WitnessUtxoMismatch { input_index }),
+                        None => {
+                            let error =
+                                FundingUtxoError::OutOfBounds { vout, len: tx.output.len() };
+                            return Err(FundingUtxo { input_index, error });
+                        }
+                    }
+                }
+                None => {
+                    // A P2SH input is only known to be non-witness once we have the redeem script.
+                    let non_witness = !(spk.is_witness_program() || spk.is_p2sh())
+                        || (spk.is_p2sh()
+                            && input
+                                .redeem_script
+                                .as_ref()
+                                .map_or(false, |s| !s.is_witness_program()));
+                    if non_witness {
+                        return Err(NonWitnessSig { input_index });
+                    }
+                }
+            }
+
+            if let Some(ref redeem_script) = input.redeem_script {
+                if ScriptBuf::new_p2sh(&redeem_script.script_hash()) != *spk {
+                    return Err(RedeemScriptMismatch { input_index });
+                }
+            }
+
+            if let Some(ref witness_script) = input.witness_script {
+                let p2wsh = ScriptBuf::new_p2wsh(&witness_script.wscript_hash());
+                if spk.is_p2sh() {
+                    if input.redeem_script.as_ref() != Some(&p2wsh) {
+                        return Err(WitnessScriptMismatchShWsh { input_index });
+                    }
+                } else if p2wsh != *spk {
+                    return Err(WitnessScriptMismatchWsh { input_index });
+                }
+            }
+
+            match (input.tap_internal_key, input.tap_merkle_root) {
+                (Some(internal_key), merkle_root) =>
+                    if ScriptBuf::new_p2tr(&secp, internal_key, merkle_root) != *spk {
+                        return Err(TaprootTweakMismatch { input_index });
+                    },
+                (None, Some(_)) => return Err(MissingTapInternalKey { input_index }),
+                (None, None) => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Aggregates the MuSig2 partial signatures of the input at `input_index` into `tap_key_sig`.
+    ///
+    /// The `tap_internal_key` must be a MuSig2 aggregate key listed in `musig2_participant_pubkeys`
+    /// and every participant must have provided a public nonce and a partial signature for the key
+    /// path spend. The aggregated signature is verified before it is added to the input.
+    pub fn aggregate_musig2_partial_sigs<C: Verification>(
+        &mut self,
+        input_index: usize,
+        secp: &Secp256k1<C>,
+    ) -> Result<(), Musig2AggregateError> {
+        let sig = self.musig2_key_sig(input_index, secp)?;
+        self.inputs[input_index].tap_key_sig = Some(sig);
+        Ok(())
+    }
+
+    /// Aggregates the MuSig2 partial signatures of the input at `input_index` into a key path
+    /// signature, see [`Psbt::aggregate_musig2_partial_sigs`].
+    fn musig2_key_sig<C: Verification>(
+        &self,
+        input_index: usize,
+        secp: &Secp256k1<C>,
+    ) -> Result<taproot::Signature, Musig2AggregateError> {
+        use Musig2AggregateError::*;
+
+        let input = self.checked_input(input_index)?;
+        let internal_key = input.tap_internal_key.ok_or(MissingTapInternalKey)?;
+        let (aggregate_key, participants) = input
+            .musig2_participant_pubkeys
+            .iter()
+            .find(|(aggregate_key, _)| aggregate_key.x_only_public_key().0 == internal_key)
+            .ok_or(MissingParticipantPubkeys)?;
+        if musig2::aggregate_key(secp, participants) != Some(*aggregate_key) {
+            return Err(AggregateKeyMismatch);
+        }
+
+        let mut nonces = Vec::with_capacity(participants.len());
+        let mut partial_sigs = Vec::with_capacity(participants.len());
+        for participant in participants.iter().copied() {
+            let key = (participant, *aggregate_key, None);
+            let nonce = input.musig2_pub_nonces.get(&key).ok_or(MissingPubNonce { participant })?;
+            let partial_sig =
+                input.musig2_partial_sigs.get(&key).ok_or(MissingPartialSig { participant })?;
+            let partial_sig = Scalar::from_be_bytes(partial_sig.to_byte_array())
+                .map_err(|_| InvalidPartialSig { participant })?;
+            nonces.push(*nonce);
+            partial_sigs.push(partial_sig);
+        }
+
+        let tx = self.unsigned_tx()?;
+        let (msg, hash_ty) =
+            self.sighash_taproot(input_index, &mut SighashCache::new(&tx), None)?;
+
+        let tweak =
+            TapTweakHash::from_key_and_tweak(internal_key, input.tap_merkle_root).to_scalar();
+        let sig = musig2::aggregate_signature(
+            secp,
+            *aggregate_key,
+            Some(tweak),
+            &nonces,
+            &partial_sigs,
+            msg.as_ref(),
+        )
+        .ok_or(Aggregation)?;
+
+        let (output_key, _) = internal_key.tap_tweak(secp, input.tap_merkle_root);
+        secp.verify_schnorr(&sig, &msg, &output_key.to_inner()).map_err(|_| InvalidSignature)?;
+
+        Ok(taproot::Signature { sig, hash_ty })
+    }
+
+    /// Computes the scripts of the silent payment outputs from the ECDH shares, see [BIP-352].
+    ///
+    /// Every share used must have a valid DLEQ proof (see [BIP-374]). Once the scripts are computed
+    /// the inputs and outputs are marked as not modifiable.
+    ///
+    /// [BIP-352]: <https://github.com/bitcoin/bips/blob/master/bip-0352.mediawiki>
+    /// [BIP-374]: <https://github.com/bitcoin/bips/blob/master/bip-0374.mediawiki>
+    fn compute_silent_payment_scripts<C: Verification>(
+        &mut self,
+        secp: &Secp256k1<C>,
+    ) -> Result<(), SilentPaymentError> {
+        use SilentPaymentError::*;
+
+        if !self.outputs.iter().any(|output| output.sp_v0_info.is_some()) {
+            return Ok(());
+        }
+        if self.global.is_inputs_modifiable() {
+            return Err(InputsModifiable);
+        }
+
+        let mut eligible = Vec::new();
+        for (input_index, input) in self.inputs.iter().enumerate() {
+            if let Some(key) = silent_payments::input_key(input_index, input)? {
+                eligible.push((input_index, key));
+            }
+        }
+        let input_keys = eligible.iter().map(|(_, key)| key).collect::<Vec<_>>();
+        if input_keys.is_empty() {
+            return Err(NoEligibleInputs);
+        }
+        let input_keys = secp256k1::PublicKey::combine_keys(&input_keys).map_err(|_| Derivation)?;
+
+        let smallest_out_point = self
+            .inputs
+            .iter()
+            .map(|input| input.out_point())
+            .min_by_key(consensus::serialize)
+            .expect("at least one eligible input");
+        let input_hash =
+            silent_payments::input_hash(&smallest_out_point, &input_keys).ok_or(Derivation)?;
+
+        let mut shared_secrets = BTreeMap::new();
+        for info in self.outputs.iter().filter_map(|output| output.sp_v0_info) {
+            let scan_key = info.scan_key;
+            if shared_secrets.contains_key(&scan_key) {
+                continue;
+            }
+            let share = match self.global.sp_ecdh_shares.get(&scan_key) {
+                Some(share) => {
+                    let proof = self
+                        .global
+                        .sp_dleq_proofs
+                        .get(&scan_key)
+                        .ok_or(MissingDleqProof { input_index: None, scan_key })?;
+                    if !proof.verify(secp, &input_keys, &scan_key, share) {
+                        return Err(InvalidDleqProof { input_index: None, scan_key });
+                    }
+                    *share
+                }
+                None => {
+                    let mut shares = Vec::with_capacity(eligible.len());
+                    for &(input_index, ref key) in &eligible {
+                        let input = &self.inputs[input_index];
+                        let share = input
+                            .sp_ecdh_shares
+                            .get(&scan_key)
+                            .ok_or(MissingEcdhShare { input_index, scan_key })?;
+                        let proof = input
+                            .sp_dleq_proofs
+                            .get(&scan_key)
+                            .ok_or(MissingDleqProof { input_index: Some(input_index), scan_key })?;
+                        if !proof.verify(secp, key, &scan_key, share) {
+                            return Err(InvalidDleqProof {
+                                input_index: Some(input_index),
+                                scan_key,
+                            });
+                        }
+                        shares.push(share);
+                    }
+                    secp256k1::PublicKey::combine_keys(&shares).map_err(|_| Derivation)?
+                }
+            };
+            let shared_secret = share.mul_tweak(secp, &input_hash).map_err(|_| Derivation)?;
+            shared_secrets.insert(scan_key, (shared_secret, 0_u32));
+        }
+
+        for output in self.outputs.iter_mut() {
+            if let Some(info) = output.sp_v0_info {
+                let (shared_secret, k) = shared_secrets
+                    .get_mut(&info.scan_key)
+                    .expect("shared secret for all scan keys");
+                let key = silent_payments::output_key(secp, shared_secret, *k, &info.spend_key)
+                    .ok_or(Derivation)?;
+                output.script_pubkey = ScriptBuf::new_p2tr_tweaked(key.dangerous_assume_tweaked());
+                *k += 1;
+            }
+        }
+
+        self.global.clear_inputs_modifiable_flag();
+        self.global.clear_outputs_modifiable_flag();
+        Ok(())
+    }
+
     /// Attempts to create _all_ the required signatures for this PSBT using `k`.
     ///
-    /// **NOTE**: Taproot inputs are, as yet, not supported by this function. We currently only
-    /// attempt to sign ECDSA inputs.
+    /// ECDSA inputs are signed using the keys in `bip32_derivations`. Taproot inputs are signed
+    /// using the keys in `tap_key_origins`, a key path signature is created for `tap_internal_key`
+    /// (tweaked with `tap_merkle_root`) and script path signatures are created for every leaf hash
+    /// associated with a key.
     ///
-    /// If you just want to sign an input with one specific key consider using `sighash_ecdsa`. This
-    /// function does not support scripts that contain `OP_CODESEPARATOR`.
+    /// If you just want to sign an input with one specific key consider using `sighash_ecdsa` or
+    /// `sighash_taproot`. This function does not support scripts that contain `OP_CODESEPARATOR`.
     ///
     /// # Returns
     ///
@@ -734,7 +1463,7 @@ impl Psbt {
         secp: &Secp256k1<C>,
     ) -> Result<SigningKeys, (SigningKeys, SigningErrors)>
     where
-        C: Signing,
+        C: Signing + Verification,
         K: GetKey,
     {
         let mut cache = SighashCache::new(&tx);
@@ -743,16 +1472,19 @@ impl Psbt {
         let mut errors = BTreeMap::new();
 
         for i in 0..self.global.input_count {
-            if let Ok(SigningAlgorithm::Ecdsa) = self.signing_algorithm(i) {
-                match self.bip32_sign_ecdsa(k, i, &mut cache, secp) {
-                    Ok(v) => {
-                        used.insert(i, v);
-                    }
-                    Err(e) => {
-                        errors.insert(i, e);
-                    }
-                }
+            let res = match self.signing_algorithm(i) {
+                Ok(SigningAlgorithm::Ecdsa) => self.bip32_sign_ecdsa(k, i, &mut cache, secp),
+                Ok(SigningAlgorithm::Schnorr) => self.bip32_sign_schnorr(k, i, &mut cache, secp),
+                Err(_) => continue,
             };
+            match res {
+                Ok(v) => {
+                    used.insert(i, v);
+                }
+                Err(e) => {
+                    errors.insert(i, e);
+                }
+            }
         }
         if errors.is_empty() {
             Ok(used)
@@ -816,6 +1548,113 @@ impl Psbt {
         Ok(used)
     }
 
+    /// Attempts to create all Taproot signatures required by this PSBT's `tap_key_origins` field.
+    ///
+    /// If we have the key for `tap_internal_key` a key path signature is added to `tap_key_sig`,
+    /// the internal key is tweaked with `tap_merkle_root` before signing as described in BIP-341.
+    /// For every other key we have, a script path signature is added to `tap_script_sigs` for each
+    /// of the leaf hashes associated with that key.
+    ///
+    /// # Returns
+    ///
+    /// - Ok: A list of the public keys used in signing (before tweaking).
+    /// - Err: Error encountered trying to calculate the sighash AND we had the signing key.
+    fn bip32_sign_schnorr<C, K, T>(
+        &mut self,
+        k: &K,
+        input_index: usize,
+        cache: &mut SighashCache<T>,
+        secp: &Secp256k1<C>,
+    ) -> Result<Vec<PublicKey>, SignError>
+    where
+        C: Signing + Verification,
+        T: Borrow<Transaction>,
+        K: GetKey,
+    {
+        let input = self.checked_input(input_index)?;
+        let internal_key = input.tap_internal_key;
+        let origins = input.tap_key_origins.clone();
+
+        let mut used = vec![]; // List of pubkeys used to sign the input.
+        let mut sighash_types = vec![]; // List of sighash types used to sign the input.
+
+        for (xonly, (leaf_hashes, key_source)) in origins {
+            let sk = if let Ok(Some(sk)) = k.get_key(KeyRequest::Bip32(key_source), secp) {
+                sk
+            } else {
+                continue;
+            };
+            let keypair = Keypair::from_secret_key(secp, &sk.inner);
+            // Only sign with the key the origin is recorded for.
+            if keypair.x_only_public_key().0 != xonly {
+                continue;
+            }
+            let mut signed = false;
+
+            // Key path spend.
+            if internal_key == Some(xonly) {
+                let (msg, sighash_type) = self.sighash_taproot(input_index, cache, None)?;
+                let input = &mut self.inputs[input_index]; // Index checked above.
+                let tweaked = keypair.tap_tweak(secp, input.tap_merkle_root).to_inner();
+                let sig = secp.sign_schnorr_no_aux_rand(&msg, &tweaked);
+
+                input.tap_key_sig = Some(taproot::Signature { sig, hash_ty: sighash_type });
+                sighash_types.push(sighash_type);
+                signed = true;
+            }
+
+            // Script path spend, for each leaf this key is used in.
+            for leaf_hash in leaf_hashes {
+                let (msg, sighash_type) =
+                    self.sighash_taproot(input_index, cache, Some(leaf_hash))?;
+                let sig = secp.sign_schnorr_no_aux_rand(&msg, &keypair);
+
+                let input = &mut self.inputs[input_index]; // Index checked above.
+                input
+                    .tap_script_sigs
+                    .insert((xonly, leaf_hash), taproot::Signature { sig, hash_ty: sighash_type });
+                sighash_types.push(sighash_type);
+                signed = true;
+            }
+
+            if signed {
+                used.push(sk.public_key(secp));
+            }
+        }
+
+        for ty in sighash_types {
+            self.clear_tx_modifiable(ty as u8);
+        }
+
+        Ok(used)
+    }
+
+    /// Returns the sighash message to sign the input at `input_index`.
+    ///
+    /// Detects the [`OutputType`] of the funding utxo and computes the legacy, segwit v0 or Taproot
+    /// sighash accordingly. For Taproot inputs `leaf_hash` selects a script path spend, if it is
+    /// `None` the sighash is for a key path spend. `leaf_hash` is ignored for non-Taproot inputs.
+    ///
+    /// Uses the sighash type from the input if one is specified, see [`Psbt::sighash_ecdsa`] and
+    /// [`Psbt::sighash_taproot`] for the defaults and requirements of each signing algorithm.
+    pub fn sighash_msg<T: Borrow<Transaction>>(
+        &self,
+        input_index: usize,
+        cache: &mut SighashCache<T>,
+        leaf_hash: Option<TapLeafHash>,
+    ) -> Result<PsbtSighashMsg, SignError> {
+        match self.signing_algorithm(input_index)? {
+            SigningAlgorithm::Ecdsa => {
+                let (msg, _) = self.ecdsa_sighash_msg(input_index, cache)?;
+                Ok(msg)
+            }
+            SigningAlgorithm::Schnorr => {
+                let (sighash, _) = self.taproot_sighash(input_index, cache, leaf_hash)?;
+                Ok(PsbtSighashMsg::TapSighash(sighash))
+            }
+        }
+    }
+
     /// Returns the sighash message to sign an ECDSA input along with the sighash type.
     ///
     /// Uses the [`EcdsaSighashType`] from this input if one is specified. If no sighash type is
@@ -826,52 +1665,119 @@ impl Psbt {
         input_index: usize,
         cache: &mut SighashCache<T>,
     ) -> Result<(Message, EcdsaSighashType), SignError> {
-        use OutputType::*;
-
         if self.signing_algorithm(input_index)? != SigningAlgorithm::Ecdsa {
             return Err(SignError::WrongSigningAlgorithm);
         }
 
+        let (msg, hash_ty) = self.ecdsa_sighash_msg(input_index, cache)?;
+        Ok((msg.to_secp_msg(), hash_ty))
+    }
+
+    /// Returns the sighash message to sign a Taproot input along with the sighash type.
+    ///
+    /// Uses the [`TapSighashType`] from this input if one is specified. If no sighash type is
+    /// specified uses [`TapSighashType::Default`]. If `leaf_hash` is `None` the sighash is for a
+    /// key path spend, otherwise it is for a script path spend of the leaf with this hash.
+    ///
+    /// Unless the sighash type uses `SIGHASH_ANYONECANPAY` all inputs must have a funding utxo.
+    pub fn sighash_taproot<T: Borrow<Transaction>>(
+        &self,
+        input_index: usize,
+        cache: &mut SighashCache<T>,
+        leaf_hash: Option<TapLeafHash>,
+    ) -> Result<(Message, TapSighashType), SignError> {
+        if self.signing_algorithm(input_index)? != SigningAlgorithm::Schnorr {
+            return Err(SignError::WrongSigningAlgorithm);
+        }
+
+        let (sighash, hash_ty) = self.taproot_sighash(input_index, cache, leaf_hash)?;
+        Ok((Message::from_digest(sighash.to_byte_array()), hash_ty))
+    }
+
+    /// Computes the legacy or segwit v0 sighash for the ECDSA input at `input_index`.
+    ///
+    /// Caller to check that the input is signed using ECDSA.
+    fn ecdsa_sighash_msg<T: Borrow<Transaction>>(
+        &self,
+        input_index: usize,
+        cache: &mut SighashCache<T>,
+    ) -> Result<(PsbtSighashMsg, EcdsaSighashType), SignError> {
+        use OutputType::*;
+
         let input = self.checked_input(input_index)?;
         let utxo = input.funding_utxo()?;
         let spk = &utxo.script_pubkey; // scriptPubkey for input spend utxo.
 
         let hash_ty = input.ecdsa_hash_ty().map_err(|_| SignError::InvalidSighashType)?; // Only support standard sighash types.
 
-        match self.output_type(input_index)? {
+        let msg = match self.output_type(input_index)? {
             Bare => {
                 let sighash = cache.legacy_signature_hash(input_index, spk, hash_ty.to_u32())?;
-                Ok((Message::from_digest(sighash.to_byte_array()), hash_ty))
+                PsbtSighashMsg::LegacySighash(sighash)
             }
             Sh => {
                 let script_code =
                     input.redeem_script.as_ref().ok_or(SignError::MissingRedeemScript)?;
                 let sighash =
                     cache.legacy_signature_hash(input_index, script_code, hash_ty.to_u32())?;
-                Ok((Message::from_digest(sighash.to_byte_array()), hash_ty))
+                PsbtSighashMsg::LegacySighash(sighash)
             }
             Wpkh => {
                 let sighash = cache.p2wpkh_signature_hash(input_index, spk, utxo.value, hash_ty)?;
-                Ok((Message::from_digest(sighash.to_byte_array()), hash_ty))
+                PsbtSighashMsg::SegwitV0Sighash(sighash)
             }
             ShWpkh => {
                 let redeem_script = input.redeem_script.as_ref().expect("checked above");
                 let sighash =
                     cache.p2wpkh_signature_hash(input_index, redeem_script, utxo.value, hash_ty)?;
-                Ok((Message::from_digest(sighash.to_byte_array()), hash_ty))
+                PsbtSighashMsg::SegwitV0Sighash(sighash)
             }
             Wsh | ShWsh => {
                 let witness_script =
                     input.witness_script.as_ref().ok_or(SignError::MissingWitnessScript)?;
                 let sighash =
                     cache.p2wsh_signature_hash(input_index, witness_script, utxo.value, hash_ty)?;
-                Ok((Message::from_digest(sighash.to_byte_array()), hash_ty))
+                PsbtSighashMsg::SegwitV0Sighash(sighash)
             }
             Tr => {
-                // This PSBT signing API is WIP, taproot to come shortly.
-                Err(SignError::Unsupported)
+                // Taproot inputs are signed with Schnorr, use `taproot_sighash`.
+                return Err(SignError::WrongSigningAlgorithm);
             }
-        }
+        };
+        Ok((msg, hash_ty))
+    }
+
+    /// Computes the Taproot sighash for the input at `input_index`.
+    ///
+    /// Caller to check that the input is signed using Schnorr.
+    fn taproot_sighash<T: Borrow<Transaction>>(
+        &self,
+        input_index: usize,
+        cache: &mut SighashCache<T>,
+        leaf_hash: Option<TapLeafHash>,
+    ) -> Result<(TapSighash, TapSighashType), SignError> {
+        let input = self.checked_input(input_index)?;
+        let hash_ty = input.taproot_hash_ty().map_err(|_| SignError::InvalidSighashType)?;
+
+        let is_anyone_can_pay = hash_ty as u8 & 0x80 != 0;
+        let all_utxos;
+        let prevouts = if is_anyone_can_pay {
+            Prevouts::One(input_index, input.funding_utxo()?)
+        } else {
+            all_utxos = self.iter_funding_utxos().collect::<Result<Vec<_>, _>>()?;
+            Prevouts::All(&all_utxos)
+        };
+
+        let sighash = match leaf_hash {
+            Some(leaf_hash) => cache.taproot_script_spend_signature_hash(
+                input_index,
+                &prevouts,
+                leaf_hash,
+                hash_ty,
+            )?,
+            None => cache.taproot_key_spend_signature_hash(input_index, &prevouts, hash_ty)?,
+        };
+        Ok((sighash, hash_ty))
     }
 
     /// Gets a reference to the input at `input_index` after checking that it is a valid index.
@@ -896,6 +1802,121 @@ impl Psbt {
         Ok(())
     }
 
+    /// Gets a mutable reference to the output at `output_index` after checking that it is a valid index.
+    fn checked_output_mut(&mut self, index: usize) -> Result<&mut Output, IndexOutOfBoundsError> {
+        self.check_output_index(index)?;
+        Ok(&mut self.outputs[index])
+    }
+
+    /// Removes the input at `index`, keeping the global input count in sync.
+    fn remove_input(&mut self, index: usize) -> Result<Input, RemoveError> {
+        self.check_input_index(index)?;
+        self.check_sighash_single_input_removal(&[index])?;
+        self.global.input_count -= 1;
+        Ok(self.inputs.remove(index))
+    }
+
+    /// Removes all inputs spending `out_point`, keeping the global input count in sync.
+    fn remove_inputs_spending(&mut self, out_point: &OutPoint) -> Result<(), RemoveError> {
+        let removed = self
+            .inputs
+            .iter()
+            .enumerate()
+            .filter(|(_, input)| input.out_point() == *out_point)
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+        self.check_sighash_single_input_removal(&removed)?;
+
+        self.inputs.retain(|input| input.out_point() != *out_point);
+        self.global.input_count = self.global.input_count.saturating_sub(removed.len());
+        Ok(())
+    }
+
+    /// Removes the output at `index`, keeping the global output count in sync.
+    fn remove_output(&mut self, index: usize) -> Result<Output, RemoveError> {
+        self.check_output_index(index)?;
+        self.check_sighash_single_output_removal(&[index])?;
+        self.global.output_count -= 1;
+        Ok(self.outputs.remove(index))
+    }
+
+    /// Removes all outputs paying to `script_pubkey`, keeping the global output count in sync.
+    fn remove_outputs_paying_to(&mut self, script_pubkey: &Script) -> Result<(), RemoveError> {
+        let removed = self
+            .outputs
+            .iter()
+            .enumerate()
+            .filter(|(_, output)| output.script_pubkey.as_script() == script_pubkey)
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+        self.check_sighash_single_output_removal(&removed)?;
+
+        self.outputs.retain(|output| output.script_pubkey.as_script() != script_pubkey);
+        self.global.output_count = self.global.output_count.saturating_sub(removed.len());
+        Ok(())
+    }
+
+    /// Inserts `input` and `output` at the same index without breaking any SIGHASH_SINGLE pairing.
+    fn insert_input_output_pair(&mut self, input: Input, output: Output) {
+        // Inputs signed using SIGHASH_SINGLE always have an output at the same index so they all
+        // come before this index, inserting here does not change the index of any of them.
+        let index = core::cmp::min(self.inputs.len(), self.outputs.len());
+        debug_assert!(self.sighash_single_inputs().all(|i| i < index));
+
+        self.inputs.insert(index, input);
+        self.global.input_count += 1;
+        self.outputs.insert(index, output);
+        self.global.output_count += 1;
+    }
+
+    /// Returns an iterator over the indices of inputs signed using `SIGHASH_SINGLE`.
+    ///
+    /// Only looks at the inputs if the "has SIGHASH_SINGLE" flag is set.
+    fn sighash_single_inputs(&self) -> impl Iterator<Item = usize> + '_ {
+        let has_sighash_single = self.global.has_sighash_single();
+        self.inputs
+            .iter()
+            .enumerate()
+            .filter(move |(_, input)| has_sighash_single && input.has_sighash_single_sig())
+            .map(|(index, _)| index)
+    }
+
+    /// Checks that removing the inputs at `removed` does not change the index of any remaining
+    /// input signed using `SIGHASH_SINGLE`.
+    fn check_sighash_single_input_removal(&self, removed: &[usize]) -> Result<(), RemoveError> {
+        for input_index in self.sighash_single_inputs() {
+            if !removed.contains(&input_index) && removed.iter().any(|&r| r < input_index) {
+                return Err(RemoveError::BreaksSighashSinglePairing { input_index });
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that removing the outputs at `removed` does not remove, or change the index of, the
+    /// output paired with an input signed using `SIGHASH_SINGLE`.
+    fn check_sighash_single_output_removal(&self, removed: &[usize]) -> Result<(), RemoveError> {
+        for input_index in self.sighash_single_inputs() {
+            if removed.iter().any(|&r| r <= input_index) {
+                return Err(RemoveError::BreaksSighashSinglePairing { input_index });
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that `index` is a valid output index for this PSBT.
+    fn check_output_index(&self, index: usize) -> Result<(), IndexOutOfBoundsError> {
+        if index >= self.outputs.len() {
+            return Err(IndexOutOfBoundsError::Outputs { index, length: self.outputs.len() });
+        }
+        if index >= self.global.output_count {
+            return Err(IndexOutOfBoundsError::OutputCount {
+                index,
+                count: self.global.output_count,
+            });
+        }
+        Ok(())
+    }
+
     /// Returns the algorithm used to sign this PSBT's input at `input_index`.
     fn signing_algorithm(&self, input_index: usize) -> Result<SigningAlgorithm, SignError> {
         let output_type = self.output_type(input_index)?;
@@ -996,6 +2017,12 @@ impl Psbt {
     }
 }
 
+impl TryFrom<Psbt> for v0::Psbt {
+    type Error = DetermineLockTimeError;
+
+    fn try_from(psbt: Psbt) -> Result<Self, Self::Error> { psbt.into_v0() }
+}
+
 /// Data required to call [`GetKey`] to get the private key to sign an input.
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[non_exhaustive]
@@ -1176,6 +2203,30 @@ impl OutputType {
     }
 }
 
+/// Sighash message (signing data) for a given PSBT input.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub enum PsbtSighashMsg {
+    /// Taproot sighash message.
+    TapSighash(TapSighash),
+    /// Legacy ECDSA sighash message.
+    LegacySighash(LegacySighash),
+    /// Segwit v0 ECDSA sighash message.
+    SegwitV0Sighash(SegwitV0Sighash),
+}
+
+impl PsbtSighashMsg {
+    /// Converts the sighash message to a [`Message`] ready to be signed.
+    pub fn to_secp_msg(&self) -> Message {
+        use PsbtSighashMsg::*;
+
+        match *self {
+            TapSighash(ref msg) => Message::from_digest(msg.to_byte_array()),
+            LegacySighash(ref msg) => Message::from_digest(msg.to_byte_array()),
+            SegwitV0Sighash(ref msg) => Message::from_digest(msg.to_byte_array()),
+        }
+    }
+}
+
 /// Signing algorithms supported by the Bitcoin network.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum SigningAlgorithm {
@@ -1309,7 +2360,7 @@ mod display_from_str {
     }
 }
 
-/// Error combining two input maps.
+/// Error combining two PSBTs.
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[non_exhaustive]
 pub enum CombineError {
@@ -1319,6 +2370,45 @@ pub enum CombineError {
     Input(input::CombineError),
     /// Error while combining the output maps.
     Output(output::CombineError),
+    /// The PSBTs have a different number of inputs.
+    InputCountMismatch {
+        /// Attempted to combine a PSBT with `this` many inputs.
+        this: usize,
+        /// Into a PSBT with `that` many inputs.
+        that: usize,
+    },
+    /// The PSBTs have a different number of outputs.
+    OutputCountMismatch {
+        /// Attempted to combine a PSBT with `this` many outputs.
+        this: usize,
+        /// Into a PSBT with `that` many outputs.
+        that: usize,
+    },
+    /// The global input count of a PSBT does not match its number of input maps.
+    InputMapCountMismatch {
+        /// The value of `PSBT_GLOBAL_INPUT_COUNT`.
+        input_count: usize,
+        /// The number of input maps in the PSBT.
+        input_maps: usize,
+    },
+    /// The global output count of a PSBT does not match its number of output maps.
+    OutputMapCountMismatch {
+        /// The value of `PSBT_GLOBAL_OUTPUT_COUNT`.
+        output_count: usize,
+        /// The number of output maps in the PSBT.
+        output_maps: usize,
+    },
+    /// The PSBTs do not describe the same transaction.
+    IdMismatch {
+        /// Attempted to combine a PSBT with `this` unique identification.
+        this: Txid,
+        /// Into a PSBT with `that` unique identification.
+        that: Txid,
+    },
+    /// Unable to determine the lock time, required to calculate the PSBT's unique identification.
+    DetermineLockTime(DetermineLockTimeError),
+    /// Attempted to combine an empty list of PSBTs.
+    NoPsbts,
 }
 
 impl fmt::Display for CombineError {
@@ -1329,6 +2419,25 @@ impl fmt::Display for CombineError {
             Global(ref e) => write_err!(f, "error while combining the global maps"; e),
             Input(ref e) => write_err!(f, "error while combining the input maps"; e),
             Output(ref e) => write_err!(f, "error while combining the output maps"; e),
+            InputCountMismatch { this, that } =>
+                write!(f, "input count mismatch (this: {}, that: {})", this, that),
+            OutputCountMismatch { this, that } =>
+                write!(f, "output count mismatch (this: {}, that: {})", this, that),
+            InputMapCountMismatch { input_count, input_maps } => write!(
+                f,
+                "global input count is {} but PSBT has {} input maps",
+                input_count, input_maps
+            ),
+            OutputMapCountMismatch { output_count, output_maps } => write!(
+                f,
+                "global output count is {} but PSBT has {} output maps",
+                output_count, output_maps
+            ),
+            IdMismatch { this, that } =>
+                write!(f, "PSBT unique identification mismatch (this: {}, that: {})", this, that),
+            DetermineLockTime(ref e) =>
+                write_err!(f, "unable to determine lock time to calculate the PSBT id"; e),
+            NoPsbts => f.write_str("no PSBTs to combine"),
         }
     }
 }
@@ -1342,10 +2451,21 @@ impl std::error::Error for CombineError {
             Global(ref e) => Some(e),
             Input(ref e) => Some(e),
             Output(ref e) => Some(e),
+            DetermineLockTime(ref e) => Some(e),
+            InputCountMismatch { .. }
+            | OutputCountMismatch { .. }
+            | InputMapCountMismatch { .. }
+            | OutputMapCountMismatch { .. }
+            | IdMismatch { .. }
+            | NoPsbts => None,
         }
     }
 }
 
+impl From<DetermineLockTimeError> for CombineError {
+    fn from(e: DetermineLockTimeError) -> Self { Self::DetermineLockTime(e) }
+}
+
 impl From<global::CombineError> for CombineError {
     fn from(e: global::CombineError) -> Self { Self::Global(e) }
 }
@@ -1357,3 +2477,110 @@ impl From<input::CombineError> for CombineError {
 impl From<output::CombineError> for CombineError {
     fn from(e: output::CombineError) -> Self { Self::Output(e) }
 }
+
+/// Error joining two PSBTs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum JoinError {
+    /// The PSBTs have different transaction versions.
+    TxVersionMismatch {
+        /// Attempted to join a PSBT with `this` transaction version.
+        this: transaction::Version,
+        /// Into a PSBT with `that` transaction version.
+        that: transaction::Version,
+    },
+    /// The PSBTs have different fallback lock times.
+    FallbackLockTimeMismatch {
+        /// Attempted to join a PSBT with `this` fallback lock time.
+        this: absolute::LockTime,
+        /// Into a PSBT with `that` fallback lock time.
+        that: absolute::LockTime,
+    },
+    /// One of the PSBTs gains inputs but its inputs are not modifiable.
+    InputsNotModifiable(InputsNotModifiableError),
+    /// One of the PSBTs gains outputs but its outputs are not modifiable.
+    OutputsNotModifiable(OutputsNotModifiableError),
+    /// An input signed using `SIGHASH_SINGLE` spends an outpoint spent by both PSBTs.
+    DuplicateSighashSingleInput {
+        /// The index of the input in the PSBT being joined.
+        input_index: usize,
+    },
+    /// An input signed using `SIGHASH_SINGLE` has no output at the same index.
+    MissingSighashSingleOutput {
+        /// The index of the input.
+        input_index: usize,
+        /// True if the input is in this PSBT, false if it is in the PSBT being joined.
+        in_this: bool,
+    },
+    /// Error while combining the global maps.
+    Global(global::CombineError),
+    /// Error while combining the input maps of an outpoint spent by both PSBTs.
+    Input(input::CombineError),
+    /// Unable to determine the lock time of the joined PSBT.
+    DetermineLockTime(DetermineLockTimeError),
+    /// Attempted to join an empty list of PSBTs.
+    NoPsbts,
+}
+
+impl fmt::Display for JoinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use JoinError::*;
+
+        match *self {
+            TxVersionMismatch { this, that } =>
+                write!(f, "transaction version mismatch (this: {:?}, that: {:?})", this, that),
+            FallbackLockTimeMismatch { this, that } =>
+                write!(f, "fallback lock time mismatch (this: {}, that: {})", this, that),
+            InputsNotModifiable(ref e) => write_err!(f, "join inputs"; e),
+            OutputsNotModifiable(ref e) => write_err!(f, "join outputs"; e),
+            DuplicateSighashSingleInput { input_index } => write!(
+                f,
+                "input {} signed using SIGHASH_SINGLE spends an outpoint spent by both PSBTs",
+                input_index
+            ),
+            MissingSighashSingleOutput { input_index, in_this } => write!(
+                f,
+                "input {} of {} PSBT signed using SIGHASH_SINGLE has no output at the same index",
+                input_index,
+                if in_this { "this" } else { "the joined" }
+            ),
+            Global(ref e) => write_err!(f, "error while combining the global maps"; e),
+            Input(ref e) => write_err!(f, "error while combining the input maps"; e),
+            DetermineLockTime(ref e) =>
+                write_err!(f, "unable to determine lock time of the joined PSBT"; e),
+            NoPsbts => f.write_str("no PSBTs to join"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for JoinError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use JoinError::*;
+
+        match *self {
+            InputsNotModifiable(ref e) => Some(e),
+            OutputsNotModifiable(ref e) => Some(e),
+            Global(ref e) => Some(e),
+            Input(ref e) => Some(e),
+            DetermineLockTime(ref e) => Some(e),
+            TxVersionMismatch { .. }
+            | FallbackLockTimeMismatch { .. }
+            | DuplicateSighashSingleInput { .. }
+            | MissingSighashSingleOutput { .. }
+            | NoPsbts => None,
+        }
+    }
+}
+
+impl From<DetermineLockTimeError> for JoinError {
+    fn from(e: DetermineLockTimeError) -> Self { Self::DetermineLockTime(e) }
+}
+
+impl From<global::CombineError> for JoinError {
+    fn from(e: global::CombineError) -> Self { Self::Global(e) }
+}
+
+impl From<input::CombineError> for JoinError {
+    fn from(e: input::CombineError) -> Self { Self::Input(e) }
+}
@@ -0,0 +1,795 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! PayJoin ([BIP-78]) processing of in-memory PSBTs.
+//!
+//! Transport (the BIP-78 HTTP endpoint and its query parameters) is left to the caller, the
+//! optional parameters are passed in as [`Params`].
+//!
+//! # Sender
+//!
+//! The sender creates a [`Sender`] from its finalized, broadcastable, PSBT and sends the
+//! [`Sender::original_psbt`] to the receiver. The receiver's proposal is checked using
+//! [`Sender::validate_proposal`], after which the sender signs its inputs again.
+//!
+//! # Receiver
+//!
+//! The receiver creates a [`Receiver`] from the original PSBT, contributes inputs and substitutes
+//! outputs, then signs and finalizes its own inputs. [`Receiver::finalize_proposal`] removes data
+//! the sender must not receive.
+//!
+//! [BIP-78]: <https://github.com/bitcoin/bips/blob/master/bip-0078.mediawiki>
+
+use core::fmt;
+
+use bitcoin::{
+    Amount, FeeRate, Script, ScriptBuf, Sequence, Transaction, TxIn, TxOut, VarInt, Weight,
+};
+
+use crate::error::{fee_rate, write_err, FeeError};
+use crate::prelude::*;
+use crate::v2::map::input::Input;
+use crate::v2::map::output::Output;
+use crate::v2::{
+    Constructor, DetermineLockTimeError, ExtractError, Extractor, IndexOutOfBoundsError,
+    Modifiable, Psbt, Updater,
+};
+
+/// The optional BIP-78 parameters, shared by the sender and the receiver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Params {
+    /// The index of the sender's output the receiver may reduce to pay for its inputs
+    /// (`additionalfeeoutputindex`).
+    pub additional_fee_output_index: Option<usize>,
+    /// The maximum amount the receiver may take from the fee output
+    /// (`maxadditionalfeecontribution`).
+    pub max_additional_fee_contribution: Amount,
+    /// If true the receiver may not substitute the payment output
+    /// (`disableoutputsubstitution`).
+    pub disable_output_substitution: bool,
+    /// The minimum fee rate of the payjoin transaction (`minfeerate`).
+    pub min_fee_rate: Option<FeeRate>,
+}
+
+/// The sender of a payjoin.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sender {
+    /// The finalized original PSBT.
+    original: Psbt,
+    /// The signed original transaction.
+    original_tx: Transaction,
+    /// The fee paid by the original transaction.
+    original_fee: Amount,
+    /// The script pubkey of the receiver's payment output.
+    payee: ScriptBuf,
+    /// The optional parameters.
+    params: Params,
+}
+
+impl Sender {
+    /// Creates a `Sender` for the finalized `original` PSBT paying to `payee`.
+    pub fn new(
+        original: Psbt,
+        payee: ScriptBuf,
+        params: Params,
+    ) -> Result<Self, OriginalPsbtError> {
+        let (original_tx, original_fee) = check_original(&original, &params)?;
+        if !original.outputs.iter().any(|output| output.script_pubkey == payee) {
+            return Err(OriginalPsbtError::MissingPayeeOutput);
+        }
+        if let Some(output_index) = params.additional_fee_output_index {
+            if original.outputs[output_index].script_pubkey == payee {
+                return Err(OriginalPsbtError::FeeOutputIsPayee);
+            }
+        }
+        Ok(Sender { original, original_tx, original_fee, payee, params })
+    }
+
+    /// Returns the original PSBT to send to the receiver.
+    ///
+    /// Key origins and extended public keys are removed, the receiver has no use for them.
+    pub fn original_psbt(&self) -> Psbt {
+        let mut psbt = self.original.clone();
+        remove_key_origins(&mut psbt);
+        psbt
+    }
+
+    /// Returns the original transaction, to broadcast if the payjoin fails.
+    pub fn original_tx(&self) -> &Transaction { &self.original_tx }
+
+    /// Validates the receiver's payjoin `proposal` as described in BIP-78.
+    ///
+    /// The funding utxos of the sender's inputs are restored from the original PSBT. The sender's
+    /// inputs must be updated again before signing since the original PSBT was finalized.
+    ///
+    /// The minimum fee rate is checked against the weight of the transaction with the sender's
+    /// inputs satisfied as in the original transaction.
+    pub fn validate_proposal(&self, proposal: Psbt) -> Result<Updater, ProposalError> {
+        use ProposalError::*;
+
+        let original = &self.original;
+        if proposal.global.tx_version != original.global.tx_version {
+            return Err(VersionChanged);
+        }
+        if proposal.determine_lock_time()? != self.original_tx.lock_time {
+            return Err(LockTimeChanged);
+        }
+
+        let sequence = uniform(original.inputs.iter().map(|input| input.sequence));
+        let script_type = uniform(original.inputs.iter().map(input_script_type)).flatten();
+
+        let mut psbt = proposal;
+        let mut out_points = BTreeSet::new();
+        // The indices of the original inputs matched by a proposal input.
+        let mut sender_inputs = BTreeSet::new();
+        let mut tx_ins = Vec::with_capacity(psbt.inputs.len());
+        for (input_index, input) in psbt.inputs.iter_mut().enumerate() {
+            if !input.bip32_derivations.is_empty() || !input.tap_key_origins.is_empty() {
+                return Err(KeyOrigins);
+            }
+            let out_point = input.out_point();
+            if !out_points.insert(out_point) {
+                return Err(DuplicateInput { input_index });
+            }
+            if let Some(i) = original.inputs.iter().position(|input| input.out_point() == out_point)
+            {
+                let original_input = &original.inputs[i];
+                if input.has_sig_data() || input.is_finalized() {
+                    return Err(SenderInputSigned { input_index });
+                }
+                if input.witness_utxo.is_some() || input.non_witness_utxo.is_some() {
+                    return Err(SenderInputUtxo { input_index });
+                }
+                if input.sequence != original_input.sequence {
+                    return Err(SequenceChanged { input_index });
+                }
+                input.witness_utxo = original_input.witness_utxo.clone();
+                input.non_witness_utxo = original_input.non_witness_utxo.clone();
+
+                let mut tx_in = self.original_tx.input[i].clone();
+                tx_in.sequence = input.sequence.unwrap_or(Sequence::MAX);
+                tx_ins.push(tx_in);
+                sender_inputs.insert(i);
+            } else {
+                if !input.is_finalized() {
+                    return Err(ReceiverInputNotFinalized { input_index });
+                }
+                let utxo =
+                    input.funding_utxo().map_err(|_| MissingReceiverInputUtxo { input_index })?;
+                if original
+                    .iter_funding_utxos()
+                    .any(|u| u.map(|u| u.script_pubkey == utxo.script_pubkey).unwrap_or(false))
+                {
+                    return Err(NewSenderInput { input_index });
+                }
+                if sequence.map(|s| s != input.sequence).unwrap_or(false) {
+                    return Err(MixedSequences { input_index });
+                }
+                if script_type.map(|t| Some(t) != input_script_type(input)).unwrap_or(false) {
+                    return Err(MixedInputTypes { input_index });
+                }
+                tx_ins.push(input.signed_tx_in());
+            }
+        }
+        if sender_inputs.len() != original.inputs.len() {
+            return Err(MissingSenderInput);
+        }
+
+        if psbt.outputs.iter().any(|output| {
+            !output.bip32_derivations.is_empty() || !output.tap_key_origins.is_empty()
+        }) {
+            return Err(KeyOrigins);
+        }
+        let mut contribution = Amount::ZERO;
+        for (output_index, output) in original.outputs.iter().enumerate() {
+            let proposed = psbt.outputs.iter().find(|o| o.script_pubkey == output.script_pubkey);
+            if output.script_pubkey == self.payee {
+                if self.params.disable_output_substitution
+                    && proposed.map(|o| o.amount < output.amount).unwrap_or(true)
+                {
+                    return Err(PayeeOutputChanged);
+                }
+                continue;
+            }
+            let proposed = proposed.ok_or(MissingSenderOutput { output_index })?;
+            if Some(output_index) == self.params.additional_fee_output_index {
+                contribution = output
+                    .amount
+                    .checked_sub(proposed.amount)
+                    .ok_or(SenderOutputChanged { output_index })?;
+            } else if proposed.amount != output.amount {
+                return Err(SenderOutputChanged { output_index });
+            }
+        }
+
+        let fee = psbt.fee()?;
+        let max = max_fee_contribution(
+            &self.original_tx,
+            self.original_fee,
+            psbt.inputs.len() - original.inputs.len(),
+            &self.params,
+        );
+        if contribution > max {
+            return Err(FeeContributionTooHigh { contribution, max });
+        }
+        let additional_fee = fee.checked_sub(self.original_fee).unwrap_or(Amount::ZERO);
+        if contribution > additional_fee {
+            return Err(FeeContributionNotForFee { contribution, additional_fee });
+        }
+
+        if let Some(minimum) = self.params.min_fee_rate {
+            let tx = Transaction {
+                version: psbt.global.tx_version,
+                lock_time: self.original_tx.lock_time,
+                input: tx_ins,
+                output: psbt.outputs.iter().map(|output| output.tx_out()).collect(),
+            };
+            let fee_rate = fee_rate(fee, tx.weight());
+            if fee_rate < minimum {
+                return Err(FeeRateBelowMinimum { fee_rate, minimum });
+            }
+        }
+
+        Ok(Updater::new(psbt)?)
+    }
+}
+
+/// The receiver of a payjoin.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Receiver {
+    /// The finalized original PSBT.
+    original: Psbt,
+    /// The signed original transaction.
+    original_tx: Transaction,
+    /// The fee paid by the original transaction.
+    original_fee: Amount,
+    /// The optional parameters.
+    params: Params,
+    /// The payjoin proposal being constructed.
+    psbt: Psbt,
+}
+
+impl Receiver {
+    /// Creates a `Receiver` for the sender's finalized `original` PSBT.
+    ///
+    /// The signatures of the sender's inputs are removed and the inputs and outputs of the proposal
+    /// are made modifiable.
+    pub fn new(original: Psbt, params: Params) -> Result<Self, OriginalPsbtError> {
+        let (original_tx, original_fee) = check_original(&original, &params)?;
+
+        let mut psbt = original.clone();
+        for input in &mut psbt.inputs {
+            input.clear_sig_data();
+        }
+        psbt.global.set_inputs_modifiable_flag();
+        psbt.global.set_outputs_modifiable_flag();
+
+        Ok(Receiver { original, original_tx, original_fee, params, psbt })
+    }
+
+    /// Returns the original transaction, to broadcast if the payjoin fails.
+    pub fn original_tx(&self) -> &Transaction { &self.original_tx }
+
+    /// Contributes `input` to the payjoin.
+    ///
+    /// If all of the sender's inputs have the same sequence the input's sequence is set to match.
+    pub fn contribute_input(mut self, mut input: Input) -> Result<Self, ContributeError> {
+        use ContributeError::*;
+
+        input.funding_utxo().map_err(|_| MissingFundingUtxo)?;
+        let out_point = input.out_point();
+        if self.psbt.inputs.iter().any(|input| input.out_point() == out_point) {
+            return Err(DuplicateInput);
+        }
+        let original = &self.original.inputs;
+        if let Some(script_type) = uniform(original.iter().map(input_script_type)).flatten() {
+            if Some(script_type) != input_script_type(&input) {
+                return Err(MixedInputTypes);
+            }
+        }
+        if let Some(sequence) = uniform(original.iter().map(|input| input.sequence)) {
+            input.sequence = sequence;
+        }
+
+        self.psbt = self.constructor().input(input).psbt()?;
+        Ok(self)
+    }
+
+    /// Substitutes the output paying to `script_pubkey` with `output`.
+    ///
+    /// If output substitution is disabled `output` must pay to the same script and not reduce the
+    /// amount, use this to add the value of contributed inputs to the payment output.
+    pub fn substitute_output(
+        mut self,
+        script_pubkey: &Script,
+        output: Output,
+    ) -> Result<Self, ContributeError> {
+        use ContributeError::*;
+
+        let output_index = self
+            .psbt
+            .outputs
+            .iter()
+            .position(|output| output.script_pubkey == *script_pubkey)
+            .ok_or(MissingOutput)?;
+        let original = &self.psbt.outputs[output_index];
+        if self.params.disable_output_substitution
+            && (output.script_pubkey != original.script_pubkey || output.amount < original.amount)
+        {
+            return Err(OutputSubstitutionDisabled);
+        }
+
+        self.psbt = self
+            .constructor()
+            .remove_output(output_index)
+            .expect("index checked above and signatures removed")
+            .output(output)
+            .psbt()?;
+        Ok(self)
+    }
+
+    /// Builds the payjoin proposal for the receiver to sign its inputs.
+    ///
+    /// If the sender allowed it the additional fee for the contributed inputs is taken from the
+    /// sender's fee output, at the original fee rate and limited to the maximum contribution.
+    pub fn build(&self) -> Result<Updater, DetermineLockTimeError> {
+        let mut psbt = self.psbt.clone();
+        let added = psbt.inputs.len() - self.original.inputs.len();
+
+        if let Some(output_index) = self.params.additional_fee_output_index {
+            let script_pubkey = &self.original.outputs[output_index].script_pubkey;
+            if let Some(output) =
+                psbt.outputs.iter_mut().find(|output| output.script_pubkey == *script_pubkey)
+            {
+                let dust = TxOut::minimal_non_dust(script_pubkey.clone()).value;
+                let available = output.amount.checked_sub(dust).unwrap_or(Amount::ZERO);
+                let max =
+                    max_fee_contribution(&self.original_tx, self.original_fee, added, &self.params);
+                output.amount -= max.min(available);
+            }
+        }
+
+        Updater::new(psbt)
+    }
+
+    /// Removes data from the signed `proposal` that the sender must not receive.
+    ///
+    /// The receiver's inputs must be finalized. Signatures and funding utxos are removed from the
+    /// sender's inputs and key origins from all inputs and outputs.
+    pub fn finalize_proposal(&self, proposal: Psbt) -> Result<Psbt, FinalizeProposalError> {
+        let mut psbt = proposal;
+        for (input_index, input) in psbt.inputs.iter_mut().enumerate() {
+            let out_point = input.out_point();
+            if self.original.inputs.iter().any(|input| input.out_point() == out_point) {
+                input.clear_sig_data();
+                input.witness_utxo = None;
+                input.non_witness_utxo = None;
+            } else if !input.is_finalized() {
+                return Err(FinalizeProposalError::InputNotFinalized { input_index });
+            }
+        }
+        remove_key_origins(&mut psbt);
+        Ok(psbt)
+    }
+
+    fn constructor(&self) -> Constructor<Modifiable> {
+        Constructor::<Modifiable>::new(self.psbt.clone())
+            .expect("Receiver sets the modifiable flags")
+    }
+}
+
+/// Checks the original PSBT, returns the signed original transaction and its fee.
+fn check_original(
+    original: &Psbt,
+    params: &Params,
+) -> Result<(Transaction, Amount), OriginalPsbtError> {
+    let fee = original.fee()?;
+    if let Some(output_index) = params.additional_fee_output_index {
+        original.check_output_index(output_index)?;
+    }
+    let tx = Extractor::new(original.clone())?
+        .extract_tx_unchecked_fee_rate()
+        .expect("Extractor guarantees the PSBT is finalized and lock time can be determined");
+    Ok((tx, fee))
+}
+
+/// Returns the maximum fee the receiver may take from the sender's fee output for `added` inputs.
+///
+/// As described in BIP-78 this is the original fee rate times the weight of `added` inputs of the
+/// sender's input type, here the heaviest input of the original transaction.
+fn max_fee_contribution(
+    original_tx: &Transaction,
+    original_fee: Amount,
+    added: usize,
+    params: &Params,
+) -> Amount {
+    let input_weight = original_tx.input.iter().map(tx_in_weight).max().unwrap_or(0);
+    let weight = Weight::from_wu(input_weight.saturating_mul(added as u64));
+    let fee = fee_rate(original_fee, original_tx.weight()).fee_wu(weight).unwrap_or(Amount::MAX);
+    fee.min(params.max_additional_fee_contribution)
+}
+
+/// Returns the weight of a signed input in a segwit serialized transaction.
+fn tx_in_weight(tx_in: &TxIn) -> u64 {
+    let script_sig = tx_in.script_sig.len();
+    let base = 36 + VarInt(script_sig as u64).size() + script_sig + 4;
+    (4 * base + tx_in.witness.size()) as u64
+}
+
+/// Removes BIP-32 and Taproot key origins and extended public keys from `psbt`.
+fn remove_key_origins(psbt: &mut Psbt) {
+    psbt.global.xpubs.clear();
+    for input in &mut psbt.inputs {
+        input.bip32_derivations.clear();
+        input.tap_key_origins.clear();
+    }
+    for output in &mut psbt.outputs {
+        output.bip32_derivations.clear();
+        output.tap_key_origins.clear();
+    }
+}
+
+/// Returns the item if all items are equal, `None` if they differ or there are none.
+fn uniform<T: PartialEq, I: Iterator<Item = T>>(mut iter: I) -> Option<T> {
+    let first = iter.next()?;
+    if iter.all(|item| item == first) {
+        Some(first)
+    } else {
+        None
+    }
+}
+
+/// The type of script spent by an input, used to check inputs are not mixed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ScriptType {
+    P2pkh,
+    P2sh,
+    P2wpkh,
+    P2wsh,
+    P2tr,
+}
+
+/// Returns the type of script spent by `input`, `None` if it has no funding utxo or the script is
+/// non-standard.
+fn input_script_type(input: &Input) -> Option<ScriptType> {
+    let script_pubkey = &input.funding_utxo().ok()?.script_pubkey;
+    if script_pubkey.is_p2pkh() {
+        Some(ScriptType::P2pkh)
+    } else if script_pubkey.is_p2sh() {
+        Some(ScriptType::P2sh)
+    } else if script_pubkey.is_p2wpkh() {
+        Some(ScriptType::P2wpkh)
+    } else if script_pubkey.is_p2wsh() {
+        Some(ScriptType::P2wsh)
+    } else if script_pubkey.is_p2tr() {
+        Some(ScriptType::P2tr)
+    } else {
+        None
+    }
+}
+
+/// Error with the original PSBT of a payjoin.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum OriginalPsbtError {
+    /// The original PSBT is not finalized or its lock time can not be determined.
+    Extract(ExtractError),
+    /// Failed to calculate the fee of the original PSBT.
+    Fee(FeeError),
+    /// The additional fee output index is out of bounds.
+    FeeOutputIndex(IndexOutOfBoundsError),
+    /// The original PSBT does not pay to the receiver.
+    MissingPayeeOutput,
+    /// The additional fee output is the receiver's payment output.
+    FeeOutputIsPayee,
+}
+
+impl fmt::Display for OriginalPsbtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use OriginalPsbtError::*;
+
+        match *self {
+            Extract(ref e) => write_err!(f, "original PSBT is not broadcastable"; e),
+            Fee(ref e) => write_err!(f, "failed to calculate original fee"; e),
+            FeeOutputIndex(ref e) => write_err!(f, "additional fee output"; e),
+            MissingPayeeOutput => f.write_str("original PSBT does not pay to the receiver"),
+            FeeOutputIsPayee =>
+                f.write_str("additional fee output is the receiver's payment output"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for OriginalPsbtError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use OriginalPsbtError::*;
+
+        match *self {
+            Extract(ref e) => Some(e),
+            Fee(ref e) => Some(e),
+            FeeOutputIndex(ref e) => Some(e),
+            MissingPayeeOutput | FeeOutputIsPayee => None,
+        }
+    }
+}
+
+impl From<ExtractError> for OriginalPsbtError {
+    fn from(e: ExtractError) -> Self { Self::Extract(e) }
+}
+
+impl From<FeeError> for OriginalPsbtError {
+    fn from(e: FeeError) -> Self { Self::Fee(e) }
+}
+
+impl From<IndexOutOfBoundsError> for OriginalPsbtError {
+    fn from(e: IndexOutOfBoundsError) -> Self { Self::FeeOutputIndex(e) }
+}
+
+/// Error validating the receiver's payjoin proposal.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ProposalError {
+    /// The transaction version was changed.
+    VersionChanged,
+    /// The lock time was changed.
+    LockTimeChanged,
+    /// The proposal contains key origins.
+    KeyOrigins,
+    /// A sender input is signed or finalized.
+    SenderInputSigned {
+        /// The index of the input in the proposal.
+        input_index: usize,
+    },
+    /// A sender input has a funding utxo.
+    SenderInputUtxo {
+        /// The index of the input in the proposal.
+        input_index: usize,
+    },
+    /// The sequence of a sender input was changed.
+    SequenceChanged {
+        /// The index of the input in the proposal.
+        input_index: usize,
+    },
+    /// An input spends the same outpoint as an earlier input.
+    DuplicateInput {
+        /// The index of the input in the proposal.
+        input_index: usize,
+    },
+    /// A sender input was removed.
+    MissingSenderInput,
+    /// An added input spends one of the sender's scripts.
+    NewSenderInput {
+        /// The index of the input in the proposal.
+        input_index: usize,
+    },
+    /// A receiver input is not finalized.
+    ReceiverInputNotFinalized {
+        /// The index of the input in the proposal.
+        input_index: usize,
+    },
+    /// A receiver input has no funding utxo.
+    MissingReceiverInputUtxo {
+        /// The index of the input in the proposal.
+        input_index: usize,
+    },
+    /// A receiver input has a different sequence than the sender inputs.
+    MixedSequences {
+        /// The index of the input in the proposal.
+        input_index: usize,
+    },
+    /// A receiver input spends a different script type than the sender inputs.
+    MixedInputTypes {
+        /// The index of the input in the proposal.
+        input_index: usize,
+    },
+    /// A sender output was removed.
+    MissingSenderOutput {
+        /// The index of the output in the original PSBT.
+        output_index: usize,
+    },
+    /// The amount of a sender output was changed.
+    SenderOutputChanged {
+        /// The index of the output in the original PSBT.
+        output_index: usize,
+    },
+    /// The payment output was substituted or reduced although output substitution is disabled.
+    PayeeOutputChanged,
+    /// The amount taken from the fee output exceeds the allowed contribution.
+    FeeContributionTooHigh {
+        /// The amount taken from the fee output.
+        contribution: Amount,
+        /// The maximum allowed contribution.
+        max: Amount,
+    },
+    /// The amount taken from the fee output exceeds the additional fee.
+    FeeContributionNotForFee {
+        /// The amount taken from the fee output.
+        contribution: Amount,
+        /// The fee of the proposal in excess of the original fee.
+        additional_fee: Amount,
+    },
+    /// The fee rate of the proposal is below the minimum fee rate.
+    FeeRateBelowMinimum {
+        /// The estimated fee rate of the proposal.
+        fee_rate: FeeRate,
+        /// The minimum fee rate.
+        minimum: FeeRate,
+    },
+    /// Failed to calculate the fee of the proposal.
+    Fee(FeeError),
+    /// Failed to determine lock time.
+    DetermineLockTime(DetermineLockTimeError),
+}
+
+impl fmt::Display for ProposalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use ProposalError::*;
+
+        match *self {
+            VersionChanged => f.write_str("transaction version was changed"),
+            LockTimeChanged => f.write_str("lock time was changed"),
+            KeyOrigins => f.write_str("proposal contains key origins"),
+            SenderInputSigned { input_index } =>
+                write!(f, "sender input {} is signed or finalized", input_index),
+            SenderInputUtxo { input_index } =>
+                write!(f, "sender input {} has a funding utxo", input_index),
+            SequenceChanged { input_index } =>
+                write!(f, "sequence of sender input {} was changed", input_index),
+            DuplicateInput { input_index } =>
+                write!(f, "input {} spends the same outpoint as an earlier input", input_index),
+            MissingSenderInput => f.write_str("a sender input was removed"),
+            NewSenderInput { input_index } =>
+                write!(f, "added input {} spends a sender script", input_index),
+            ReceiverInputNotFinalized { input_index } =>
+                write!(f, "receiver input {} is not finalized", input_index),
+            MissingReceiverInputUtxo { input_index } =>
+                write!(f, "receiver input {} has no funding utxo", input_index),
+            MixedSequences { input_index } =>
+                write!(f, "receiver input {} has a different sequence", input_index),
+            MixedInputTypes { input_index } =>
+                write!(f, "receiver input {} spends a different script type", input_index),
+            MissingSenderOutput { output_index } =>
+                write!(f, "sender output {} was removed", output_index),
+            SenderOutputChanged { output_index } =>
+                write!(f, "amount of sender output {} was changed", output_index),
+            PayeeOutputChanged =>
+                f.write_str("payment output was changed although output substitution is disabled"),
+            FeeContributionTooHigh { contribution, max } =>
+                write!(f, "fee contribution {} exceeds the maximum {}", contribution, max),
+            FeeContributionNotForFee { contribution, additional_fee } => write!(
+                f,
+                "fee contribution {} exceeds the additional fee {}",
+                contribution, additional_fee
+            ),
+            FeeRateBelowMinimum { fee_rate, minimum } =>
+                write!(f, "fee rate {} is below the minimum {}", fee_rate, minimum),
+            Fee(ref e) => write_err!(f, "failed to calculate proposal fee"; e),
+            DetermineLockTime(ref e) => write_err!(f, "proposal determine lock time"; e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ProposalError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use ProposalError::*;
+
+        match *self {
+            Fee(ref e) => Some(e),
+            DetermineLockTime(ref e) => Some(e),
+            VersionChanged
+            | LockTimeChanged
+            | KeyOrigins
+            | SenderInputSigned { .. }
+            | SenderInputUtxo { .. }
+            | SequenceChanged { .. }
+            | DuplicateInput { .. }
+            | MissingSenderInput
+            | NewSenderInput { .. }
+            | ReceiverInputNotFinalized { .. }
+            | MissingReceiverInputUtxo { .. }
+            | MixedSequences { .. }
+            | MixedInputTypes { .. }
+            | MissingSenderOutput { .. }
+            | SenderOutputChanged { .. }
+            | PayeeOutputChanged
+            | FeeContributionTooHigh { .. }
+            | FeeContributionNotForFee { .. }
+            | FeeRateBelowMinimum { .. } => None,
+        }
+    }
+}
+
+impl From<FeeError> for ProposalError {
+    fn from(e: FeeError) -> Self { Self::Fee(e) }
+}
+
+impl From<DetermineLockTimeError> for ProposalError {
+    fn from(e: DetermineLockTimeError) -> Self { Self::DetermineLockTime(e) }
+}
+
+/// Error contributing inputs or substituting outputs.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ContributeError {
+    /// The contributed input has no funding utxo.
+    MissingFundingUtxo,
+    /// The contributed input is already spent by the proposal.
+    DuplicateInput,
+    /// The contributed input spends a different script type than the sender inputs.
+    MixedInputTypes,
+    /// No output pays to the script pubkey.
+    MissingOutput,
+    /// The sender disabled output substitution.
+    OutputSubstitutionDisabled,
+    /// Failed to determine lock time.
+    DetermineLockTime(DetermineLockTimeError),
+}
+
+impl fmt::Display for ContributeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use ContributeError::*;
+
+        match *self {
+            MissingFundingUtxo => f.write_str("contributed input has no funding utxo"),
+            DuplicateInput => f.write_str("contributed input is already spent"),
+            MixedInputTypes =>
+                f.write_str("contributed input spends a different script type than the sender"),
+            MissingOutput => f.write_str("no output pays to the script pubkey"),
+            OutputSubstitutionDisabled => f.write_str("output substitution is disabled"),
+            DetermineLockTime(ref e) => write_err!(f, "contribute determine lock time"; e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ContributeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use ContributeError::*;
+
+        match *self {
+            DetermineLockTime(ref e) => Some(e),
+            MissingFundingUtxo
+            | DuplicateInput
+            | MixedInputTypes
+            | MissingOutput
+            | OutputSubstitutionDisabled => None,
+        }
+    }
+}
+
+impl From<DetermineLockTimeError> for ContributeError {
+    fn from(e: DetermineLockTimeError) -> Self { Self::DetermineLockTime(e) }
+}
+
+/// Error finalizing the receiver's payjoin proposal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FinalizeProposalError {
+    /// A receiver input is not finalized.
+    InputNotFinalized {
+        /// The index of the input in the proposal.
+        input_index: usize,
+    },
+}
+
+impl fmt::Display for FinalizeProposalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use FinalizeProposalError::*;
+
+        match *self {
+            InputNotFinalized { input_index } =>
+                write!(f, "receiver input {} is not finalized", input_index),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for FinalizeProposalError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use FinalizeProposalError::*;
+
+        match *self {
+            InputNotFinalized { .. } => None,
+        }
+    }
+}
@@ -0,0 +1,416 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! A borrowed view of a serialized PSBT v2.
+//!
+//! [`PsbtRef`] indexes a serialized PSBT into the byte slices of its maps without decoding any of
+//! the values, fields are only decoded when they are asked for. This is useful when only a few
+//! fields are needed e.g., to check the output amounts and scripts of a large PSBT.
+
+use core::fmt;
+
+use bitcoin::{transaction, Amount, OutPoint, Script, Sequence, TxOut, Txid};
+
+use crate::consts::{
+    PSBT_GLOBAL_INPUT_COUNT, PSBT_GLOBAL_OUTPUT_COUNT, PSBT_GLOBAL_TX_VERSION, PSBT_GLOBAL_VERSION,
+    PSBT_IN_OUTPUT_INDEX, PSBT_IN_PREVIOUS_TXID, PSBT_IN_SEQUENCE, PSBT_IN_WITNESS_UTXO,
+    PSBT_OUT_AMOUNT, PSBT_OUT_SCRIPT,
+};
+use crate::error::write_err;
+use crate::prelude::*;
+use crate::raw;
+use crate::serialize::{self, Deserialize};
+use crate::v2::{MAGIC_BYTES, PSBT_SERPARATOR};
+
+/// A borrowed, lazily decoded view of a serialized PSBT v2.
+///
+/// Creating a `PsbtRef` checks the structure of the PSBT (i.e., that it is made up of correctly
+/// length prefixed key-value pairs and has as many input and output maps as the global map says)
+/// but does not decode any values other than the version and the input and output counts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PsbtRef<'a> {
+    global: MapRef<'a>,
+    inputs: Vec<MapRef<'a>>,
+    outputs: Vec<MapRef<'a>>,
+    len: usize,
+}
+
+impl<'a> PsbtRef<'a> {
+    /// Indexes the serialized PSBT in `bytes`.
+    ///
+    /// Any bytes following the last output map are ignored, use [`PsbtRef::len`] to get the
+    /// number of bytes the PSBT spans.
+    pub fn new(bytes: &'a [u8]) -> Result<Self, PsbtRefError> {
+        use PsbtRefError::*;
+
+        if bytes.get(0..MAGIC_BYTES.len()) != Some(MAGIC_BYTES) {
+            return Err(InvalidMagic);
+        }
+        if bytes.get(MAGIC_BYTES.len()) != Some(&PSBT_SERPARATOR) {
+            return Err(InvalidSeparator);
+        }
+        let mut rest = &bytes[MAGIC_BYTES.len() + 1..];
+
+        let global = MapRef::split(&mut rest)?;
+        match global.get(PSBT_GLOBAL_VERSION, &[]) {
+            Some(version) if version == 2_u32.to_le_bytes() => {}
+            Some(_) => return Err(UnsupportedVersion),
+            None => return Err(MissingVersion),
+        }
+        let input_count = global.count(PSBT_GLOBAL_INPUT_COUNT).ok_or(MissingInputCount)??;
+        let output_count = global.count(PSBT_GLOBAL_OUTPUT_COUNT).ok_or(MissingOutputCount)??;
+
+        // Do not trust the counts for allocation, each map is at least one byte.
+        let mut inputs = Vec::with_capacity(input_count.min(rest.len()));
+        for _ in 0..input_count {
+            inputs.push(MapRef::split(&mut rest)?);
+        }
+        let mut outputs = Vec::with_capacity(output_count.min(rest.len()));
+        for _ in 0..output_count {
+            outputs.push(MapRef::split(&mut rest)?);
+        }
+
+        Ok(PsbtRef { global, inputs, outputs, len: bytes.len() - rest.len() })
+    }
+
+    /// Returns the number of bytes of the serialized PSBT.
+    #[allow(clippy::len_without_is_empty)] // A PSBT is never empty.
+    pub fn len(&self) -> usize { self.len }
+
+    /// Returns the global map.
+    pub fn global(&self) -> MapRef<'a> { self.global }
+
+    /// Returns the number of inputs.
+    pub fn input_count(&self) -> usize { self.inputs.len() }
+
+    /// Returns the number of outputs.
+    pub fn output_count(&self) -> usize { self.outputs.len() }
+
+    /// Returns the input map at `index`.
+    pub fn input(&self, index: usize) -> Option<InputRef<'a>> {
+        self.inputs.get(index).map(|map| InputRef(*map))
+    }
+
+    /// Returns the output map at `index`.
+    pub fn output(&self, index: usize) -> Option<OutputRef<'a>> {
+        self.outputs.get(index).map(|map| OutputRef(*map))
+    }
+
+    /// Returns an iterator over the input maps.
+    pub fn inputs(&self) -> impl Iterator<Item = InputRef<'a>> + '_ {
+        self.inputs.iter().map(|map| InputRef(*map))
+    }
+
+    /// Returns an iterator over the output maps.
+    pub fn outputs(&self) -> impl Iterator<Item = OutputRef<'a>> + '_ {
+        self.outputs.iter().map(|map| OutputRef(*map))
+    }
+
+    /// Decodes the transaction version.
+    pub fn tx_version(&self) -> Result<transaction::Version, FieldError> {
+        self.global.decode(PSBT_GLOBAL_TX_VERSION, "PSBT_GLOBAL_TX_VERSION")
+    }
+}
+
+/// A borrowed input map of a [`PsbtRef`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct InputRef<'a>(MapRef<'a>);
+
+impl<'a> InputRef<'a> {
+    /// Returns the key-value pairs of this input.
+    pub fn map(&self) -> MapRef<'a> { self.0 }
+
+    /// Decodes the txid of the transaction being spent.
+    pub fn previous_txid(&self) -> Result<Txid, FieldError> {
+        self.0.decode(PSBT_IN_PREVIOUS_TXID, "PSBT_IN_PREVIOUS_TXID")
+    }
+
+    /// Decodes the index of the output being spent.
+    pub fn spent_output_index(&self) -> Result<u32, FieldError> {
+        self.0.decode(PSBT_IN_OUTPUT_INDEX, "PSBT_IN_OUTPUT_INDEX")
+    }
+
+    /// Decodes the outpoint being spent.
+    pub fn out_point(&self) -> Result<OutPoint, FieldError> {
+        Ok(OutPoint { txid: self.previous_txid()?, vout: self.spent_output_index()? })
+    }
+
+    /// Decodes the sequence number, if present.
+    pub fn sequence(&self) -> Result<Option<Sequence>, FieldError> {
+        self.0.decode_optional(PSBT_IN_SEQUENCE, "PSBT_IN_SEQUENCE")
+    }
+
+    /// Decodes the witness UTXO, if present.
+    pub fn witness_utxo(&self) -> Result<Option<TxOut>, FieldError> {
+        self.0.decode_optional(PSBT_IN_WITNESS_UTXO, "PSBT_IN_WITNESS_UTXO")
+    }
+}
+
+/// A borrowed output map of a [`PsbtRef`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct OutputRef<'a>(MapRef<'a>);
+
+impl<'a> OutputRef<'a> {
+    /// Returns the key-value pairs of this output.
+    pub fn map(&self) -> MapRef<'a> { self.0 }
+
+    /// Decodes the output amount.
+    pub fn amount(&self) -> Result<Amount, FieldError> {
+        self.0.decode(PSBT_OUT_AMOUNT, "PSBT_OUT_AMOUNT")
+    }
+
+    /// Returns the output script without copying it, if present.
+    ///
+    /// The script is only missing for silent payment outputs that have not been computed yet.
+    pub fn script_pubkey(&self) -> Option<&'a Script> {
+        self.0.get(PSBT_OUT_SCRIPT, &[]).map(Script::from_bytes)
+    }
+}
+
+/// A borrowed key-value map of a [`PsbtRef`], excluding the `0x00` separator.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MapRef<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> MapRef<'a> {
+    /// Splits the map at the front of `bytes` off, checking that all its pairs are well formed.
+    fn split(bytes: &mut &'a [u8]) -> Result<Self, PsbtRefError> {
+        let start = *bytes;
+        loop {
+            match PairRef::split(bytes)? {
+                Some(_) => {}
+                None => {
+                    // Exclude the separator.
+                    let len = start.len() - bytes.len() - 1;
+                    return Ok(MapRef { bytes: &start[..len] });
+                }
+            }
+        }
+    }
+
+    /// Returns the raw bytes of the pairs of this map.
+    pub fn as_bytes(&self) -> &'a [u8] { self.bytes }
+
+    /// Returns an iterator over the key-value pairs of this map.
+    pub fn pairs(&self) -> Pairs<'a> { Pairs { bytes: self.bytes } }
+
+    /// Returns the value of the pair with key type `type_value` and key data `key`, if present.
+    pub fn get(&self, type_value: u8, key: &[u8]) -> Option<&'a [u8]> {
+        self.pairs()
+            .find(|pair| pair.type_value == type_value && pair.key == key)
+            .map(|pair| pair.value)
+    }
+
+    /// Decodes a compact size count.
+    fn count(&self, type_value: u8) -> Option<Result<usize, PsbtRefError>> {
+        self.get(type_value, &[]).map(|mut value| {
+            let count = read_compact_size(&mut value)?;
+            if !value.is_empty() {
+                return Err(PsbtRefError::InvalidCount);
+            }
+            usize::try_from(count).map_err(|_| PsbtRefError::InvalidCount)
+        })
+    }
+
+    /// Decodes the required field with key type `type_value` and no key data.
+    fn decode<T: Deserialize>(&self, type_value: u8, field: &'static str) -> Result<T, FieldError> {
+        self.decode_optional(type_value, field)?.ok_or(FieldError::Missing { field })
+    }
+
+    /// Decodes the optional field with key type `type_value` and no key data.
+    fn decode_optional<T: Deserialize>(
+        &self,
+        type_value: u8,
+        field: &'static str,
+    ) -> Result<Option<T>, FieldError> {
+        self.get(type_value, &[])
+            .map(|value| {
+                T::deserialize(value).map_err(|error| FieldError::Deserialize { field, error })
+            })
+            .transpose()
+    }
+}
+
+/// A borrowed key-value pair.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PairRef<'a> {
+    /// The key type.
+    pub type_value: u8,
+    /// The key data.
+    pub key: &'a [u8],
+    /// The value data.
+    pub value: &'a [u8],
+}
+
+impl<'a> PairRef<'a> {
+    /// Splits the pair at the front of `bytes` off, returns `None` if it is the map separator.
+    fn split(bytes: &mut &'a [u8]) -> Result<Option<Self>, PsbtRefError> {
+        let key_len = read_compact_size(bytes)?;
+        if key_len == 0 {
+            return Ok(None);
+        }
+        let key = take(bytes, key_len)?;
+        let value_len = read_compact_size(bytes)?;
+        let value = take(bytes, value_len)?;
+
+        Ok(Some(PairRef { type_value: key[0], key: &key[1..], value }))
+    }
+
+    /// Copies this pair into an owned [`raw::Pair`].
+    pub fn to_pair(&self) -> raw::Pair {
+        raw::Pair {
+            key: raw::Key { type_value: self.type_value, key: self.key.to_vec() },
+            value: self.value.to_vec(),
+        }
+    }
+}
+
+/// An iterator over the key-value pairs of a [`MapRef`].
+#[derive(Debug, Clone)]
+pub struct Pairs<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Iterator for Pairs<'a> {
+    type Item = PairRef<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.bytes.is_empty() {
+            return None;
+        }
+        let pair = PairRef::split(&mut self.bytes).expect("pairs are checked when indexing");
+        Some(pair.expect("the separator is not part of the map"))
+    }
+}
+
+/// Reads a compact size (a.k.a. `VarInt`) from the front of `bytes`.
+fn read_compact_size(bytes: &mut &[u8]) -> Result<u64, PsbtRefError> {
+    let (&prefix, rest) = bytes.split_first().ok_or(PsbtRefError::UnexpectedEnd)?;
+    *bytes = rest;
+    let (len, min) = match prefix {
+        0xfd => (2, 0xfd),
+        0xfe => (4, 0x1_0000),
+        0xff => (8, 0x1_0000_0000),
+        n => return Ok(u64::from(n)),
+    };
+    let mut buf = [0_u8; 8];
+    buf[..len].copy_from_slice(take(bytes, len as u64)?);
+    let n = u64::from_le_bytes(buf);
+    if n < min {
+        return Err(PsbtRefError::NonMinimalCompactSize);
+    }
+    Ok(n)
+}
+
+/// Takes `len` bytes from the front of `bytes`.
+fn take<'a>(bytes: &mut &'a [u8], len: u64) -> Result<&'a [u8], PsbtRefError> {
+    let len = usize::try_from(len).map_err(|_| PsbtRefError::UnexpectedEnd)?;
+    if bytes.len() < len {
+        return Err(PsbtRefError::UnexpectedEnd);
+    }
+    let (taken, rest) = bytes.split_at(len);
+    *bytes = rest;
+    Ok(taken)
+}
+
+/// Error indexing a serialized PSBT.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PsbtRefError {
+    /// Invalid magic bytes, expected the ASCII for "psbt" serialized in most significant byte order.
+    InvalidMagic,
+    /// The separator for a PSBT must be `0xff`.
+    InvalidSeparator,
+    /// The data ended in the middle of a key-value pair or map.
+    UnexpectedEnd,
+    /// A compact size was not encoded using the minimal number of bytes.
+    NonMinimalCompactSize,
+    /// The global map has no version, this is not a PSBT v2.
+    MissingVersion,
+    /// The global map has a version other than 2.
+    UnsupportedVersion,
+    /// The global map has no input count.
+    MissingInputCount,
+    /// The global map has no output count.
+    MissingOutputCount,
+    /// The input or output count is not a valid compact size.
+    InvalidCount,
+}
+
+impl fmt::Display for PsbtRefError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use PsbtRefError::*;
+
+        match *self {
+            InvalidMagic => f.write_str("invalid magic"),
+            InvalidSeparator => f.write_str("invalid separator"),
+            UnexpectedEnd => f.write_str("unexpected end of data"),
+            NonMinimalCompactSize => f.write_str("non-minimal compact size"),
+            MissingVersion => f.write_str("global map has no version"),
+            UnsupportedVersion => f.write_str("global map version is not 2"),
+            MissingInputCount => f.write_str("global map has no input count"),
+            MissingOutputCount => f.write_str("global map has no output count"),
+            InvalidCount => f.write_str("invalid input or output count"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PsbtRefError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use PsbtRefError::*;
+
+        match *self {
+            InvalidMagic
+            | InvalidSeparator
+            | UnexpectedEnd
+            | NonMinimalCompactSize
+            | MissingVersion
+            | UnsupportedVersion
+            | MissingInputCount
+            | MissingOutputCount
+            | InvalidCount => None,
+        }
+    }
+}
+
+/// Error decoding a field of a [`PsbtRef`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum FieldError {
+    /// The field is required but not present.
+    Missing {
+        /// The name of the field's key type.
+        field: &'static str,
+    },
+    /// The field value could not be decoded.
+    Deserialize {
+        /// The name of the field's key type.
+        field: &'static str,
+        /// The decoding error.
+        error: serialize::Error,
+    },
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use FieldError::*;
+
+        match *self {
+            Missing { field } => write!(f, "missing required field {}", field),
+            Deserialize { field, ref error } => write_err!(f, "unable to decode {}", field; error),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for FieldError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use FieldError::*;
+
+        match *self {
+            Deserialize { ref error, .. } => Some(error),
+            Missing { .. } => None,
+        }
+    }
+}
@@ -5,8 +5,9 @@ use core::fmt;
 
 use bitcoin::consensus::encode as consensus;
 
-use crate::prelude::Vec;
-use crate::serialize::{self, Deserialize, Serialize};
+use crate::io;
+use crate::io_ext::Encode;
+use crate::serialize::{self, Deserialize};
 
 /// The PSBT version.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -47,8 +48,12 @@ impl TryFrom<u32> for Version {
     }
 }
 
-impl Serialize for Version {
-    fn serialize(&self) -> Vec<u8> { consensus::serialize(&self.to_u32()) }
+impl Encode for Version {
+    fn encode_to<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<()> {
+        self.to_u32().encode_to(w)
+    }
+
+    fn encoded_len(&self) -> usize { 4 }
 }
 
 impl Deserialize for Version {
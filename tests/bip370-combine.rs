@@ -0,0 +1,89 @@
+//! BIP-370 Combiner role.
+
+#![cfg(feature = "std")]
+
+use psbt_v2::bitcoin::hashes::Hash as _;
+use psbt_v2::bitcoin::{Amount, OutPoint, ScriptBuf, TxOut, Txid};
+use psbt_v2::raw::ProprietaryKey;
+use psbt_v2::v2::{self, CombineError, Constructor, InputBuilder, Modifiable, OutputBuilder, Psbt};
+
+fn out_point(vout: u32) -> OutPoint { OutPoint { txid: Txid::all_zeros(), vout } }
+
+fn txout(sats: u64) -> TxOut {
+    TxOut { value: Amount::from_sat(sats), script_pubkey: ScriptBuf::new_op_return([0x01]) }
+}
+
+/// Creates a PSBT with `n` inputs and one output paying `sats`.
+fn psbt(n: u32, sats: u64) -> Psbt {
+    let mut constructor = Constructor::<Modifiable>::default();
+    for vout in 0..n {
+        constructor = constructor.input(InputBuilder::new(&out_point(vout)).build());
+    }
+    constructor.output(OutputBuilder::new(txout(sats)).build()).psbt().expect("valid lock time")
+}
+
+/// Adds a proprietary key to the first input to simulate a cosigner response.
+fn cosigner_response(mut psbt: Psbt, cosigner: u8) -> Psbt {
+    let key = ProprietaryKey { prefix: b"test".to_vec(), subtype: 0x00, key: vec![cosigner] };
+    psbt.inputs[0].proprietaries.insert(key, vec![cosigner]);
+    psbt
+}
+
+#[test]
+fn combine_same_psbt() {
+    let a = cosigner_response(psbt(2, 1_000), 0x01);
+    let b = cosigner_response(psbt(2, 1_000), 0x02);
+
+    let combined = v2::combine(a, b).expect("same transaction");
+    assert_eq!(combined.inputs[0].proprietaries.len(), 2);
+}
+
+#[test]
+fn combine_input_count_mismatch() {
+    let err = v2::combine(psbt(2, 1_000), psbt(1, 1_000)).expect_err("different input counts");
+    assert_eq!(err, CombineError::InputCountMismatch { this: 2, that: 1 });
+}
+
+#[test]
+fn combine_output_count_mismatch() {
+    let mut other = psbt(1, 1_000);
+    other.outputs.push(OutputBuilder::new(txout(2_000)).build());
+    other.global.output_count += 1;
+
+    let err = v2::combine(psbt(1, 1_000), other).expect_err("different output counts");
+    assert_eq!(err, CombineError::OutputCountMismatch { this: 1, that: 2 });
+}
+
+#[test]
+fn combine_map_count_mismatch() {
+    // Same number of input maps, but the global input count of `other` is wrong.
+    let mut other = psbt(2, 1_000);
+    other.global.input_count = 3;
+    let err = v2::combine(psbt(2, 1_000), other).expect_err("inconsistent input count");
+    assert_eq!(err, CombineError::InputMapCountMismatch { input_count: 3, input_maps: 2 });
+
+    let mut other = psbt(1, 1_000);
+    other.global.output_count = 0;
+    let err = v2::combine(psbt(1, 1_000), other).expect_err("inconsistent output count");
+    assert_eq!(err, CombineError::OutputMapCountMismatch { output_count: 0, output_maps: 1 });
+}
+
+#[test]
+fn combine_id_mismatch() {
+    let err = v2::combine(psbt(1, 1_000), psbt(1, 2_000)).expect_err("different transactions");
+    assert!(matches!(err, CombineError::IdMismatch { .. }));
+}
+
+#[test]
+fn combine_all() {
+    let psbts = (1..=3).map(|cosigner| cosigner_response(psbt(1, 1_000), cosigner));
+
+    let combined = v2::combine_all(psbts).expect("same transaction");
+    assert_eq!(combined.inputs[0].proprietaries.len(), 3);
+}
+
+#[test]
+fn combine_all_empty() {
+    let err = v2::combine_all(Vec::new()).expect_err("nothing to combine");
+    assert_eq!(err, CombineError::NoPsbts);
+}
@@ -0,0 +1,178 @@
+//! BIP-370 Constructor adding and removing inputs and outputs.
+
+#![cfg(feature = "std")]
+
+use psbt_v2::bitcoin::hashes::Hash as _;
+use psbt_v2::bitcoin::secp256k1::{Message, Secp256k1, SecretKey};
+use psbt_v2::bitcoin::sighash::EcdsaSighashType;
+use psbt_v2::bitcoin::{ecdsa, Amount, OutPoint, PublicKey, ScriptBuf, TxOut, Txid};
+use psbt_v2::v2::{
+    Constructor, Creator, Input, InputBuilder, InputsOnlyModifiable, Modifiable, OutputBuilder,
+    OutputsOnlyModifiable, RemoveError,
+};
+
+fn out_point(vout: u32) -> OutPoint { OutPoint { txid: Txid::all_zeros(), vout } }
+
+fn txout(script: u8) -> TxOut {
+    TxOut { value: Amount::from_sat(1_000), script_pubkey: ScriptBuf::new_op_return([script]) }
+}
+
+fn constructor() -> Constructor<Modifiable> {
+    Constructor::<Modifiable>::default()
+        .input(InputBuilder::new(&out_point(0)).build())
+        .input(InputBuilder::new(&out_point(1)).build())
+        .input(InputBuilder::new(&out_point(2)).build())
+        .output(OutputBuilder::new(txout(0)).build())
+        .output(OutputBuilder::new(txout(1)).build())
+        .output(OutputBuilder::new(txout(1)).build())
+}
+
+#[test]
+fn remove_input_by_index() {
+    let psbt = constructor().remove_input(1).expect("valid index").psbt().expect("valid psbt");
+
+    assert_eq!(psbt.global.input_count, 2);
+    assert_eq!(psbt.inputs.len(), 2);
+    assert_eq!(psbt.inputs[0].spent_output_index, 0);
+    assert_eq!(psbt.inputs[1].spent_output_index, 2);
+}
+
+#[test]
+fn remove_input_index_out_of_bounds() {
+    assert!(constructor().remove_input(3).is_err());
+}
+
+#[test]
+fn remove_inputs_spending() {
+    let psbt = constructor()
+        .remove_inputs_spending(&out_point(0))
+        .expect("no SIGHASH_SINGLE inputs")
+        .remove_inputs_spending(&out_point(7)) // Not in the PSBT, no-op.
+        .expect("no SIGHASH_SINGLE inputs")
+        .psbt()
+        .expect("valid psbt");
+
+    assert_eq!(psbt.global.input_count, 2);
+    assert!(psbt.inputs.iter().all(|input| input.spent_output_index != 0));
+}
+
+#[test]
+fn remove_output_by_index() {
+    let psbt = constructor().remove_output(0).expect("valid index").psbt().expect("valid psbt");
+
+    assert_eq!(psbt.global.output_count, 2);
+    assert_eq!(psbt.outputs.len(), 2);
+    assert!(psbt.outputs.iter().all(|output| output.script_pubkey == txout(1).script_pubkey));
+}
+
+#[test]
+fn remove_output_index_out_of_bounds() {
+    assert!(constructor().remove_output(3).is_err());
+}
+
+#[test]
+fn remove_outputs_paying_to() {
+    let psbt = constructor()
+        .remove_outputs_paying_to(&txout(1).script_pubkey)
+        .expect("no SIGHASH_SINGLE inputs")
+        .psbt()
+        .expect("valid psbt");
+
+    assert_eq!(psbt.global.output_count, 1);
+    assert_eq!(psbt.outputs[0].script_pubkey, txout(0).script_pubkey);
+}
+
+#[test]
+fn remove_with_single_modifiable_constructors() {
+    let psbt = Constructor::<InputsOnlyModifiable>::default()
+        .input(InputBuilder::new(&out_point(0)).build())
+        .remove_input(0)
+        .expect("valid index")
+        .psbt()
+        .expect("valid psbt");
+    assert_eq!(psbt.global.input_count, 0);
+
+    let psbt = Constructor::<OutputsOnlyModifiable>::default()
+        .output(OutputBuilder::new(txout(0)).build())
+        .remove_output(0)
+        .expect("valid index")
+        .psbt()
+        .expect("valid psbt");
+    assert_eq!(psbt.global.output_count, 0);
+}
+
+/// Creates an input that has been signed using `SIGHASH_SINGLE`.
+fn sighash_single_input(vout: u32) -> Input {
+    let secp = Secp256k1::new();
+    let sk = SecretKey::from_slice(&[0x01; 32]).expect("valid secret key");
+    let msg = Message::from_digest([0xab; 32]);
+    let sig = ecdsa::Signature {
+        sig: secp.sign_ecdsa(&msg, &sk),
+        hash_ty: EcdsaSighashType::SinglePlusAnyoneCanPay,
+    };
+
+    let mut input = InputBuilder::new(&out_point(vout)).build();
+    input.partial_sigs.insert(PublicKey::new(sk.public_key(&secp)), sig);
+    input
+}
+
+/// Creates a constructor with the input at index 1 signed using `SIGHASH_SINGLE`.
+fn sighash_single_constructor() -> Constructor<Modifiable> {
+    Creator::new()
+        .sighash_single()
+        .constructor_modifiable()
+        .input(InputBuilder::new(&out_point(0)).build())
+        .input(sighash_single_input(1))
+        .input(InputBuilder::new(&out_point(2)).build())
+        .output(OutputBuilder::new(txout(0)).build())
+        .output(OutputBuilder::new(txout(1)).build())
+}
+
+fn remove_err(res: Result<Constructor<Modifiable>, RemoveError>) -> RemoveError {
+    match res {
+        Ok(_) => panic!("expected removal to fail"),
+        Err(e) => e,
+    }
+}
+
+#[test]
+fn remove_keeps_sighash_single_pairing() {
+    let err = remove_err(sighash_single_constructor().remove_input(0));
+    assert_eq!(err, RemoveError::BreaksSighashSinglePairing { input_index: 1 });
+
+    let err = remove_err(sighash_single_constructor().remove_output(1));
+    assert_eq!(err, RemoveError::BreaksSighashSinglePairing { input_index: 1 });
+
+    let err =
+        remove_err(sighash_single_constructor().remove_outputs_paying_to(&txout(0).script_pubkey));
+    assert_eq!(err, RemoveError::BreaksSighashSinglePairing { input_index: 1 });
+
+    // Removing inputs after the signed input, or the signed input itself, is fine.
+    let psbt = sighash_single_constructor()
+        .remove_input(2)
+        .expect("does not shift signed input")
+        .remove_inputs_spending(&out_point(1))
+        .expect("removes signed input")
+        .psbt()
+        .expect("valid psbt");
+    assert_eq!(psbt.global.input_count, 1);
+}
+
+#[test]
+fn input_output_pair_keeps_sighash_single_pairing() {
+    let psbt = sighash_single_constructor()
+        .input_output_pair(sighash_single_input(3), OutputBuilder::new(txout(3)).build())
+        .input(InputBuilder::new(&out_point(4)).build())
+        .output(OutputBuilder::new(txout(4)).build())
+        .psbt()
+        .expect("valid psbt");
+
+    assert_eq!(psbt.global.input_count, 5);
+    assert_eq!(psbt.global.output_count, 4);
+    // The existing pair is untouched.
+    assert_eq!(psbt.inputs[1].spent_output_index, 1);
+    assert_eq!(psbt.outputs[1].script_pubkey, txout(1).script_pubkey);
+    // The new pair is at matching indices.
+    assert_eq!(psbt.inputs[2].spent_output_index, 3);
+    assert_eq!(psbt.outputs[2].script_pubkey, txout(3).script_pubkey);
+}
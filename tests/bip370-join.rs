@@ -0,0 +1,167 @@
+//! Joining PSBTs built by separate BIP-370 Constructors.
+
+#![cfg(feature = "std")]
+
+use psbt_v2::bitcoin::hashes::Hash as _;
+use psbt_v2::bitcoin::secp256k1::{self, Secp256k1, SecretKey};
+use psbt_v2::bitcoin::{
+    absolute, ecdsa, transaction, Amount, EcdsaSighashType, OutPoint, PublicKey, ScriptBuf, TxOut,
+    Txid,
+};
+use psbt_v2::raw::ProprietaryKey;
+use psbt_v2::v2::{self, Constructor, InputBuilder, JoinError, Modifiable, OutputBuilder, Psbt};
+
+fn out_point(vout: u32) -> OutPoint { OutPoint { txid: Txid::all_zeros(), vout } }
+
+fn txout(sats: u64) -> TxOut {
+    TxOut { value: Amount::from_sat(sats), script_pubkey: ScriptBuf::new_op_return([0x01]) }
+}
+
+/// Creates a PSBT spending `vouts` with one output paying `sats`.
+fn psbt(vouts: &[u32], sats: u64) -> Psbt {
+    let mut constructor = Constructor::<Modifiable>::default();
+    for &vout in vouts {
+        constructor = constructor.input(InputBuilder::new(&out_point(vout)).build());
+    }
+    constructor.output(OutputBuilder::new(txout(sats)).build()).psbt().expect("valid lock time")
+}
+
+/// Marks the input at `index` as signed using `SIGHASH_SINGLE`.
+fn add_sighash_single_sig(psbt: &mut Psbt, index: usize) {
+    let secp = Secp256k1::new();
+    let sk = SecretKey::from_slice(&[0x01; 32]).expect("valid key");
+    let pk = PublicKey::new(secp256k1::PublicKey::from_secret_key(&secp, &sk));
+    let sig = ecdsa::Signature {
+        sig: secp.sign_ecdsa(&secp256k1::Message::from_digest([0x01; 32]), &sk),
+        hash_ty: EcdsaSighashType::SinglePlusAnyoneCanPay,
+    };
+    psbt.inputs[index].partial_sigs.insert(pk, sig);
+    psbt.global.set_sighash_single_flag();
+}
+
+#[test]
+fn join_unions_inputs_and_outputs() {
+    let mut this = psbt(&[0, 1], 1_000);
+    let mut that = psbt(&[1, 2], 2_000);
+    let key = ProprietaryKey { prefix: b"test".to_vec(), subtype: 0x00, key: vec![0x01] };
+    this.inputs[1].proprietaries.insert(key.clone(), vec![0x01]);
+    that.inputs[0].sequence = Some(transaction::Sequence::ENABLE_RBF_NO_LOCKTIME);
+
+    let joined = this.clone().join_with(that).expect("no conflicts");
+    let vouts: Vec<u32> = joined.inputs.iter().map(|input| input.spent_output_index).collect();
+    assert_eq!(vouts, vec![0, 1, 2]);
+    assert_eq!(joined.global.input_count, 3);
+    // The duplicate input is combined.
+    assert_eq!(joined.inputs[1].proprietaries.get(&key), Some(&vec![0x01]));
+    assert_eq!(joined.inputs[1].sequence, Some(transaction::Sequence::ENABLE_RBF_NO_LOCKTIME));
+
+    let amounts: Vec<u64> = joined.outputs.iter().map(|output| output.amount.to_sat()).collect();
+    assert_eq!(amounts, vec![1_000, 2_000]);
+    assert_eq!(joined.global.output_count, 2);
+    assert_eq!(joined.global.tx_modifiable_flags, this.global.tx_modifiable_flags);
+}
+
+#[test]
+fn join_all() {
+    let psbts = vec![psbt(&[0], 1_000), psbt(&[1], 2_000), psbt(&[2], 3_000)];
+    let joined = v2::join_all(psbts).expect("no conflicts");
+    assert_eq!(joined.inputs.len(), 3);
+    assert_eq!(joined.outputs.len(), 3);
+
+    assert!(matches!(v2::join_all(vec![]), Err(JoinError::NoPsbts)));
+}
+
+#[test]
+fn join_requires_modifiable_flags() {
+    let this = psbt(&[0, 1], 1_000);
+    let mut that = psbt(&[1], 2_000);
+    that.global.clear_inputs_modifiable_flag();
+
+    // `that` gains input 0.
+    assert!(matches!(v2::join(this.clone(), that.clone()), Err(JoinError::InputsNotModifiable(_))));
+
+    // Neither gains inputs, both gain outputs.
+    let mut same_inputs = psbt(&[0, 1], 2_000);
+    same_inputs.global.clear_inputs_modifiable_flag();
+    let joined = v2::join(this.clone(), same_inputs).expect("outputs modifiable");
+    assert_eq!(joined.inputs.len(), 2);
+    assert!(!joined.global.is_inputs_modifiable());
+    assert!(joined.global.is_outputs_modifiable());
+
+    that.global.set_inputs_modifiable_flag();
+    that.global.clear_outputs_modifiable_flag();
+    assert!(matches!(v2::join(this, that), Err(JoinError::OutputsNotModifiable(_))));
+}
+
+#[test]
+fn join_reconciles_globals() {
+    let lock_time = absolute::LockTime::from_height(800_000).expect("valid height");
+    let this = psbt(&[0], 1_000);
+    let mut that = psbt(&[1], 2_000);
+    that.global.fallback_lock_time = Some(lock_time);
+
+    let joined = v2::join(this.clone(), that.clone()).expect("no conflicts");
+    assert_eq!(joined.global.fallback_lock_time, Some(lock_time));
+
+    let mut other = this.clone();
+    other.global.fallback_lock_time = Some(absolute::LockTime::ZERO);
+    assert!(matches!(v2::join(other, that), Err(JoinError::FallbackLockTimeMismatch { .. })));
+
+    let mut other = psbt(&[2], 3_000);
+    other.global.tx_version = transaction::Version::ONE;
+    assert!(matches!(v2::join(this, other), Err(JoinError::TxVersionMismatch { .. })));
+}
+
+#[test]
+fn join_keeps_sighash_single_pairing() {
+    let this = psbt(&[0, 1], 1_000);
+    let mut that = psbt(&[5, 6], 2_000);
+    that = Constructor::<Modifiable>::new(that)
+        .expect("modifiable")
+        .output(OutputBuilder::new(txout(3_000)).build())
+        .psbt()
+        .expect("valid lock time");
+
+    // Paired with the 3_000 sat output.
+    add_sighash_single_sig(&mut that, 1);
+
+    let joined = v2::join(this, that).expect("no conflicts");
+    let vouts: Vec<u32> = joined.inputs.iter().map(|input| input.spent_output_index).collect();
+    // Inserted at the same index as its output.
+    assert_eq!(vouts, vec![0, 6, 1, 5]);
+    let amounts: Vec<u64> = joined.outputs.iter().map(|output| output.amount.to_sat()).collect();
+    assert_eq!(amounts, vec![1_000, 3_000, 2_000]);
+    assert!(joined.global.has_sighash_single());
+}
+
+#[test]
+fn join_sighash_single_without_output() {
+    let this = psbt(&[0], 1_000);
+    let mut that = psbt(&[5, 6], 2_000);
+    // There is no output at index 1 to pair the input with.
+    add_sighash_single_sig(&mut that, 1);
+
+    assert_eq!(
+        v2::join(this, that),
+        Err(JoinError::MissingSighashSingleOutput { input_index: 1, in_this: false })
+    );
+}
+
+#[test]
+fn join_sighash_single_without_output_in_this() {
+    let mut this = psbt(&[0, 1], 1_000);
+    // There is no output at index 1 to pair the input with.
+    add_sighash_single_sig(&mut this, 1);
+    let mut that = psbt(&[5], 2_000);
+    add_sighash_single_sig(&mut that, 0);
+
+    assert_eq!(
+        v2::join(this.clone(), that.clone()),
+        Err(JoinError::MissingSighashSingleOutput { input_index: 1, in_this: true })
+    );
+    // `join_all` joins into the first PSBT.
+    assert_eq!(
+        v2::join_all(vec![this, that]),
+        Err(JoinError::MissingSighashSingleOutput { input_index: 1, in_this: true })
+    );
+}
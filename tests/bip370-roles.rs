@@ -0,0 +1,100 @@
+//! Transitions between the BIP-370 roles.
+
+#![cfg(feature = "std")]
+
+use psbt_v2::bitcoin::hashes::Hash as _;
+use psbt_v2::bitcoin::{Amount, OutPoint, ScriptBuf, TxOut, Txid};
+use psbt_v2::v2::{
+    Constructor, ExtractError, InputBuilder, Modifiable, OutputBuilder, Psbt, SignerChecksError,
+};
+
+fn out_point() -> OutPoint { OutPoint { txid: Txid::all_zeros(), vout: 0 } }
+
+fn output() -> TxOut {
+    TxOut { value: Amount::from_sat(90_000), script_pubkey: ScriptBuf::new_op_return([0x01]) }
+}
+
+/// Creates an unsigned PSBT with a single input funded by `script_pubkey`.
+fn psbt(script_pubkey: ScriptBuf) -> Psbt {
+    let utxo = TxOut { value: Amount::from_sat(100_000), script_pubkey };
+    Constructor::<Modifiable>::default()
+        .input(InputBuilder::new(&out_point()).segwit_fund(utxo).build())
+        .output(OutputBuilder::new(output()).build())
+        .psbt()
+        .expect("valid lock time combination")
+}
+
+#[test]
+fn updater_to_signer_runs_signer_checks() {
+    let updater = psbt(ScriptBuf::new_p2wsh(&ScriptBuf::new().wscript_hash()))
+        .into_updater()
+        .expect("valid lock time combination");
+    assert!(updater.clone().signer().is_ok());
+
+    let mut psbt = updater.psbt();
+    psbt.inputs[0].witness_script = Some(ScriptBuf::new_op_return([0x01]));
+    let err = psbt.into_updater().expect("valid lock time combination").signer().unwrap_err();
+    assert_eq!(err, SignerChecksError::WitnessScriptMismatchWsh { input_index: 0 });
+}
+
+#[test]
+fn into_extractor_requires_finalized_psbt() {
+    let psbt = psbt(ScriptBuf::new_op_return([0x02]));
+    assert!(matches!(psbt.into_extractor(), Err(ExtractError::PsbtNotFinalized)));
+}
+
+#[cfg(feature = "miniscript")]
+mod miniscript {
+    use core::str::FromStr;
+
+    use psbt_v2::bitcoin::bip32::{Xpriv, Xpub};
+    use psbt_v2::bitcoin::secp256k1::Secp256k1;
+    use psbt_v2::bitcoin::Network;
+    use psbt_v2::miniscript::descriptor::{DefiniteDescriptorKey, Descriptor, DescriptorPublicKey};
+    use psbt_v2::v2::NewFinalizerError;
+
+    use super::*;
+
+    fn master() -> Xpriv { Xpriv::new_master(Network::Testnet, &[0x01; 32]).expect("valid seed") }
+
+    fn descriptor() -> Descriptor<DefiniteDescriptorKey> {
+        let xpub = Xpub::from_priv(&Secp256k1::new(), &master());
+        Descriptor::<DescriptorPublicKey>::from_str(&format!("wpkh({}/0/*)", xpub))
+            .expect("valid descriptor")
+            .at_derivation_index(0)
+            .expect("valid derivation index")
+    }
+
+    #[test]
+    fn all_roles_in_order() {
+        let secp = Secp256k1::new();
+        let desc = descriptor();
+
+        let signer = psbt(desc.script_pubkey())
+            .into_updater()
+            .expect("valid lock time combination")
+            .update_input_with_descriptor(0, &desc)
+            .expect("failed to update")
+            .signer()
+            .expect("passes signer checks");
+        let (signed, _) = signer.sign(&master(), &secp).expect("failed to sign");
+
+        let finalized = signed
+            .into_finalizer()
+            .expect("input is signed")
+            .finalize(&secp)
+            .expect("failed to finalize");
+        let tx = finalized.into_extractor().expect("finalized").extract_tx().expect("valid fee");
+        assert_eq!(tx.input.len(), 1);
+    }
+
+    #[test]
+    fn into_finalizer_requires_sig_data() {
+        let psbt = psbt(descriptor().script_pubkey());
+
+        match psbt.into_finalizer() {
+            Err(NewFinalizerError::MissingSigData { input_index: 0 }) => {}
+            res => panic!("unexpected result: {:?}", res),
+        }
+    }
+}
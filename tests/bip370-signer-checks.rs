@@ -0,0 +1,160 @@
+//! Checks run by the BIP-370 Signer role before signing.
+
+#![cfg(feature = "std")]
+
+use psbt_v2::bitcoin::hashes::Hash as _;
+use psbt_v2::bitcoin::key::{PublicKey, XOnlyPublicKey};
+use psbt_v2::bitcoin::secp256k1::{Secp256k1, SecretKey};
+use psbt_v2::bitcoin::taproot::TapNodeHash;
+use psbt_v2::bitcoin::{
+    absolute, transaction, Amount, OutPoint, ScriptBuf, Transaction, TxOut, Txid,
+};
+use psbt_v2::v2::{
+    Constructor, Input, InputBuilder, Modifiable, NewSignerError, OutputBuilder, Psbt, Signer,
+    SignerChecksError,
+};
+
+fn public_key() -> PublicKey {
+    let secp = Secp256k1::new();
+    let sk = SecretKey::from_slice(&[0x01; 32]).expect("valid secret key");
+    PublicKey::new(sk.public_key(&secp))
+}
+
+fn x_only() -> XOnlyPublicKey { public_key().inner.x_only_public_key().0 }
+
+fn utxo(script_pubkey: ScriptBuf) -> TxOut {
+    TxOut { value: Amount::from_sat(100_000), script_pubkey }
+}
+
+fn out_point(vout: u32) -> OutPoint { OutPoint { txid: Txid::all_zeros(), vout } }
+
+/// Creates an input spending a P2WPKH output, these always pass the signer checks.
+fn wpkh_input(vout: u32) -> Input {
+    let wpkh = public_key().wpubkey_hash().expect("compressed key");
+    InputBuilder::new(&out_point(vout)).segwit_fund(utxo(ScriptBuf::new_p2wpkh(&wpkh))).build()
+}
+
+/// Creates a PSBT with a valid input at index 0 followed by `input`.
+fn psbt(input: Input) -> Psbt {
+    let output = OutputBuilder::new(TxOut {
+        value: Amount::from_sat(90_000),
+        script_pubkey: ScriptBuf::new_op_return([0x01]),
+    })
+    .build();
+
+    Constructor::<Modifiable>::default()
+        .input(wpkh_input(7))
+        .input(input)
+        .output(output)
+        .psbt()
+        .expect("valid lock time combination")
+}
+
+fn signer_checks_err(psbt: Psbt) -> SignerChecksError {
+    match Signer::new(psbt) {
+        Err(NewSignerError::SignerChecks(e)) => e,
+        res => panic!("unexpected result: {:?}", res),
+    }
+}
+
+#[test]
+fn signer_checks_pass() {
+    let secp = Secp256k1::new();
+    let mut input = InputBuilder::new(&out_point(0))
+        .segwit_fund(utxo(ScriptBuf::new_p2tr(&secp, x_only(), None)))
+        .build();
+    input.tap_internal_key = Some(x_only());
+
+    assert!(Signer::new(psbt(input)).is_ok());
+}
+
+#[test]
+fn non_witness_utxo_txid_mismatch() {
+    let tx = Transaction {
+        version: transaction::Version::TWO,
+        lock_time: absolute::LockTime::ZERO,
+        input: vec![],
+        output: vec![utxo(ScriptBuf::new_p2pkh(&public_key().pubkey_hash()))],
+    };
+    let input = InputBuilder::new(&out_point(0)).legacy_fund(tx).build();
+
+    let err = signer_checks_err(psbt(input));
+    assert_eq!(err, SignerChecksError::NonWitnessUtxoTxidMismatch { input_index: 1 });
+}
+
+#[test]
+fn witness_utxo_bare() {
+    let spk = ScriptBuf::new_p2pkh(&public_key().pubkey_hash());
+    let input = InputBuilder::new(&out_point(0)).segwit_fund(utxo(spk)).build();
+
+    let err = signer_checks_err(psbt(input));
+    assert_eq!(err, SignerChecksError::NonWitnessSig { input_index: 1 });
+}
+
+#[test]
+fn redeem_script_mismatch() {
+    let wpkh = public_key().wpubkey_hash().expect("compressed key");
+    let redeem_script = ScriptBuf::new_p2wpkh(&wpkh);
+    let spk = ScriptBuf::new_p2sh(&ScriptBuf::new_op_return([0x01]).script_hash());
+
+    let mut input = InputBuilder::new(&out_point(0)).segwit_fund(utxo(spk)).build();
+    input.redeem_script = Some(redeem_script);
+
+    let err = signer_checks_err(psbt(input));
+    assert_eq!(err, SignerChecksError::RedeemScriptMismatch { input_index: 1 });
+}
+
+#[test]
+fn witness_script_mismatch() {
+    let witness_script = ScriptBuf::new_op_return([0x01]);
+    let spk = ScriptBuf::new_p2wsh(&ScriptBuf::new_op_return([0x02]).wscript_hash());
+
+    let mut input = InputBuilder::new(&out_point(0)).segwit_fund(utxo(spk)).build();
+    input.witness_script = Some(witness_script);
+
+    let err = signer_checks_err(psbt(input));
+    assert_eq!(err, SignerChecksError::WitnessScriptMismatchWsh { input_index: 1 });
+}
+
+#[test]
+fn witness_script_not_p2wsh() {
+    let witness_script = ScriptBuf::new_op_return([0x01]);
+
+    // A witness script can not be used to spend a P2WPKH output.
+    let mut input = wpkh_input(0);
+    input.witness_script = Some(witness_script.clone());
+    let err = signer_checks_err(psbt(input));
+    assert_eq!(err, SignerChecksError::WitnessScriptMismatchWsh { input_index: 1 });
+
+    // A P2SH output requires a P2WSH redeem script to be spent with a witness script.
+    let spk = ScriptBuf::new_p2sh(&ScriptBuf::new_op_return([0x02]).script_hash());
+    let mut input = InputBuilder::new(&out_point(0)).segwit_fund(utxo(spk)).build();
+    input.witness_script = Some(witness_script);
+    let err = signer_checks_err(psbt(input));
+    assert_eq!(err, SignerChecksError::WitnessScriptMismatchShWsh { input_index: 1 });
+}
+
+#[test]
+fn taproot_tweak_mismatch() {
+    let secp = Secp256k1::new();
+    let spk = ScriptBuf::new_p2tr(&secp, x_only(), None);
+
+    let mut input = InputBuilder::new(&out_point(0)).segwit_fund(utxo(spk)).build();
+    input.tap_internal_key = Some(x_only());
+    input.tap_merkle_root = Some(TapNodeHash::all_zeros());
+
+    let err = signer_checks_err(psbt(input));
+    assert_eq!(err, SignerChecksError::TaprootTweakMismatch { input_index: 1 });
+}
+
+#[test]
+fn taproot_missing_internal_key() {
+    let secp = Secp256k1::new();
+    let spk = ScriptBuf::new_p2tr(&secp, x_only(), None);
+
+    let mut input = InputBuilder::new(&out_point(0)).segwit_fund(utxo(spk)).build();
+    input.tap_merkle_root = Some(TapNodeHash::all_zeros());
+
+    let err = signer_checks_err(psbt(input));
+    assert_eq!(err, SignerChecksError::MissingTapInternalKey { input_index: 1 });
+}
@@ -0,0 +1,185 @@
+//! BIP-371 Taproot signing using the PSBT v2 `Signer`.
+
+#![cfg(feature = "std")]
+
+use core::str::FromStr;
+
+use psbt_v2::bitcoin::bip32::{DerivationPath, Xpriv};
+use psbt_v2::bitcoin::hashes::Hash as _;
+use psbt_v2::bitcoin::key::{TapTweak, XOnlyPublicKey};
+use psbt_v2::bitcoin::opcodes::all::OP_CHECKSIG;
+use psbt_v2::bitcoin::secp256k1::Secp256k1;
+use psbt_v2::bitcoin::sighash::SighashCache;
+use psbt_v2::bitcoin::taproot::{LeafVersion, TapLeafHash, TaprootBuilder};
+use psbt_v2::bitcoin::{script, Amount, Network, OutPoint, ScriptBuf, TxOut, Txid};
+use psbt_v2::v2::{Constructor, Input, InputBuilder, Modifiable, OutputBuilder, Psbt, Signer};
+
+const SEED: [u8; 32] = [0x01; 32];
+const PATH: &str = "m/86'/0'/0'/0/0";
+
+fn master() -> Xpriv { Xpriv::new_master(Network::Bitcoin, &SEED).expect("valid seed") }
+
+fn derive_x_only(xpriv: &Xpriv, path: &DerivationPath) -> XOnlyPublicKey {
+    let secp = Secp256k1::new();
+    let sk = xpriv.derive_priv(&secp, path).expect("valid derivation");
+    sk.to_keypair(&secp).x_only_public_key().0
+}
+
+/// Creates a PSBT with a single P2TR key path input funded by [`master`] derived at [`PATH`].
+fn key_path_psbt() -> (Psbt, XOnlyPublicKey) {
+    let secp = Secp256k1::new();
+    let master = master();
+    let path = DerivationPath::from_str(PATH).expect("valid path");
+    let internal_key = derive_x_only(&master, &path);
+
+    let utxo = TxOut {
+        value: Amount::from_sat(100_000),
+        script_pubkey: ScriptBuf::new_p2tr(&secp, internal_key, None),
+    };
+    let out_point = OutPoint { txid: Txid::all_zeros(), vout: 0 };
+    let mut input = InputBuilder::new(&out_point).segwit_fund(utxo).build();
+    input.tap_internal_key = Some(internal_key);
+    input.tap_key_origins.insert(internal_key, (vec![], (master.fingerprint(&secp), path)));
+
+    (single_input_psbt(input, internal_key), internal_key)
+}
+
+/// Creates a PSBT with a single P2TR input that has an unknown internal key and a single
+/// `<key> OP_CHECKSIG` leaf with key from [`master`] derived at [`PATH`].
+fn script_path_psbt() -> (Psbt, XOnlyPublicKey, TapLeafHash) {
+    let secp = Secp256k1::new();
+    let master = master();
+    let path = DerivationPath::from_str(PATH).expect("valid path");
+    let key = derive_x_only(&master, &path);
+
+    let other = Xpriv::new_master(Network::Bitcoin, &[0x02; 32]).expect("valid seed");
+    let internal_key = derive_x_only(&other, &path);
+
+    let leaf_script =
+        script::Builder::new().push_x_only_key(&key).push_opcode(OP_CHECKSIG).into_script();
+    let leaf_hash = TapLeafHash::from_script(&leaf_script, LeafVersion::TapScript);
+    let spend_info = TaprootBuilder::new()
+        .add_leaf(0, leaf_script.clone())
+        .expect("valid leaf")
+        .finalize(&secp, internal_key)
+        .expect("valid tree");
+    let control_block = spend_info
+        .control_block(&(leaf_script.clone(), LeafVersion::TapScript))
+        .expect("leaf is in tree");
+
+    let utxo = TxOut {
+        value: Amount::from_sat(100_000),
+        script_pubkey: ScriptBuf::new_p2tr(&secp, internal_key, spend_info.merkle_root()),
+    };
+    let out_point = OutPoint { txid: Txid::all_zeros(), vout: 0 };
+    let mut input = InputBuilder::new(&out_point).segwit_fund(utxo).build();
+    input.tap_internal_key = Some(internal_key);
+    input.tap_merkle_root = spend_info.merkle_root();
+    input.tap_scripts.insert(control_block, (leaf_script, LeafVersion::TapScript));
+    input.tap_key_origins.insert(key, (vec![leaf_hash], (master.fingerprint(&secp), path)));
+
+    (single_input_psbt(input, internal_key), key, leaf_hash)
+}
+
+fn single_input_psbt(input: Input, key: XOnlyPublicKey) -> Psbt {
+    let secp = Secp256k1::new();
+    let output = OutputBuilder::new(TxOut {
+        value: Amount::from_sat(90_000),
+        script_pubkey: ScriptBuf::new_p2tr(&secp, key, None),
+    })
+    .build();
+
+    Constructor::<Modifiable>::default()
+        .input(input)
+        .output(output)
+        .psbt()
+        .expect("valid lock time combination")
+}
+
+#[test]
+fn sign_taproot_key_path() {
+    let secp = Secp256k1::new();
+    let (psbt, internal_key) = key_path_psbt();
+
+    let signer = Signer::new(psbt).expect("valid lock time combination");
+    let tx = signer.unsigned_tx();
+    let (psbt, used) = signer.sign(&master(), &secp).expect("failed to sign");
+
+    assert_eq!(used.get(&0).map(|keys| keys.len()), Some(1));
+    // Signing with the default sighash type clears both modifiable flags.
+    assert_eq!(psbt.global.tx_modifiable_flags, 0);
+
+    let sig = psbt.inputs[0].tap_key_sig.expect("key path signature");
+    let (msg, _) = psbt
+        .sighash_taproot(0, &mut SighashCache::new(&tx), None)
+        .expect("failed to compute sighash");
+    let (output_key, _) = internal_key.tap_tweak(&secp, None);
+    secp.verify_schnorr(&sig.sig, &msg, &output_key.to_inner()).expect("valid signature");
+}
+
+#[test]
+fn sign_taproot_key_path_unknown_key() {
+    let secp = Secp256k1::new();
+    let (psbt, _) = key_path_psbt();
+
+    let other = Xpriv::new_master(Network::Bitcoin, &[0x02; 32]).expect("valid seed");
+    let signer = Signer::new(psbt).expect("valid lock time combination");
+    let (psbt, used) = signer.sign(&other, &secp).expect("failed to sign");
+
+    assert!(used.get(&0).map(|keys| keys.is_empty()).unwrap_or(true));
+    assert!(psbt.inputs[0].tap_key_sig.is_none());
+}
+
+#[test]
+fn sign_taproot_key_path_wrong_key_source() {
+    let secp = Secp256k1::new();
+    let (mut psbt, internal_key) = key_path_psbt();
+
+    // The key source derives a different key than the internal key.
+    let path = DerivationPath::from_str("m/86'/0'/0'/0/1").expect("valid path");
+    psbt.inputs[0]
+        .tap_key_origins
+        .insert(internal_key, (vec![], (master().fingerprint(&secp), path)));
+
+    let signer = Signer::new(psbt).expect("valid lock time combination");
+    let (psbt, used) = signer.sign(&master(), &secp).expect("failed to sign");
+
+    assert!(used.get(&0).map(|keys| keys.is_empty()).unwrap_or(true));
+    assert!(psbt.inputs[0].tap_key_sig.is_none());
+}
+
+#[test]
+fn sign_taproot_script_path() {
+    let secp = Secp256k1::new();
+    let (psbt, key, leaf_hash) = script_path_psbt();
+
+    let signer = Signer::new(psbt).expect("valid lock time combination");
+    let tx = signer.unsigned_tx();
+    let (psbt, used) = signer.sign(&master(), &secp).expect("failed to sign");
+
+    assert_eq!(used.get(&0).map(|keys| keys.len()), Some(1));
+    assert_eq!(psbt.global.tx_modifiable_flags, 0);
+    assert!(psbt.inputs[0].tap_key_sig.is_none());
+
+    let sig = psbt.inputs[0].tap_script_sigs.get(&(key, leaf_hash)).expect("script path signature");
+    let (msg, _) = psbt
+        .sighash_taproot(0, &mut SighashCache::new(&tx), Some(leaf_hash))
+        .expect("failed to compute sighash");
+    secp.verify_schnorr(&sig.sig, &msg, &key).expect("valid signature");
+}
+
+#[test]
+fn sign_taproot_key_without_leaves() {
+    let secp = Secp256k1::new();
+    let (mut psbt, key, _) = script_path_psbt();
+
+    // The key is neither the internal key nor used in any leaf so there is nothing to sign.
+    let origin = psbt.inputs[0].tap_key_origins.get(&key).expect("key origin").1.clone();
+    psbt.inputs[0].tap_key_origins.insert(key, (vec![], origin));
+
+    let signer = Signer::new(psbt).expect("valid lock time combination");
+    let (psbt, used) = signer.sign(&master(), &secp).expect("failed to sign");
+
+    assert!(used.get(&0).map(|keys| keys.is_empty()).unwrap_or(true));
+    assert!(psbt.inputs[0].tap_script_sigs.is_empty());
+}
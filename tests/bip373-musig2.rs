@@ -0,0 +1,218 @@
+//! BIP-373 MuSig2 fields and aggregating partial signatures.
+
+#![cfg(feature = "std")]
+
+use psbt_v2::bitcoin::hashes::{sha256, Hash as _, HashEngine};
+use psbt_v2::bitcoin::secp256k1::{PublicKey, Scalar, Secp256k1, SecretKey};
+use psbt_v2::bitcoin::sighash::SighashCache;
+use psbt_v2::bitcoin::taproot::TapTweakHash;
+use psbt_v2::bitcoin::{Amount, OutPoint, ScriptBuf, TxOut, Txid};
+use psbt_v2::musig2::{self, PartialSignature, PublicNonce};
+use psbt_v2::v2::{
+    self, Constructor, InputBuilder, Modifiable, Musig2AggregateError, OutputBuilder, Psbt,
+};
+
+fn tagged_hash(tag: &str, data: &[&[u8]]) -> Scalar {
+    let tag = sha256::Hash::hash(tag.as_bytes());
+    let mut engine = sha256::Hash::engine();
+    engine.input(tag.as_ref());
+    engine.input(tag.as_ref());
+    for bytes in data {
+        engine.input(bytes);
+    }
+    Scalar::from_be_bytes(sha256::Hash::from_engine(engine).to_byte_array()).expect("valid scalar")
+}
+
+fn has_even_y(pk: &PublicKey) -> bool { pk.serialize()[0] == 0x02 }
+
+fn secret_key(byte: u8) -> SecretKey { SecretKey::from_slice(&[byte; 32]).expect("valid key") }
+
+/// The participants' secret keys and secret nonces.
+fn participants() -> Vec<(SecretKey, (SecretKey, SecretKey))> {
+    vec![
+        (secret_key(0x01), (secret_key(0x11), secret_key(0x12))),
+        (secret_key(0x02), (secret_key(0x21), secret_key(0x22))),
+    ]
+}
+
+fn participant_pubkeys() -> Vec<PublicKey> {
+    let secp = Secp256k1::new();
+    participants().iter().map(|(sk, _)| sk.public_key(&secp)).collect()
+}
+
+fn pub_nonce(secnonce: &(SecretKey, SecretKey)) -> PublicNonce {
+    let secp = Secp256k1::new();
+    PublicNonce { r1: secnonce.0.public_key(&secp), r2: secnonce.1.public_key(&secp) }
+}
+
+/// Creates an unsigned PSBT spending an output locked to the aggregate key of the participants,
+/// with the participant pubkeys and public nonces already added.
+fn psbt() -> Psbt {
+    let secp = Secp256k1::new();
+    let pubkeys = participant_pubkeys();
+    let aggregate_key = musig2::aggregate_key(&secp, &pubkeys).expect("valid keys");
+    let internal_key = aggregate_key.x_only_public_key().0;
+
+    let utxo = TxOut {
+        value: Amount::from_sat(100_000),
+        script_pubkey: ScriptBuf::new_p2tr(&secp, internal_key, None),
+    };
+    let out_point = OutPoint { txid: Txid::from_byte_array([0x01; 32]), vout: 0 };
+    let output =
+        TxOut { value: Amount::from_sat(90_000), script_pubkey: ScriptBuf::new_op_return([0x01]) };
+    let mut psbt = Constructor::<Modifiable>::default()
+        .input(InputBuilder::new(&out_point).segwit_fund(utxo).build())
+        .output(OutputBuilder::new(output).build())
+        .psbt()
+        .expect("valid lock time combination");
+
+    let input = &mut psbt.inputs[0];
+    input.tap_internal_key = Some(internal_key);
+    input.musig2_participant_pubkeys.insert(aggregate_key, pubkeys.clone());
+    for (pk, (_, secnonce)) in pubkeys.iter().zip(participants().iter()) {
+        input.musig2_pub_nonces.insert((*pk, aggregate_key, None), pub_nonce(secnonce));
+    }
+    psbt
+}
+
+/// Creates the BIP-327 partial signature of participant `index` for a key path spend of input 0.
+fn partial_sign(psbt: &Psbt, index: usize) -> PartialSignature {
+    let secp = Secp256k1::new();
+    let pubkeys = participant_pubkeys();
+    let (sk, secnonce) = participants()[index];
+
+    // KeyAgg, the second key gets a coefficient of one.
+    let serialized = pubkeys.iter().map(|pk| pk.serialize()).collect::<Vec<_>>();
+    let list = tagged_hash("KeyAgg list", &serialized.iter().map(|pk| &pk[..]).collect::<Vec<_>>());
+    let a = if index == 1 {
+        Scalar::ONE
+    } else {
+        tagged_hash("KeyAgg coefficient", &[&list.to_be_bytes(), &serialized[index]])
+    };
+    let q = musig2::aggregate_key(&secp, &pubkeys).expect("valid keys");
+
+    // ApplyTweak with the Taproot tweak.
+    let tweak = TapTweakHash::from_key_and_tweak(q.x_only_public_key().0, None).to_scalar();
+    let q_even = if has_even_y(&q) { q } else { q.negate(&secp) };
+    let output_key = q_even.add_exp_tweak(&secp, &tweak).expect("valid tweak");
+    let output_key_bytes = output_key.x_only_public_key().0.serialize();
+
+    let tx = psbt.clone().into_signer().expect("valid PSBT").unsigned_tx();
+    let (msg, _) =
+        psbt.sighash_taproot(0, &mut SighashCache::new(&tx), None).expect("valid sighash");
+    let msg: &[u8; 32] = msg.as_ref();
+
+    // Sign.
+    let nonces = participants().iter().map(|(_, secnonce)| pub_nonce(secnonce)).collect::<Vec<_>>();
+    let r1 = PublicKey::combine_keys(&nonces.iter().map(|n| &n.r1).collect::<Vec<_>>()).unwrap();
+    let r2 = PublicKey::combine_keys(&nonces.iter().map(|n| &n.r2).collect::<Vec<_>>()).unwrap();
+    let aggnonce = PublicNonce { r1, r2 }.serialize();
+    let b = tagged_hash("MuSig/noncecoef", &[&aggnonce, &output_key_bytes, msg]);
+    let r = r1.combine(&r2.mul_tweak(&secp, &b).unwrap()).unwrap();
+    let e = tagged_hash(
+        "BIP0340/challenge",
+        &[&r.x_only_public_key().0.serialize(), &output_key_bytes, msg],
+    );
+
+    let mut k = secnonce.0.add_tweak(&Scalar::from(secnonce.1.mul_tweak(&b).unwrap())).unwrap();
+    if !has_even_y(&r) {
+        k = k.negate();
+    }
+    let d = if has_even_y(&q) == has_even_y(&output_key) { sk } else { sk.negate() };
+    let ead = SecretKey::from_slice(&e.to_be_bytes()).unwrap().mul_tweak(&a).unwrap();
+    let ead = ead.mul_tweak(&Scalar::from(d)).unwrap();
+    let s = k.add_tweak(&Scalar::from(ead)).unwrap();
+
+    PartialSignature::from_byte_array(s.secret_bytes())
+}
+
+/// Adds the partial signatures of the participants at `indices` to input 0.
+fn add_partial_sigs(mut psbt: Psbt, indices: &[usize]) -> Psbt {
+    let secp = Secp256k1::new();
+    let pubkeys = participant_pubkeys();
+    let aggregate_key = musig2::aggregate_key(&secp, &pubkeys).expect("valid keys");
+    for index in indices {
+        let partial_sig = partial_sign(&psbt, *index);
+        psbt.inputs[0]
+            .musig2_partial_sigs
+            .insert((pubkeys[*index], aggregate_key, None), partial_sig);
+    }
+    psbt
+}
+
+#[test]
+fn serialize_roundtrip() {
+    let psbt = add_partial_sigs(psbt(), &[0, 1]);
+
+    let decoded = Psbt::deserialize(&psbt.serialize()).expect("valid PSBT");
+    assert_eq!(decoded, psbt);
+
+    // A v0 PSBT carries the MuSig2 fields as unknowns.
+    let v0 = psbt.clone().into_v0().expect("valid lock time");
+    assert_eq!(v0.inputs[0].unknown.len(), 5);
+    let flags = psbt.global.tx_modifiable_flags;
+    let from_v0 = Psbt::from_v0(v0, flags).expect("valid v0 PSBT");
+    let (input, want) = (&from_v0.inputs[0], &psbt.inputs[0]);
+    assert_eq!(input.musig2_participant_pubkeys, want.musig2_participant_pubkeys);
+    assert_eq!(input.musig2_pub_nonces, want.musig2_pub_nonces);
+    assert_eq!(input.musig2_partial_sigs, want.musig2_partial_sigs);
+    assert!(input.unknowns.is_empty());
+}
+
+#[test]
+fn combine_partial_sigs() {
+    let this = add_partial_sigs(psbt(), &[0]);
+    let that = add_partial_sigs(psbt(), &[1]);
+
+    let combined = v2::combine(this, that).expect("same transaction");
+    assert_eq!(combined, add_partial_sigs(psbt(), &[0, 1]));
+}
+
+#[test]
+fn aggregate_partial_sigs() {
+    let secp = Secp256k1::new();
+    let mut psbt = add_partial_sigs(psbt(), &[0, 1]);
+
+    psbt.aggregate_musig2_partial_sigs(0, &secp).expect("valid partial signatures");
+    assert!(psbt.inputs[0].tap_key_sig.is_some());
+}
+
+#[test]
+fn aggregate_missing_partial_sig() {
+    let secp = Secp256k1::new();
+    let mut psbt = add_partial_sigs(psbt(), &[0]);
+
+    let err = psbt.aggregate_musig2_partial_sigs(0, &secp).expect_err("missing partial signature");
+    let participant = participant_pubkeys()[1];
+    assert_eq!(err, Musig2AggregateError::MissingPartialSig { participant });
+}
+
+#[test]
+fn aggregate_invalid_partial_sig() {
+    let secp = Secp256k1::new();
+    let mut psbt = add_partial_sigs(psbt(), &[0, 1]);
+    // Use the first participant's partial signature for the second participant.
+    let sigs = &mut psbt.inputs[0].musig2_partial_sigs;
+    let first = *sigs.values().next().expect("two partial signatures");
+    sigs.values_mut().for_each(|sig| *sig = first);
+
+    let err = psbt.aggregate_musig2_partial_sigs(0, &secp).expect_err("invalid partial signature");
+    assert_eq!(err, Musig2AggregateError::InvalidSignature);
+}
+
+#[cfg(feature = "miniscript")]
+mod miniscript {
+    use psbt_v2::v2::Finalizer;
+
+    use super::*;
+
+    #[test]
+    fn finalizer_aggregates_partial_sigs() {
+        let secp = Secp256k1::new();
+        let finalizer = Finalizer::new(add_partial_sigs(psbt(), &[0, 1])).expect("valid PSBT");
+
+        let finalized = finalizer.finalize(&secp).expect("failed to finalize");
+        assert!(finalized.is_finalized());
+        assert_eq!(finalized.inputs[0].final_script_witness.as_ref().map(|w| w.len()), Some(1));
+    }
+}
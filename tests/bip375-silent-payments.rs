@@ -0,0 +1,309 @@
+//! BIP-375 silent payment fields and computing the silent payment output scripts.
+
+#![cfg(feature = "std")]
+
+use std::collections::BTreeMap;
+
+use psbt_v2::bitcoin::bip32::{DerivationPath, Fingerprint};
+use psbt_v2::bitcoin::hashes::{sha256, Hash as _, HashEngine};
+use psbt_v2::bitcoin::key::{Keypair, TapTweak};
+use psbt_v2::bitcoin::secp256k1::{Parity, PublicKey, Scalar, Secp256k1, SecretKey};
+use psbt_v2::bitcoin::{consensus, Amount, Network, OutPoint, PrivateKey, ScriptBuf, TxOut, Txid};
+use psbt_v2::silent_payments::{DleqProof, SilentPaymentInfo};
+use psbt_v2::v2::{
+    self, Constructor, EndConstructionError, InputBuilder, Modifiable, NewSignerError,
+    OutputBuilder, Psbt, Signer, SignerChecksError, SilentPaymentError,
+};
+
+fn tagged_hash(tag: &str, data: &[&[u8]]) -> Scalar {
+    let tag = sha256::Hash::hash(tag.as_bytes());
+    let mut engine = sha256::Hash::engine();
+    engine.input(tag.as_ref());
+    engine.input(tag.as_ref());
+    for bytes in data {
+        engine.input(bytes);
+    }
+    Scalar::from_be_bytes(sha256::Hash::from_engine(engine).to_byte_array()).expect("valid scalar")
+}
+
+fn secret_key(byte: u8) -> SecretKey { SecretKey::from_slice(&[byte; 32]).expect("valid key") }
+
+fn out_point(byte: u8) -> OutPoint { OutPoint { txid: Txid::from_byte_array([byte; 32]), vout: 0 } }
+
+/// The receiver's scan and spend secret keys.
+fn receiver() -> (SecretKey, SecretKey) { (secret_key(0x0a), secret_key(0x0b)) }
+
+fn info() -> SilentPaymentInfo {
+    let secp = Secp256k1::new();
+    let (scan, spend) = receiver();
+    SilentPaymentInfo { scan_key: scan.public_key(&secp), spend_key: spend.public_key(&secp) }
+}
+
+/// The secret keys of the inputs as used for silent payments, input 0 is P2WPKH and input 1 is
+/// P2TR (the tweaked key, negated if its public key has an odd y-coordinate).
+fn input_secret_keys() -> Vec<SecretKey> {
+    let secp = Secp256k1::new();
+    let keypair = Keypair::from_secret_key(&secp, &secret_key(0x02));
+    let tweaked = keypair.tap_tweak(&secp, None).to_inner();
+    let taproot = match tweaked.x_only_public_key().1 {
+        Parity::Even => tweaked.secret_key(),
+        Parity::Odd => tweaked.secret_key().negate(),
+    };
+    vec![secret_key(0x01), taproot]
+}
+
+/// Creates a PSBT with a P2WPKH input and a P2TR input paying to two silent payment outputs of
+/// the same receiver, the output scripts have not been computed.
+fn psbt() -> Psbt {
+    let secp = Secp256k1::new();
+
+    let pk = psbt_v2::bitcoin::PublicKey::new(secret_key(0x01).public_key(&secp));
+    let p2wpkh = TxOut {
+        value: Amount::from_sat(50_000),
+        script_pubkey: ScriptBuf::new_p2wpkh(&pk.wpubkey_hash().expect("compressed key")),
+    };
+    let internal_key = secret_key(0x02).x_only_public_key(&secp).0;
+    let p2tr = TxOut {
+        value: Amount::from_sat(50_000),
+        script_pubkey: ScriptBuf::new_p2tr(&secp, internal_key, None),
+    };
+
+    let mut psbt = Constructor::<Modifiable>::default()
+        .input(InputBuilder::new(&out_point(0x02)).segwit_fund(p2wpkh).build())
+        .input(InputBuilder::new(&out_point(0x01)).segwit_fund(p2tr).build())
+        .output(OutputBuilder::silent_payment(Amount::from_sat(40_000), info()).build())
+        .output(OutputBuilder::silent_payment(Amount::from_sat(40_000), info()).build())
+        .no_more_inputs()
+        .psbt()
+        .expect("valid lock time combination");
+
+    psbt.inputs[0]
+        .bip32_derivations
+        .insert(pk.inner, (Fingerprint::default(), DerivationPath::master()));
+    psbt.inputs[1].tap_internal_key = Some(internal_key);
+    psbt
+}
+
+/// Creates a BIP-374 DLEQ proof for the ECDH share of secret key `a` and the receiver's scan key.
+fn dleq_proof(a: SecretKey) -> DleqProof {
+    let secp = Secp256k1::new();
+    let scan_key = info().scan_key;
+    let share = scan_key.mul_tweak(&secp, &Scalar::from(a)).unwrap();
+    let generator = SecretKey::from_slice(&Scalar::ONE.to_be_bytes()).unwrap().public_key(&secp);
+
+    // A fixed nonce is fine for testing.
+    let k = SecretKey::from_slice(&[0x42; 32]).unwrap();
+    let r1 = k.public_key(&secp);
+    let r2 = scan_key.mul_tweak(&secp, &Scalar::from(k)).unwrap();
+    let points = [a.public_key(&secp), scan_key, share, generator, r1, r2];
+    let points = points.iter().map(|point| point.serialize()).collect::<Vec<_>>();
+    let e = tagged_hash("BIP0374/challenge", &points.iter().map(|p| &p[..]).collect::<Vec<_>>());
+    let s = k.add_tweak(&Scalar::from(a.mul_tweak(&e).unwrap())).unwrap();
+
+    DleqProof { e: e.to_be_bytes(), s: s.secret_bytes() }
+}
+
+/// Adds the ECDH share of the input at `index` for the receiver's scan key.
+fn add_share(mut psbt: Psbt, index: usize) -> Psbt {
+    let secp = Secp256k1::new();
+    let scan_key = info().scan_key;
+    let a = input_secret_keys()[index];
+    let share = scan_key.mul_tweak(&secp, &Scalar::from(a)).unwrap();
+    psbt.inputs[index].sp_ecdh_shares.insert(scan_key, share);
+    psbt.inputs[index].sp_dleq_proofs.insert(scan_key, dleq_proof(a));
+    psbt
+}
+
+/// Computes the script of the `k`-th output as the receiver would when scanning.
+fn receiver_script(k: u32) -> ScriptBuf {
+    let secp = Secp256k1::new();
+    let (scan, spend) = receiver();
+
+    let keys = input_secret_keys().iter().map(|sk| sk.public_key(&secp)).collect::<Vec<_>>();
+    let input_keys = PublicKey::combine_keys(&keys.iter().collect::<Vec<_>>()).unwrap();
+    let smallest = consensus::serialize(&out_point(0x01));
+    let input_hash = tagged_hash("BIP0352/Inputs", &[&smallest, &input_keys.serialize()]);
+
+    let shared_secret = input_keys
+        .mul_tweak(&secp, &input_hash)
+        .unwrap()
+        .mul_tweak(&secp, &Scalar::from(scan))
+        .unwrap();
+    let tweak =
+        tagged_hash("BIP0352/SharedSecret", &[&shared_secret.serialize(), &k.to_be_bytes()]);
+    let output_key = spend.public_key(&secp).add_exp_tweak(&secp, &tweak).unwrap();
+
+    ScriptBuf::new_p2tr_tweaked(output_key.x_only_public_key().0.dangerous_assume_tweaked())
+}
+
+#[test]
+fn serialize_roundtrip() {
+    let mut psbt = add_share(add_share(psbt(), 0), 1);
+    psbt.outputs[1].sp_v0_label = Some(1);
+    assert!(psbt.outputs[0].is_silent_payment_pending());
+
+    let decoded = Psbt::deserialize(&psbt.serialize()).expect("valid PSBT");
+    assert_eq!(decoded, psbt);
+
+    // A v0 PSBT carries the silent payment fields as unknowns.
+    let v0 = psbt.clone().into_v0().expect("valid lock time");
+    let flags = psbt.global.tx_modifiable_flags;
+    let from_v0 = Psbt::from_v0(v0, flags).expect("valid v0 PSBT");
+    for (input, want) in from_v0.inputs.iter().zip(psbt.inputs.iter()) {
+        assert_eq!(input.sp_ecdh_shares, want.sp_ecdh_shares);
+        assert_eq!(input.sp_dleq_proofs, want.sp_dleq_proofs);
+        assert!(input.unknowns.is_empty());
+    }
+    for (output, want) in from_v0.outputs.iter().zip(psbt.outputs.iter()) {
+        assert_eq!(output.sp_v0_info, want.sp_v0_info);
+        assert_eq!(output.sp_v0_label, want.sp_v0_label);
+        assert!(output.unknowns.is_empty());
+    }
+}
+
+#[test]
+fn combine_shares() {
+    let this = add_share(psbt(), 0);
+    let that = add_share(psbt(), 1);
+
+    let combined = v2::combine(this, that).expect("same transaction");
+    assert_eq!(combined, add_share(add_share(psbt(), 0), 1));
+}
+
+#[test]
+fn compute_scripts() {
+    let secp = Secp256k1::new();
+    let updater = add_share(add_share(psbt(), 0), 1).into_updater().expect("valid lock time");
+
+    let psbt = updater.compute_silent_payment_scripts(&secp).expect("all shares present").psbt();
+    assert_eq!(psbt.outputs[0].script_pubkey, receiver_script(0));
+    assert_eq!(psbt.outputs[1].script_pubkey, receiver_script(1));
+    assert!(!psbt.global.is_inputs_modifiable());
+    assert!(!psbt.global.is_outputs_modifiable());
+}
+
+#[test]
+fn compute_scripts_global_share() {
+    let secp = Secp256k1::new();
+    let mut psbt = psbt();
+    let scan_key = info().scan_key;
+    let secret_keys = input_secret_keys();
+    let a = secret_keys[0].add_tweak(&Scalar::from(secret_keys[1])).unwrap();
+    let share = scan_key.mul_tweak(&secp, &Scalar::from(a)).unwrap();
+    psbt.global.sp_ecdh_shares.insert(scan_key, share);
+    psbt.global.sp_dleq_proofs.insert(scan_key, dleq_proof(a));
+
+    let updater = psbt.into_updater().expect("valid lock time");
+    let psbt = updater.compute_silent_payment_scripts(&secp).expect("global share present").psbt();
+    assert_eq!(psbt.outputs[0].script_pubkey, receiver_script(0));
+    assert_eq!(psbt.outputs[1].script_pubkey, receiver_script(1));
+}
+
+#[test]
+fn compute_scripts_missing_share() {
+    let secp = Secp256k1::new();
+    let updater = add_share(psbt(), 0).into_updater().expect("valid lock time");
+
+    let err = updater.compute_silent_payment_scripts(&secp).expect_err("missing share");
+    assert_eq!(
+        err,
+        SilentPaymentError::MissingEcdhShare { input_index: 1, scan_key: info().scan_key }
+    );
+}
+
+#[test]
+fn compute_scripts_tampered_share() {
+    let secp = Secp256k1::new();
+    let scan_key = info().scan_key;
+
+    // A share for a key other than the input's, still carrying the proof of the honest share.
+    let mut tampered = add_share(add_share(psbt(), 0), 1);
+    let share = scan_key.mul_tweak(&secp, &Scalar::from(secret_key(0x03))).unwrap();
+    tampered.inputs[1].sp_ecdh_shares.insert(scan_key, share);
+    let updater = tampered.into_updater().expect("valid lock time");
+    assert_eq!(
+        updater.compute_silent_payment_scripts(&secp).expect_err("tampered share"),
+        SilentPaymentError::InvalidDleqProof { input_index: Some(1), scan_key }
+    );
+
+    // The tampered share with a valid proof for the wrong key.
+    let mut tampered = add_share(add_share(psbt(), 0), 1);
+    tampered.inputs[1].sp_ecdh_shares.insert(scan_key, share);
+    tampered.inputs[1].sp_dleq_proofs.insert(scan_key, dleq_proof(secret_key(0x03)));
+    let updater = tampered.into_updater().expect("valid lock time");
+    assert_eq!(
+        updater.compute_silent_payment_scripts(&secp).expect_err("tampered share"),
+        SilentPaymentError::InvalidDleqProof { input_index: Some(1), scan_key }
+    );
+
+    let mut missing = add_share(add_share(psbt(), 0), 1);
+    missing.inputs[0].sp_dleq_proofs.clear();
+    let updater = missing.into_updater().expect("valid lock time");
+    assert_eq!(
+        updater.compute_silent_payment_scripts(&secp).expect_err("missing proof"),
+        SilentPaymentError::MissingDleqProof { input_index: Some(0), scan_key }
+    );
+}
+
+#[test]
+fn compute_scripts_tampered_global_share() {
+    let secp = Secp256k1::new();
+    let scan_key = info().scan_key;
+    let mut psbt = psbt();
+    let share = scan_key.mul_tweak(&secp, &Scalar::from(secret_key(0x03))).unwrap();
+    psbt.global.sp_ecdh_shares.insert(scan_key, share);
+
+    let updater = psbt.clone().into_updater().expect("valid lock time");
+    assert_eq!(
+        updater.compute_silent_payment_scripts(&secp).expect_err("missing proof"),
+        SilentPaymentError::MissingDleqProof { input_index: None, scan_key }
+    );
+
+    psbt.global.sp_dleq_proofs.insert(scan_key, dleq_proof(secret_key(0x03)));
+    let updater = psbt.into_updater().expect("valid lock time");
+    assert_eq!(
+        updater.compute_silent_payment_scripts(&secp).expect_err("tampered share"),
+        SilentPaymentError::InvalidDleqProof { input_index: None, scan_key }
+    );
+}
+
+#[test]
+fn no_more_outputs_while_pending() {
+    let constructor = || {
+        Constructor::<Modifiable>::default()
+            .input(InputBuilder::new(&out_point(0x01)).build())
+            .output(OutputBuilder::silent_payment(Amount::from_sat(40_000), info()).build())
+    };
+
+    let err = constructor().no_more_outputs().err().expect("pending silent payment output");
+    assert_eq!(err.output_index, 0);
+
+    match constructor().updater().expect_err("pending silent payment output") {
+        EndConstructionError::SilentPaymentPending(e) => assert_eq!(e.output_index, 0),
+        e => panic!("unexpected error: {:?}", e),
+    }
+}
+
+#[test]
+fn sign_after_computing_scripts() {
+    let secp = Secp256k1::new();
+    let psbt = add_share(add_share(psbt(), 0), 1);
+    let sk = PrivateKey::new(secret_key(0x01), Network::Regtest);
+    let keys = BTreeMap::from([(sk.public_key(&secp), sk)]);
+
+    let err = Signer::new(psbt.clone()).expect_err("pending silent payment outputs");
+    match err {
+        NewSignerError::SignerChecks(SignerChecksError::SilentPaymentPending(e)) =>
+            assert_eq!(e.output_index, 0),
+        e => panic!("unexpected error: {:?}", e),
+    }
+
+    let updater = psbt.into_updater().expect("valid lock time");
+    let signer = updater
+        .compute_silent_payment_scripts(&secp)
+        .expect("all shares present")
+        .signer()
+        .expect("scripts computed");
+    let (psbt, _) = signer.sign(&keys, &secp).expect("failed to sign");
+    assert_eq!(psbt.inputs[0].partial_sigs.len(), 1);
+}
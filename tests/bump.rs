@@ -0,0 +1,174 @@
+//! Replace-by-fee bumping of a signed PSBT v2.
+
+#![cfg(all(feature = "std", feature = "miniscript"))]
+
+mod fixtures;
+
+use fixtures::{
+    descriptor, extract, finalized_payment, master, sign_and_finalize, CHANGE, FUNDING, PAYMENT,
+};
+use psbt_v2::bitcoin::hashes::Hash as _;
+use psbt_v2::bitcoin::{Amount, FeeRate, OutPoint, ScriptBuf, Transaction, TxOut, Txid};
+use psbt_v2::v2::{
+    BumpFeeError, Constructor, FeeBumper, InputBuilder, Modifiable, NewFeeBumperError, Psbt,
+};
+use psbt_v2::PayFeeError;
+
+fn funded_input(vout: u32) -> psbt_v2::v2::Input {
+    let out_point = OutPoint { txid: Txid::from_byte_array([0x01; 32]), vout };
+    let utxo = TxOut {
+        value: Amount::from_sat(FUNDING),
+        script_pubkey: descriptor(&master(), vout).script_pubkey(),
+    };
+    let mut psbt = Constructor::<Modifiable>::default()
+        .input(InputBuilder::new(&out_point).segwit_fund(utxo).build())
+        .updater()
+        .expect("valid lock time combination")
+        .update_input_with_descriptor(0, &descriptor(&master(), vout))
+        .expect("failed to update")
+        .psbt();
+    psbt.inputs.remove(0)
+}
+
+/// Creates a finalized PSBT paying [`PAYMENT`] with a change output of [`CHANGE`].
+fn finalized_psbt() -> Psbt { finalized_payment(ScriptBuf::new_op_return([0x01])) }
+
+fn fee_rate(psbt: &Psbt, tx: &Transaction) -> FeeRate {
+    FeeRate::from_sat_per_kwu(psbt.fee().expect("valid fee").to_sat() * 1000 / tx.weight().to_wu())
+}
+
+#[test]
+fn bump_reduces_change() {
+    let mut original = finalized_psbt();
+    // The original was signed with SIGHASH_SINGLE, the replacement has no signatures.
+    original.global.set_sighash_single_flag();
+    let target = FeeRate::from_sat_per_vb_unchecked(10);
+
+    let bumper = original.clone().into_fee_bumper().expect("finalized");
+    assert_eq!(bumper.original_fee(), Amount::from_sat(FUNDING - PAYMENT - CHANGE));
+
+    let replacement = bumper.change_output(1).bump(target).expect("enough change").psbt();
+    assert!(!replacement.inputs[0].has_sig_data());
+    assert!(!replacement.inputs[0].is_finalized());
+    assert!(replacement.inputs[0].sequence.expect("sequence set").is_rbf());
+    assert_eq!(replacement.outputs[0].amount, Amount::from_sat(PAYMENT));
+    assert!(replacement.outputs[1].amount < Amount::from_sat(CHANGE));
+    assert!(replacement.global.is_inputs_modifiable());
+    assert!(replacement.global.is_outputs_modifiable());
+    assert!(!replacement.global.has_sighash_single());
+
+    let finalized = sign_and_finalize(replacement, &master(), &[descriptor(&master(), 0)]);
+    let tx = extract(finalized.clone());
+    assert!(fee_rate(&finalized, &tx) >= target);
+    assert!(finalized.fee().unwrap() > original.fee().unwrap());
+}
+
+#[test]
+fn bump_from_tx() {
+    let original = finalized_psbt();
+    let tx = extract(original.clone());
+
+    // The signer's copy of the PSBT is not finalized.
+    let mut unfinalized = original.clone();
+    unfinalized.inputs[0].final_script_witness = None;
+    unfinalized.inputs[0].final_script_sig = None;
+    assert!(matches!(FeeBumper::new(unfinalized.clone()), Err(NewFeeBumperError::NotFinalized)));
+
+    let target = FeeRate::from_sat_per_vb_unchecked(10);
+    let from_tx = FeeBumper::from_tx(&tx, unfinalized).expect("matching transaction");
+    let from_psbt = FeeBumper::new(original.clone()).expect("finalized");
+    assert_eq!(
+        from_tx.change_output(1).bump(target).unwrap().psbt(),
+        from_psbt.change_output(1).bump(target).unwrap().psbt()
+    );
+
+    let mut other = tx.clone();
+    other.output[0].value = Amount::from_sat(PAYMENT - 1);
+    assert!(matches!(
+        FeeBumper::from_tx(&other, original),
+        Err(NewFeeBumperError::TransactionMismatch)
+    ));
+}
+
+#[test]
+fn bump_fee_rate_too_low() {
+    let bumper = finalized_psbt().into_fee_bumper().expect("finalized");
+    let original = bumper.original_fee_rate();
+
+    match bumper.change_output(1).bump(original) {
+        Err(BumpFeeError::FeeRateTooLow { .. }) => {}
+        res => panic!("unexpected result: {:?}", res),
+    }
+}
+
+#[test]
+fn bump_insufficient_change() {
+    let bumper = finalized_psbt().into_fee_bumper().expect("finalized");
+
+    match bumper.clone().bump(FeeRate::from_sat_per_vb_unchecked(10)) {
+        Err(BumpFeeError::PayFee(PayFeeError::InsufficientFunds { .. })) => {}
+        res => panic!("unexpected result: {:?}", res),
+    }
+    // Leaves less than the dust limit of the change output.
+    let weight = extract(finalized_psbt()).weight().to_wu();
+    let fee_rate =
+        FeeRate::from_sat_per_kwu(((FUNDING - PAYMENT - 100) * 1000 + weight - 1) / weight);
+    match bumper.change_output(1).bump(fee_rate) {
+        Err(BumpFeeError::PayFee(PayFeeError::ChangeBelowDust { .. })) => {}
+        res => panic!("unexpected result: {:?}", res),
+    }
+}
+
+#[test]
+fn bump_add_input() {
+    let target = FeeRate::from_sat_per_vb_unchecked(10);
+    let input = funded_input(1);
+    let satisfaction_weight = input.max_weight_to_satisfy().expect("wpkh input");
+
+    let original = finalized_psbt();
+    assert!(!original.global.is_inputs_modifiable());
+    let replacement = original
+        .into_fee_bumper()
+        .expect("finalized")
+        .input(input, satisfaction_weight)
+        .change_output(1)
+        .bump(target)
+        .expect("enough funds")
+        .psbt();
+    assert_eq!(replacement.inputs.len(), 2);
+    assert_eq!(replacement.global.input_count, 2);
+    // The added input pays the additional fee, the rest goes to change.
+    assert!(replacement.outputs[1].amount > Amount::from_sat(CHANGE));
+
+    let descriptors = [descriptor(&master(), 0), descriptor(&master(), 1)];
+    let finalized = sign_and_finalize(replacement, &master(), &descriptors);
+    let tx = extract(finalized.clone());
+    assert!(fee_rate(&finalized, &tx) >= target);
+    assert!(tx.input.iter().all(|input| input.sequence.is_rbf()));
+}
+
+#[test]
+fn bump_duplicate_input() {
+    let target = FeeRate::from_sat_per_vb_unchecked(10);
+    let bumper = finalized_psbt().into_fee_bumper().expect("finalized").change_output(1);
+
+    // Spends the same outpoint as the original input.
+    let input = funded_input(0);
+    let satisfaction_weight = input.max_weight_to_satisfy().expect("wpkh input");
+    match bumper.clone().input(input, satisfaction_weight).bump(target) {
+        Err(BumpFeeError::DuplicateInput { out_point }) => assert_eq!(out_point.vout, 0),
+        res => panic!("unexpected result: {:?}", res),
+    }
+
+    // Spends the same outpoint as an earlier added input.
+    let input = funded_input(1);
+    let satisfaction_weight = input.max_weight_to_satisfy().expect("wpkh input");
+    match bumper
+        .input(input.clone(), satisfaction_weight)
+        .input(input, satisfaction_weight)
+        .bump(target)
+    {
+        Err(BumpFeeError::DuplicateInput { out_point }) => assert_eq!(out_point.vout, 1),
+        res => panic!("unexpected result: {:?}", res),
+    }
+}
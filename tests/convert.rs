@@ -0,0 +1,165 @@
+//! Conversion between PSBT v0 and PSBT v2.
+
+#![cfg(feature = "std")]
+
+use psbt_v2::bitcoin::hashes::Hash as _;
+use psbt_v2::bitcoin::hex::FromHex;
+use psbt_v2::bitcoin::locktime::absolute;
+use psbt_v2::bitcoin::{Amount, EcdsaSighashType, OutPoint, ScriptBuf, Sequence, TxOut, Txid};
+use psbt_v2::raw::ProprietaryKey;
+use psbt_v2::v0;
+use psbt_v2::v2::{Constructor, InputBuilder, Modifiable, OutputBuilder, Psbt};
+
+fn out_point(vout: u32) -> OutPoint { OutPoint { txid: Txid::all_zeros(), vout } }
+
+fn utxo(sats: u64) -> TxOut {
+    TxOut { value: Amount::from_sat(sats), script_pubkey: ScriptBuf::new_op_return([0x01]) }
+}
+
+fn proprietary_key() -> ProprietaryKey {
+    ProprietaryKey { prefix: b"test".to_vec(), subtype: 0x01, key: vec![0xab] }
+}
+
+/// Creates a v2 PSBT with two inputs (one with a required height lock time) and one output.
+fn v2_psbt() -> Psbt {
+    let height = absolute::Height::from_consensus(800_000).expect("valid height");
+    let mut a = InputBuilder::new(&out_point(0))
+        .minimum_required_height_based_lock_time(height)
+        .segwit_fund(utxo(50_000))
+        .build();
+    a.sequence = Some(Sequence::ENABLE_RBF_NO_LOCKTIME);
+    a.proprietaries.insert(proprietary_key(), vec![0x01, 0x02]);
+
+    let b = InputBuilder::new(&out_point(1)).segwit_fund(utxo(60_000)).build();
+
+    let mut output = OutputBuilder::new(utxo(100_000)).build();
+    output.proprietaries.insert(proprietary_key(), vec![0x03]);
+
+    let mut psbt = Constructor::<Modifiable>::default()
+        .input(a)
+        .input(b)
+        .output(output)
+        .psbt()
+        .expect("valid lock time combination");
+    psbt.global.proprietaries.insert(proprietary_key(), vec![0x04]);
+    psbt
+}
+
+#[test]
+fn v2_into_v0() {
+    let psbt = v2_psbt();
+    let v0 = psbt.clone().into_v0().expect("valid lock time combination");
+
+    let tx = &v0.unsigned_tx;
+    assert_eq!(tx.version, psbt.global.tx_version);
+    assert_eq!(tx.lock_time, absolute::LockTime::from_consensus(800_000));
+    assert_eq!(tx.input.len(), 2);
+    assert_eq!(tx.input[0].previous_output, out_point(0));
+    assert_eq!(tx.input[0].sequence, Sequence::ENABLE_RBF_NO_LOCKTIME);
+    assert_eq!(tx.input[1].previous_output, out_point(1));
+    assert_eq!(tx.output, vec![utxo(100_000)]);
+
+    assert_eq!(v0.version, 0);
+    assert_eq!(v0.inputs[0].witness_utxo, Some(utxo(50_000)));
+    assert_eq!(v0.inputs[1].witness_utxo, Some(utxo(60_000)));
+
+    let key = v0::bitcoin::raw::ProprietaryKey::from(proprietary_key());
+    assert_eq!(v0.proprietary.get(&key), Some(&vec![0x04]));
+    assert_eq!(v0.inputs[0].proprietary.get(&key), Some(&vec![0x01, 0x02]));
+    assert_eq!(v0.outputs[0].proprietary.get(&key), Some(&vec![0x03]));
+
+    // The converted PSBT must be a valid v0 PSBT.
+    let decoded = v0::Psbt::deserialize(&v0.serialize()).expect("valid v0 PSBT");
+    assert_eq!(decoded, v0);
+}
+
+#[test]
+fn v2_into_v0_indeterminate_lock_time() {
+    let height = absolute::Height::from_consensus(800_000).expect("valid height");
+    let time = absolute::Time::from_consensus(1_657_048_460).expect("valid time");
+
+    let a =
+        InputBuilder::new(&out_point(0)).minimum_required_height_based_lock_time(height).build();
+    let b = InputBuilder::new(&out_point(1)).minimum_required_time_based_lock_time(time).build();
+
+    let mut psbt = Constructor::<Modifiable>::default().input(a).psbt().expect("valid lock time");
+    psbt.inputs.push(b);
+    psbt.global.input_count += 1;
+
+    assert!(psbt.into_v0().is_err());
+}
+
+#[test]
+fn v2_try_from() {
+    let psbt = v2_psbt();
+    let v0 = v0::Psbt::try_from(psbt.clone()).expect("valid lock time combination");
+    assert_eq!(v0, psbt.into_v0().unwrap());
+}
+
+/// Parses the BIP-174 test vector PSBT that has been signed by the first signer.
+fn v0_signed_once() -> v0::Psbt {
+    let hex = include_str!("data/sign_1_psbt_hex");
+    let bytes = Vec::<u8>::from_hex(hex.trim()).expect("valid hex");
+    v0::Psbt::deserialize(&bytes).expect("valid v0 PSBT")
+}
+
+#[test]
+fn v0_into_v2() {
+    let v0 = v0_signed_once();
+    // Inputs and outputs modifiable.
+    let psbt = Psbt::from_v0(v0.clone(), 0x03).expect("valid v0 PSBT");
+
+    let tx = &v0.unsigned_tx;
+    assert_eq!(psbt.global.tx_version, tx.version);
+    assert_eq!(psbt.global.fallback_lock_time, Some(tx.lock_time));
+    // The inputs are signed using SIGHASH_ALL.
+    assert_eq!(psbt.global.tx_modifiable_flags, 0x00);
+    assert_eq!(psbt.global.input_count, tx.input.len());
+    assert_eq!(psbt.global.output_count, tx.output.len());
+
+    for (input, (tx_in, v0_input)) in psbt.inputs.iter().zip(tx.input.iter().zip(&v0.inputs)) {
+        assert_eq!(input.previous_txid, tx_in.previous_output.txid);
+        assert_eq!(input.spent_output_index, tx_in.previous_output.vout);
+        assert_eq!(input.sequence, Some(tx_in.sequence));
+        assert_eq!(input.partial_sigs, v0_input.partial_sigs);
+        assert!(!input.partial_sigs.is_empty());
+    }
+    for (output, tx_out) in psbt.outputs.iter().zip(tx.output.iter()) {
+        assert_eq!(output.amount, tx_out.value);
+        assert_eq!(output.script_pubkey, tx_out.script_pubkey);
+    }
+
+    // The upgraded PSBT must be a valid v2 PSBT.
+    let decoded = Psbt::deserialize(&psbt.serialize()).expect("valid v2 PSBT");
+    assert_eq!(decoded, psbt);
+}
+
+#[test]
+fn v0_into_v2_sighash_single() {
+    let mut v0 = v0_signed_once();
+    for input in v0.inputs.iter_mut() {
+        for sig in input.partial_sigs.values_mut() {
+            sig.hash_ty = EcdsaSighashType::SinglePlusAnyoneCanPay;
+        }
+    }
+    // Inputs and outputs modifiable.
+    let psbt = Psbt::from_v0(v0, 0x03).expect("valid v0 PSBT");
+
+    assert!(psbt.global.is_inputs_modifiable());
+    assert!(!psbt.global.is_outputs_modifiable());
+    assert!(psbt.global.has_sighash_single());
+}
+
+#[test]
+fn v0_into_v2_into_v0() {
+    let v0 = v0_signed_once();
+    let psbt = Psbt::from_v0(v0.clone(), 0x00).expect("valid v0 PSBT");
+    assert_eq!(psbt.into_v0().expect("valid lock time"), v0);
+}
+
+#[test]
+fn v0_into_v2_input_count_mismatch() {
+    let mut v0 = v0_signed_once();
+    v0.inputs.pop();
+    assert!(Psbt::from_v0(v0, 0x00).is_err());
+}
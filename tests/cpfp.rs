@@ -0,0 +1,127 @@
+//! Child-pays-for-parent fee bumping of a PSBT v2.
+
+#![cfg(all(feature = "std", feature = "miniscript"))]
+
+mod fixtures;
+
+use fixtures::{
+    descriptor, extract, finalized_payment, master, sign_and_finalize, CHANGE, FUNDING, PAYMENT,
+};
+use psbt_v2::bitcoin::{
+    absolute, transaction, Amount, FeeRate, ScriptBuf, Transaction, TxIn, TxOut, Weight,
+};
+use psbt_v2::v2::{BuildChildError, ChildBuilder, NewChildBuilderError, Psbt};
+use psbt_v2::PayFeeError;
+
+/// Creates a finalized parent paying [`PAYMENT`] with a change output of [`CHANGE`].
+fn parent() -> Psbt { finalized_payment(ScriptBuf::new_op_return([0x01])) }
+
+fn satisfaction_weight() -> Weight {
+    Weight::from_wu(descriptor(&master(), 9).max_weight_to_satisfy().expect("wpkh") as u64)
+}
+
+#[test]
+fn child_lifts_package_fee_rate() {
+    let parent = parent();
+    let parent_tx = extract(parent.clone());
+    let target = FeeRate::from_sat_per_vb_unchecked(10);
+
+    let builder = ChildBuilder::new(parent.clone()).expect("finalized");
+    assert_eq!(builder.parent_fee(), Amount::from_sat(FUNDING - PAYMENT - CHANGE));
+    assert!(builder.parent_fee_rate() < target);
+
+    let child = builder
+        .spend(1, satisfaction_weight())
+        .build(target, descriptor(&master(), 10).script_pubkey())
+        .expect("enough funds")
+        .psbt()
+        .expect("valid lock time combination");
+    assert_eq!(child.inputs.len(), 1);
+    assert_eq!(child.inputs[0].previous_txid, parent_tx.txid());
+    assert_eq!(child.inputs[0].spent_output_index, 1);
+    assert_eq!(child.inputs[0].witness_utxo, Some(parent_tx.output[1].clone()));
+    assert_eq!(child.outputs.len(), 1);
+
+    let child = sign_and_finalize(child, &master(), &[descriptor(&master(), 9)]);
+    let child_fee = child.fee().expect("valid fee");
+    let child_tx = extract(child);
+
+    let package_fee = parent.fee().unwrap() + child_fee;
+    let package_weight = parent_tx.weight() + child_tx.weight();
+    assert!(package_fee >= target.fee_wu(package_weight).unwrap());
+    // The estimate is at most a few weight units above the actual child weight.
+    assert!(package_fee <= target.fee_wu(package_weight + Weight::from_wu(4)).unwrap());
+}
+
+#[test]
+fn child_pays_own_fee_rate() {
+    let parent = parent();
+    // The parent already pays more than the target.
+    let target = FeeRate::from_sat_per_kwu(1);
+    assert!(ChildBuilder::new(parent.clone()).unwrap().parent_fee_rate() > target);
+
+    let child = ChildBuilder::new(parent)
+        .unwrap()
+        .spend(1, satisfaction_weight())
+        .build(target, descriptor(&master(), 10).script_pubkey())
+        .expect("enough funds")
+        .psbt()
+        .unwrap();
+    assert!(child.fee().unwrap() > Amount::ZERO);
+}
+
+#[test]
+fn child_of_legacy_output() {
+    let script_pubkey = ScriptBuf::from_hex("76a914000000000000000000000000000000000000000088ac")
+        .expect("valid hex");
+    let parent = Transaction {
+        version: transaction::Version::TWO,
+        lock_time: absolute::LockTime::ZERO,
+        input: vec![TxIn::default()],
+        output: vec![TxOut { value: Amount::from_sat(FUNDING), script_pubkey }],
+    };
+
+    let child = ChildBuilder::from_tx(parent.clone(), Amount::ZERO)
+        .spend(0, Weight::from_wu(4 * 107))
+        .build(FeeRate::from_sat_per_vb_unchecked(1), descriptor(&master(), 10).script_pubkey())
+        .expect("enough funds")
+        .psbt()
+        .unwrap();
+    assert_eq!(child.inputs[0].non_witness_utxo, Some(parent));
+    assert!(child.inputs[0].witness_utxo.is_none());
+}
+
+#[test]
+fn child_errors() {
+    let change = descriptor(&master(), 10).script_pubkey();
+    let target = FeeRate::from_sat_per_vb_unchecked(10);
+
+    let mut unfinalized = parent();
+    unfinalized.inputs[0].final_script_witness = None;
+    assert!(matches!(ChildBuilder::new(unfinalized), Err(NewChildBuilderError::Extract(_))));
+
+    let builder = ChildBuilder::new(parent()).expect("finalized");
+    assert_eq!(
+        builder.clone().build(target, change.clone()).err(),
+        Some(BuildChildError::NoSpentOutputs)
+    );
+    assert_eq!(
+        builder.clone().spend(2, satisfaction_weight()).build(target, change.clone()).err(),
+        Some(BuildChildError::SpentOutputIndex { vout: 2, output_count: 2 })
+    );
+    assert_eq!(
+        builder
+            .clone()
+            .spend(1, satisfaction_weight())
+            .spend(1, satisfaction_weight())
+            .build(target, change.clone())
+            .err(),
+        Some(BuildChildError::DuplicateSpentOutput { vout: 1 })
+    );
+
+    let too_high = FeeRate::from_sat_per_vb_unchecked(1_000);
+    assert!(matches!(
+        builder.spend(1, satisfaction_weight()).build(too_high, change).err(),
+        Some(BuildChildError::PayFee(PayFeeError::InsufficientFunds { .. }))
+    ));
+}
@@ -0,0 +1,144 @@
+//! Decoding PSBTs from a reader.
+
+#![cfg(feature = "std")]
+
+use std::io::{Cursor, ErrorKind, Read};
+
+use psbt_v2::bitcoin::hashes::Hash as _;
+use psbt_v2::bitcoin::hex::FromHex;
+use psbt_v2::bitcoin::{consensus, Amount, OutPoint, ScriptBuf, TxOut, Txid};
+use psbt_v2::v2::{input, output, Constructor, InputBuilder, Modifiable, OutputBuilder};
+use psbt_v2::{v0, v2};
+
+const V0_HEX: &str = "70736274ff0100750200000001268171371edff285e937adeea4b37b78000c0566cbb3ad64641713ca42171bf60000000000feffffff02d3dff505000000001976a914d0c59903c5bac2868760e90fd521a4665aa7652088ac00e1f5050000000017a9143545e6e33b832c47050f24d3eeb93c9c03948bc787b32e1300000100fda5010100000000010289a3c71eab4d20e0371bbba4cc698fa295c9463afa2e397f8533ccb62f9567e50100000017160014be18d152a9b012039daf3da7de4f53349eecb985ffffffff86f8aa43a71dff1448893a530a7237ef6b4608bbb2dd2d0171e63aec6a4890b40100000017160014fe3e9ef1a745e974d902c4355943abcb34bd5353ffffffff0200c2eb0b000000001976a91485cff1097fd9e008bb34af709c62197b38978a4888ac72fef84e2c00000017a914339725ba21efd62ac753a9bcd067d6c7a6a39d05870247304402202712be22e0270f394f568311dc7ca9a68970b8025fdd3b240229f07f8a5f3a240220018b38d7dcd314e734c9276bd6fb40f673325bc4baa144c800d2f2f02db2765c012103d2e15674941bad4a996372cb87e1856d3652606d98562fe39c5e9e7e413f210502483045022100d12b852d85dcd961d2f5f4ab660654df6eedcc794c0c33ce5cc309ffb5fce58d022067338a8e0e1725c197fb1a88af59f51e44e4255b20167c8684031c05d1f2592a01210223b72beef0965d10be0778efecd61fcac6f79a4ea169393380734464f84f2ab300000000000000";
+
+const V2_HEX: &str = "70736274ff01020402000000010401010105010201fb040200000000010e200b0ad921419c1c8719735d72dc739f9ea9e0638d1fe4c1eef0f9944084815fc8010f0400000000000103080008af2f000000000104160014c430f64c4756da310dbd1a085572ef299926272c000103088bbdeb0b0000000001041600144dd193ac964a56ac1b9e1cca8454fe2f474f851300";
+
+fn bytes(hex: &str) -> Vec<u8> { Vec::from_hex(hex).expect("valid hex") }
+
+/// A reader that returns at most one byte per read.
+struct ByteReader(Cursor<Vec<u8>>);
+
+impl Read for ByteReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = buf.len().min(1);
+        self.0.read(&mut buf[..len])
+    }
+}
+
+#[test]
+fn decode_v0_with_trailing_data() {
+    let psbt = bytes(V0_HEX);
+    let mut buf = psbt.clone();
+    buf.extend_from_slice(&[0xde, 0xad, 0xbe, 0xef]);
+
+    let mut r = &buf[..];
+    let (decoded, consumed) = v0::Psbt::decode(&mut r).expect("valid PSBT");
+    assert_eq!(consumed, psbt.len());
+    assert_eq!(r, &[0xde, 0xad, 0xbe, 0xef]);
+    assert_eq!(decoded, v0::Psbt::deserialize(&psbt).expect("valid PSBT"));
+}
+
+#[test]
+fn decode_v2_with_trailing_data() {
+    let psbt = bytes(V2_HEX);
+    let mut buf = psbt.clone();
+    buf.extend_from_slice(&[0xde, 0xad, 0xbe, 0xef]);
+
+    let mut r = &buf[..];
+    let (decoded, consumed) = v2::Psbt::decode(&mut r).expect("valid PSBT");
+    assert_eq!(consumed, psbt.len());
+    assert_eq!(r, &[0xde, 0xad, 0xbe, 0xef]);
+    assert_eq!(decoded, v2::Psbt::deserialize(&psbt).expect("valid PSBT"));
+}
+
+#[test]
+fn decode_consecutive_psbts() {
+    let psbt = bytes(V2_HEX);
+    let mut buf = psbt.clone();
+    buf.extend_from_slice(&psbt);
+
+    let mut r = ByteReader(Cursor::new(buf));
+    for _ in 0..2 {
+        let (_, consumed) = v2::Psbt::decode(&mut r).expect("valid PSBT");
+        assert_eq!(consumed, psbt.len());
+    }
+    assert_eq!(r.0.position() as usize, 2 * psbt.len());
+}
+
+#[test]
+fn decode_truncated() {
+    let psbt = bytes(V2_HEX);
+
+    let mut r = &psbt[..3];
+    let err = v2::Psbt::decode(&mut r).expect_err("truncated magic");
+    assert!(matches!(err, v2::DeserializeError::Io(_)));
+
+    let mut r = &psbt[..psbt.len() - 1];
+    assert!(v2::Psbt::decode(&mut r).is_err());
+
+    let psbt = bytes(V0_HEX);
+    let mut r = &psbt[..psbt.len() - 1];
+    match v0::Psbt::decode(&mut r).expect_err("truncated output map") {
+        v0::bitcoin::Error::ConsensusEncoding(consensus::encode::Error::Io(e)) =>
+            assert_eq!(e.kind(), ErrorKind::UnexpectedEof),
+        e => panic!("unexpected error: {:?}", e),
+    }
+}
+
+#[test]
+fn decode_huge_input_count() {
+    // An input count of 2^56 - 1 must not be used to preallocate the inputs.
+    let psbt = bytes(&V2_HEX.replace("0104010101", "010409ffffffffffffffff0001"));
+
+    let mut r = &psbt[..];
+    assert!(v2::Psbt::decode(&mut r).is_err());
+}
+
+#[test]
+fn decode_invalid_magic() {
+    let mut psbt = bytes(V2_HEX);
+    psbt[0] = 0x00;
+
+    let mut r = &psbt[..];
+    let err = v2::Psbt::decode(&mut r).expect_err("invalid magic");
+    assert!(matches!(err, v2::DeserializeError::InvalidMagic));
+}
+
+#[test]
+fn decode_zero_value_output() {
+    let anchor =
+        TxOut { value: Amount::ZERO, script_pubkey: ScriptBuf::from_hex("51024e73").unwrap() };
+    let op_return = TxOut { value: Amount::ZERO, script_pubkey: ScriptBuf::new_op_return([0xab]) };
+    let psbt = Constructor::<Modifiable>::default()
+        .input(InputBuilder::new(&OutPoint { txid: Txid::all_zeros(), vout: u32::MAX }).build())
+        .output(OutputBuilder::new(anchor).build())
+        .output(OutputBuilder::new(op_return).build())
+        .no_more_inputs()
+        .no_more_outputs()
+        .expect("no pending silent payment outputs")
+        .psbt()
+        .expect("valid lock time combination");
+
+    let decoded = v2::Psbt::deserialize(&psbt.serialize()).expect("valid PSBT");
+    assert_eq!(decoded, psbt);
+    assert_eq!(decoded.outputs[0].amount, Amount::ZERO);
+    assert_eq!(decoded.inputs[0].previous_txid, Txid::all_zeros());
+    assert_eq!(decoded.inputs[0].spent_output_index, u32::MAX);
+}
+
+#[test]
+fn decode_missing_required_fields() {
+    // Remove the amount of the first output.
+    let psbt = bytes(&V2_HEX.replace("0103080008af2f00000000", ""));
+    let err = v2::Psbt::deserialize(&psbt).expect_err("missing amount");
+    assert!(matches!(err, v2::DeserializeError::DecodeOutput(output::DecodeError::MissingValue)));
+
+    // Remove the spent output index of the input.
+    let psbt = bytes(&V2_HEX.replace("010f0400000000", ""));
+    let err = v2::Psbt::deserialize(&psbt).expect_err("missing spent output index");
+    assert!(matches!(
+        err,
+        v2::DeserializeError::DecodeInput(input::DecodeError::MissingSpentOutputIndex)
+    ));
+}
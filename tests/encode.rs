@@ -0,0 +1,85 @@
+//! Encoding PSBTs to a writer.
+
+#![cfg(feature = "std")]
+
+use std::io::{self, Write};
+
+use psbt_v2::bitcoin::hex::FromHex;
+use psbt_v2::{v0, v2};
+
+const V0_HEX: &str = "70736274ff0100750200000001268171371edff285e937adeea4b37b78000c0566cbb3ad64641713ca42171bf60000000000feffffff02d3dff505000000001976a914d0c59903c5bac2868760e90fd521a4665aa7652088ac00e1f5050000000017a9143545e6e33b832c47050f24d3eeb93c9c03948bc787b32e1300000100fda5010100000000010289a3c71eab4d20e0371bbba4cc698fa295c9463afa2e397f8533ccb62f9567e50100000017160014be18d152a9b012039daf3da7de4f53349eecb985ffffffff86f8aa43a71dff1448893a530a7237ef6b4608bbb2dd2d0171e63aec6a4890b40100000017160014fe3e9ef1a745e974d902c4355943abcb34bd5353ffffffff0200c2eb0b000000001976a91485cff1097fd9e008bb34af709c62197b38978a4888ac72fef84e2c00000017a914339725ba21efd62ac753a9bcd067d6c7a6a39d05870247304402202712be22e0270f394f568311dc7ca9a68970b8025fdd3b240229f07f8a5f3a240220018b38d7dcd314e734c9276bd6fb40f673325bc4baa144c800d2f2f02db2765c012103d2e15674941bad4a996372cb87e1856d3652606d98562fe39c5e9e7e413f210502483045022100d12b852d85dcd961d2f5f4ab660654df6eedcc794c0c33ce5cc309ffb5fce58d022067338a8e0e1725c197fb1a88af59f51e44e4255b20167c8684031c05d1f2592a01210223b72beef0965d10be0778efecd61fcac6f79a4ea169393380734464f84f2ab300000000000000";
+
+const V2_HEX: &str = "70736274ff01020402000000010401010105010201fb040200000000010e200b0ad921419c1c8719735d72dc739f9ea9e0638d1fe4c1eef0f9944084815fc8010f0400000000000103080008af2f000000000104160014c430f64c4756da310dbd1a085572ef299926272c000103088bbdeb0b0000000001041600144dd193ac964a56ac1b9e1cca8454fe2f474f851300";
+
+fn bytes(hex: &str) -> Vec<u8> { Vec::from_hex(hex).expect("valid hex") }
+
+/// A writer that fails once `limit` bytes have been written.
+struct LimitedWriter {
+    buf: Vec<u8>,
+    limit: usize,
+}
+
+impl Write for LimitedWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = buf.len().min(self.limit - self.buf.len());
+        if len == 0 {
+            return Err(io::Error::new(io::ErrorKind::WriteZero, "limit reached"));
+        }
+        self.buf.extend_from_slice(&buf[..len]);
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> { Ok(()) }
+}
+
+#[test]
+fn encode_v0() {
+    let psbt = v0::Psbt::deserialize(&bytes(V0_HEX)).expect("valid PSBT");
+
+    let mut buf = Vec::new();
+    let len = psbt.encode(&mut buf).expect("in-memory writer");
+    assert_eq!(len, buf.len());
+    assert_eq!(buf, bytes(V0_HEX));
+    assert_eq!(buf, psbt.serialize());
+}
+
+#[test]
+fn encode_v2() {
+    let psbt = v2::Psbt::deserialize(&bytes(V2_HEX)).expect("valid PSBT");
+
+    let mut buf = Vec::new();
+    let len = psbt.encode(&mut buf).expect("in-memory writer");
+    assert_eq!(len, buf.len());
+    assert_eq!(buf, psbt.serialize());
+    // Fields are written in canonical order so we only check the bytes decode to the same PSBT.
+    assert_eq!(v2::Psbt::deserialize(&buf).expect("valid PSBT"), psbt);
+}
+
+#[test]
+fn encode_decode_roundtrip() {
+    let psbt = v2::Psbt::deserialize(&bytes(V2_HEX)).expect("valid PSBT");
+
+    let mut buf = Vec::new();
+    let len = psbt.encode(&mut buf).expect("in-memory writer");
+    let (decoded, consumed) = v2::Psbt::decode(&mut &buf[..]).expect("valid PSBT");
+    assert_eq!(decoded, psbt);
+    assert_eq!(consumed, len);
+}
+
+#[test]
+fn encode_writer_error() {
+    let v0 = v0::Psbt::deserialize(&bytes(V0_HEX)).expect("valid PSBT");
+    let v2 = v2::Psbt::deserialize(&bytes(V2_HEX)).expect("valid PSBT");
+
+    // Fail in the magic bytes, in the global map and in the last output map.
+    for limit in [2, 20, v2.serialize().len() - 1] {
+        let mut w = LimitedWriter { buf: Vec::new(), limit };
+        let err = v2.encode(&mut w).expect_err("writer fails");
+        assert_eq!(err.kind(), io::ErrorKind::WriteZero);
+    }
+    for limit in [2, 20, v0.serialize().len() - 1] {
+        let mut w = LimitedWriter { buf: Vec::new(), limit };
+        let err = v0.encode(&mut w).expect_err("writer fails");
+        assert_eq!(err.kind(), io::ErrorKind::WriteZero);
+    }
+}
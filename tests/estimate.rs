@@ -0,0 +1,129 @@
+//! Estimating the weight and fee rate of a PSBT v2 before it is finalized.
+
+#![cfg(all(feature = "std", feature = "miniscript"))]
+
+use core::str::FromStr;
+
+use psbt_v2::bitcoin::bip32::{Xpriv, Xpub};
+use psbt_v2::bitcoin::hashes::Hash as _;
+use psbt_v2::bitcoin::secp256k1::Secp256k1;
+use psbt_v2::bitcoin::{Amount, FeeRate, Network, OutPoint, ScriptBuf, TxOut, Txid, Weight};
+use psbt_v2::miniscript::descriptor::{DefiniteDescriptorKey, Descriptor, DescriptorPublicKey};
+use psbt_v2::v2::{
+    Constructor, EstimateInputError, EstimateWeightError, Extractor, Finalizer, InputBuilder,
+    Modifiable, OutputBuilder, Psbt, Signer,
+};
+
+fn master() -> Xpriv { Xpriv::new_master(Network::Testnet, &[0x01; 32]).expect("valid seed") }
+
+fn xpub() -> Xpub { Xpub::from_priv(&Secp256k1::new(), &master()) }
+
+fn descriptor(s: &str) -> Descriptor<DefiniteDescriptorKey> {
+    Descriptor::<DescriptorPublicKey>::from_str(s)
+        .expect("valid descriptor")
+        .at_derivation_index(0)
+        .expect("valid derivation index")
+}
+
+fn descriptors() -> Vec<Descriptor<DefiniteDescriptorKey>> {
+    let other = Xpriv::new_master(Network::Testnet, &[0x02; 32]).expect("valid seed");
+    let other = Xpub::from_priv(&Secp256k1::new(), &other);
+    vec![
+        descriptor(&format!("wpkh({}/0/*)", xpub())),
+        descriptor(&format!("wsh(multi(1,{}/1/*,{}/0/*))", xpub(), other)),
+        descriptor(&format!("sh(wpkh({}/2/*))", xpub())),
+        descriptor(&format!("tr({}/3/*)", xpub())),
+    ]
+}
+
+/// Creates an updated PSBT with an input spending each descriptor in `descs`.
+fn updated_psbt(descs: &[Descriptor<DefiniteDescriptorKey>]) -> Psbt {
+    let mut constructor = Constructor::<Modifiable>::default();
+    for (vout, desc) in descs.iter().enumerate() {
+        let out_point = OutPoint { txid: Txid::from_byte_array([0x01; 32]), vout: vout as u32 };
+        let utxo = TxOut { value: Amount::from_sat(100_000), script_pubkey: desc.script_pubkey() };
+        constructor = constructor.input(InputBuilder::new(&out_point).segwit_fund(utxo).build());
+    }
+    let output = OutputBuilder::new(TxOut {
+        value: Amount::from_sat(90_000 * descs.len() as u64),
+        script_pubkey: ScriptBuf::new_op_return([0x01]),
+    })
+    .build();
+
+    let mut updater = constructor.output(output).updater().expect("valid lock time combination");
+    for (input_index, desc) in descs.iter().enumerate() {
+        updater =
+            updater.update_input_with_descriptor(input_index, desc).expect("failed to update");
+    }
+    updater.psbt()
+}
+
+fn finalized_psbt(psbt: Psbt) -> Psbt {
+    let secp = Secp256k1::new();
+    let signer = Signer::new(psbt).expect("valid lock time combination");
+    let (psbt, _) = signer.sign(&master(), &secp).expect("failed to sign");
+    Finalizer::new(psbt).expect("valid PSBT").finalize(&secp).expect("failed to finalize")
+}
+
+#[test]
+fn estimate_is_upper_bound() {
+    let descs = descriptors();
+    let psbt = updated_psbt(&descs);
+
+    let estimate = psbt.estimate_weight().expect("scripts and keys present");
+    assert_eq!(psbt.estimate_weight_with_descriptors(&descs).expect("one per input"), estimate);
+
+    let tx = Extractor::new(finalized_psbt(psbt))
+        .expect("finalized")
+        .extract_tx_unchecked_fee_rate()
+        .expect("valid transaction");
+    assert!(tx.weight() <= estimate);
+    // Signatures are at most a few bytes smaller than assumed.
+    assert!(estimate - tx.weight() <= Weight::from_wu(4 * descs.len() as u64));
+}
+
+#[test]
+fn estimate_finalized() {
+    let descs = descriptors();
+    let finalized = finalized_psbt(updated_psbt(&descs));
+    let tx = Extractor::new(finalized.clone())
+        .expect("finalized")
+        .extract_tx_unchecked_fee_rate()
+        .expect("valid transaction");
+
+    assert_eq!(finalized.estimate_weight().expect("finalized"), tx.weight());
+}
+
+#[test]
+fn estimate_fee_rate() {
+    let descs = descriptors();
+    let psbt = updated_psbt(&descs);
+
+    let weight = psbt.estimate_weight().expect("scripts and keys present");
+    let fee = psbt.fee().expect("valid fee");
+    let fee_rate = psbt.estimate_fee_rate().expect("valid fee");
+    assert_eq!(fee_rate, FeeRate::from_sat_per_kwu(fee.to_sat() * 1000 / weight.to_wu()));
+    assert_eq!(psbt.estimate_fee_rate_with_descriptors(&descs).expect("valid fee"), fee_rate);
+}
+
+#[test]
+fn estimate_missing_witness_script() {
+    let descs = descriptors();
+    let mut psbt = updated_psbt(&descs);
+    psbt.inputs[1].witness_script = None;
+
+    match psbt.estimate_weight() {
+        Err(EstimateWeightError::Input {
+            input_index: 1,
+            error: EstimateInputError::MissingWitnessScript,
+        }) => {}
+        res => panic!("unexpected result: {:?}", res),
+    }
+    // The descriptor provides the script instead.
+    assert!(psbt.estimate_weight_with_descriptors(&descs).is_ok());
+
+    match psbt.estimate_weight_with_descriptors(&descs[1..]) {
+        Err(EstimateWeightError::DescriptorCount { inputs: 4, descriptors: 3 }) => {}
+        res => panic!("unexpected result: {:?}", res),
+    }
+}
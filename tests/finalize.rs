@@ -0,0 +1,189 @@
+//! Finalizing PSBT v2 inputs using the miniscript `Finalizer`.
+
+#![cfg(all(feature = "std", feature = "miniscript"))]
+
+use core::str::FromStr;
+
+use psbt_v2::bitcoin::bip32::{Xpriv, Xpub};
+use psbt_v2::bitcoin::hashes::Hash as _;
+use psbt_v2::bitcoin::secp256k1::Secp256k1;
+use psbt_v2::bitcoin::{
+    absolute, transaction, Amount, Network, OutPoint, ScriptBuf, Transaction, TxOut, Txid, Witness,
+};
+use psbt_v2::miniscript::descriptor::{DefiniteDescriptorKey, Descriptor, DescriptorPublicKey};
+use psbt_v2::v2::{
+    Constructor, FinalizeError, Finalizer, InputBuilder, Modifiable, OutputBuilder, Psbt, Signer,
+};
+
+fn master() -> Xpriv { Xpriv::new_master(Network::Testnet, &[0x01; 32]).expect("valid seed") }
+
+fn descriptor(s: &str) -> Descriptor<DefiniteDescriptorKey> {
+    Descriptor::<DescriptorPublicKey>::from_str(s)
+        .expect("valid descriptor")
+        .at_derivation_index(0)
+        .expect("valid derivation index")
+}
+
+/// Creates a signed PSBT with a single input spending `desc`.
+fn signed_psbt(desc: &Descriptor<DefiniteDescriptorKey>) -> Psbt { signed_psbt_multi(&[desc]) }
+
+/// Creates a PSBT with an input spending each descriptor in `descs`, signed using [`master`].
+fn signed_psbt_multi(descs: &[&Descriptor<DefiniteDescriptorKey>]) -> Psbt {
+    let mut constructor = Constructor::<Modifiable>::default();
+    for (vout, desc) in descs.iter().enumerate() {
+        let out_point = OutPoint { txid: Txid::all_zeros(), vout: vout as u32 };
+        let utxo = TxOut { value: Amount::from_sat(100_000), script_pubkey: desc.script_pubkey() };
+        constructor = constructor.input(InputBuilder::new(&out_point).segwit_fund(utxo).build());
+    }
+    sign(constructor, descs)
+}
+
+/// Creates a signed PSBT with a single input spending the legacy descriptor `desc`.
+fn signed_legacy_psbt(desc: &Descriptor<DefiniteDescriptorKey>) -> Psbt {
+    let utxo = TxOut { value: Amount::from_sat(100_000), script_pubkey: desc.script_pubkey() };
+    let funding = Transaction {
+        version: transaction::Version::TWO,
+        lock_time: absolute::LockTime::ZERO,
+        input: vec![],
+        output: vec![utxo],
+    };
+    let out_point = OutPoint { txid: funding.txid(), vout: 0 };
+    let input = InputBuilder::new(&out_point).legacy_fund(funding).build();
+    sign(Constructor::<Modifiable>::default().input(input), &[desc])
+}
+
+/// Adds an output to `constructor` and signs input `i` using [`master`], updated with `descs[i]`.
+fn sign(
+    constructor: Constructor<Modifiable>,
+    descs: &[&Descriptor<DefiniteDescriptorKey>],
+) -> Psbt {
+    let secp = Secp256k1::new();
+    let output = OutputBuilder::new(TxOut {
+        value: Amount::from_sat(90_000),
+        script_pubkey: ScriptBuf::new_op_return([0x01]),
+    })
+    .build();
+
+    let mut updater = constructor.output(output).updater().expect("valid lock time combination");
+    for (input_index, desc) in descs.iter().enumerate() {
+        updater =
+            updater.update_input_with_descriptor(input_index, desc).expect("failed to update");
+    }
+
+    let signer = Signer::new(updater.psbt()).expect("valid lock time combination");
+    let (psbt, _) = signer.sign(&master(), &secp).expect("failed to sign");
+    psbt
+}
+
+fn xpub() -> Xpub { Xpub::from_priv(&Secp256k1::new(), &master()) }
+
+#[test]
+fn finalize_wpkh() {
+    let secp = Secp256k1::new();
+    let desc = descriptor(&format!("wpkh({}/0/*)", xpub()));
+    let finalizer = Finalizer::new(signed_psbt(&desc)).expect("valid PSBT");
+
+    let finalized = finalizer.finalize(&secp).expect("failed to finalize");
+    assert!(finalized.is_finalized());
+    assert_eq!(finalized.inputs[0].final_script_witness.as_ref().map(|w| w.len()), Some(2));
+}
+
+#[test]
+fn finalize_mall_wsh() {
+    let secp = Secp256k1::new();
+    let desc = descriptor(&format!("wsh(pk({}/0/*))", xpub()));
+    let finalizer = Finalizer::new(signed_psbt(&desc)).expect("valid PSBT");
+
+    let finalized = finalizer.finalize(&secp).expect("failed to finalize");
+    let finalized_mall = finalizer.finalize_mall(&secp).expect("failed to finalize");
+    // There is only one way to satisfy a single key, both modes produce the same witness.
+    assert_eq!(finalized, finalized_mall);
+}
+
+/// Creates a PSBT where the first input is signed and the second is not.
+fn partially_signed_psbt() -> Psbt {
+    let signed = descriptor(&format!("wpkh({}/0/*)", xpub()));
+    let other = Xpriv::new_master(Network::Testnet, &[0x02; 32]).expect("valid seed");
+    let other = Xpub::from_priv(&Secp256k1::new(), &other);
+    let unsigned = descriptor(&format!("wpkh({}/0/*)", other));
+
+    signed_psbt_multi(&[&signed, &unsigned])
+}
+
+#[test]
+fn finalize_best_effort() {
+    let secp = Secp256k1::new();
+    let psbt = partially_signed_psbt();
+    let finalizer = Finalizer::new(psbt.clone()).expect("valid PSBT");
+
+    assert!(finalizer.finalize(&secp).is_err());
+
+    let (finalized, errors) = finalizer.finalize_best_effort(&secp);
+    assert!(finalized.inputs[0].is_finalized());
+    assert!(!finalized.inputs[1].is_finalized());
+    assert_eq!(errors.keys().copied().collect::<Vec<_>>(), vec![1]);
+    // Finalizing an input must not change the transaction being signed.
+    let id = |psbt: Psbt| Finalizer::new(psbt).expect("valid PSBT").id();
+    assert_eq!(id(finalized.clone()), id(psbt));
+
+    // Already finalized inputs are left alone.
+    let finalizer = Finalizer::new(finalized.clone()).expect("valid PSBT");
+    let (again, errors) = finalizer.finalize_best_effort(&secp);
+    assert_eq!(again, finalized);
+    assert_eq!(errors.len(), 1);
+}
+
+#[test]
+fn finalize_single_input() {
+    let secp = Secp256k1::new();
+    let finalizer = Finalizer::new(partially_signed_psbt()).expect("valid PSBT");
+
+    let finalized = finalizer.finalize_input(0, &secp).expect("input 0 is signed");
+    assert!(finalized.inputs[0].is_finalized());
+    assert!(!finalized.inputs[1].is_finalized());
+
+    match finalizer.finalize_input(1, &secp) {
+        Err(FinalizeError::FinalizeInput { input_index: 1, .. }) => {}
+        res => panic!("unexpected result: {:?}", res),
+    }
+    match finalizer.finalize_input_mall(2, &secp) {
+        Err(FinalizeError::IndexOutOfBounds(_)) => {}
+        res => panic!("unexpected result: {:?}", res),
+    }
+}
+
+#[test]
+fn finalize_after_single_input() {
+    let secp = Secp256k1::new();
+    let finalizer = Finalizer::new(partially_signed_psbt()).expect("valid PSBT");
+    let psbt = finalizer.finalize_input(0, &secp).expect("input 0 is signed");
+    // Finalizing clears the partial sigs and derivations of input 0.
+    assert!(psbt.inputs[0].partial_sigs.is_empty());
+
+    let finalizer = Finalizer::new(psbt.clone()).expect("valid PSBT");
+    assert_eq!(finalizer.finalize_input(0, &secp).expect("already finalized"), psbt);
+
+    // The second party signs input 1 later, then the whole PSBT is finalized.
+    let other = Xpriv::new_master(Network::Testnet, &[0x02; 32]).expect("valid seed");
+    let (psbt, _) = Signer::new(psbt).expect("valid PSBT").sign(&other, &secp).expect("signed");
+    let finalized =
+        Finalizer::new(psbt.clone()).expect("valid PSBT").finalize(&secp).expect("all signed");
+    assert_eq!(finalized.inputs[0], psbt.inputs[0]);
+    assert!(finalized.is_finalized());
+}
+
+#[test]
+fn finalize_legacy_input_again() {
+    let secp = Secp256k1::new();
+    let desc = descriptor(&format!("pkh({}/0/*)", xpub()));
+    let finalizer = Finalizer::new(signed_legacy_psbt(&desc)).expect("valid PSBT");
+
+    let psbt = finalizer.finalize_input(0, &secp).expect("input 0 is signed");
+    assert!(psbt.inputs[0].is_finalized());
+    assert_eq!(psbt.inputs[0].final_script_witness, Some(Witness::default()));
+
+    // The signatures are cleared, finalizing again must keep the finalized input.
+    let finalizer = Finalizer::new(psbt.clone()).expect("valid PSBT");
+    assert_eq!(finalizer.finalize_input(0, &secp).expect("already finalized"), psbt);
+    assert_eq!(finalizer.finalize(&secp).expect("already finalized"), psbt);
+}
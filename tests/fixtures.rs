@@ -0,0 +1,77 @@
+#![cfg(all(feature = "std", feature = "miniscript"))]
+// Functions in this file are all used but clippy complains still.
+#![allow(dead_code)]
+
+use core::str::FromStr;
+
+use psbt_v2::bitcoin::bip32::{Xpriv, Xpub};
+use psbt_v2::bitcoin::hashes::Hash as _;
+use psbt_v2::bitcoin::secp256k1::Secp256k1;
+use psbt_v2::bitcoin::{Amount, Network, OutPoint, ScriptBuf, Transaction, TxOut, Txid};
+use psbt_v2::miniscript::descriptor::{DefiniteDescriptorKey, Descriptor, DescriptorPublicKey};
+use psbt_v2::v2::{Constructor, Finalizer, InputBuilder, Modifiable, OutputBuilder, Psbt, Signer};
+
+/// The value of the utxo spent by the original transaction.
+pub const FUNDING: u64 = 100_000;
+/// The value of the payment output of the original transaction.
+pub const PAYMENT: u64 = 50_000;
+/// The value of the change output of the original transaction.
+pub const CHANGE: u64 = 49_000;
+
+/// Returns the master key of the wallet funding the original transaction.
+pub fn master() -> Xpriv { Xpriv::new_master(Network::Testnet, &[0x01; 32]).expect("valid seed") }
+
+/// Returns the `wpkh` descriptor of `key` at derivation path `0/index`.
+pub fn descriptor(key: &Xpriv, index: u32) -> Descriptor<DefiniteDescriptorKey> {
+    let xpub = Xpub::from_priv(&Secp256k1::new(), key);
+    Descriptor::<DescriptorPublicKey>::from_str(&format!("wpkh({}/0/*)", xpub))
+        .expect("valid descriptor")
+        .at_derivation_index(index)
+        .expect("valid derivation index")
+}
+
+/// Signs and finalizes `psbt` with `key`, updating each input with the descriptor at the same
+/// index first since finalizing removes the derivations.
+pub fn sign_and_finalize(
+    psbt: Psbt,
+    key: &Xpriv,
+    descriptors: &[Descriptor<DefiniteDescriptorKey>],
+) -> Psbt {
+    let secp = Secp256k1::new();
+    let mut updater = psbt.into_updater().expect("valid lock time combination");
+    for (index, desc) in descriptors.iter().enumerate() {
+        updater = updater.update_input_with_descriptor(index, desc).expect("failed to update");
+    }
+    let (psbt, _) = Signer::new(updater.psbt())
+        .expect("valid lock time combination")
+        .sign(key, &secp)
+        .expect("failed to sign");
+    Finalizer::new(psbt).expect("valid PSBT").finalize(&secp).expect("failed to finalize")
+}
+
+/// Creates a finalized PSBT spending [`FUNDING`] from the descriptor at index 0, paying
+/// [`PAYMENT`] to `payee` with a change output of [`CHANGE`] to the descriptor at index 9.
+pub fn finalized_payment(payee: ScriptBuf) -> Psbt {
+    let out_point = OutPoint { txid: Txid::from_byte_array([0x01; 32]), vout: 0 };
+    let utxo = TxOut {
+        value: Amount::from_sat(FUNDING),
+        script_pubkey: descriptor(&master(), 0).script_pubkey(),
+    };
+    let payment = TxOut { value: Amount::from_sat(PAYMENT), script_pubkey: payee };
+    let change = TxOut {
+        value: Amount::from_sat(CHANGE),
+        script_pubkey: descriptor(&master(), 9).script_pubkey(),
+    };
+    let psbt = Constructor::<Modifiable>::default()
+        .input(InputBuilder::new(&out_point).segwit_fund(utxo).build())
+        .output(OutputBuilder::new(payment).build())
+        .output(OutputBuilder::new(change).build())
+        .psbt()
+        .expect("valid lock time combination");
+    sign_and_finalize(psbt, &master(), &[descriptor(&master(), 0)])
+}
+
+/// Extracts the transaction from the finalized `psbt`.
+pub fn extract(psbt: Psbt) -> Transaction {
+    psbt.into_extractor().expect("finalized").extract_tx_unchecked_fee_rate().expect("valid tx")
+}
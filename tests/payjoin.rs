@@ -0,0 +1,230 @@
+//! PayJoin (BIP-78) between a sender and a receiver using PSBT v2.
+
+#![cfg(all(feature = "std", feature = "miniscript"))]
+
+mod fixtures;
+
+use fixtures::{descriptor, CHANGE, FUNDING, PAYMENT};
+use psbt_v2::bitcoin::bip32::Xpriv;
+use psbt_v2::bitcoin::hashes::Hash as _;
+use psbt_v2::bitcoin::secp256k1::Secp256k1;
+use psbt_v2::bitcoin::{Amount, FeeRate, Network, OutPoint, ScriptBuf, Sequence, TxOut, Txid};
+use psbt_v2::v2::payjoin::{
+    ContributeError, FinalizeProposalError, Params, ProposalError, Receiver, Sender,
+};
+use psbt_v2::v2::{
+    Constructor, Finalizer, Input, InputBuilder, Modifiable, OutputBuilder, Psbt, Updater,
+};
+
+const RECEIVER_FUNDING: u64 = 30_000;
+
+fn sender_key() -> Xpriv { fixtures::master() }
+
+fn receiver_key() -> Xpriv { Xpriv::new_master(Network::Testnet, &[0x02; 32]).expect("valid seed") }
+
+fn payee() -> ScriptBuf { descriptor(&receiver_key(), 0).script_pubkey() }
+
+fn params() -> Params {
+    Params {
+        additional_fee_output_index: Some(1),
+        max_additional_fee_contribution: Amount::from_sat(1_000),
+        ..Default::default()
+    }
+}
+
+/// Updates, signs and finalizes the input at `input_index` with `key`.
+fn sign_input(updater: Updater, key: &Xpriv, index: u32, input_index: usize) -> Psbt {
+    let secp = Secp256k1::new();
+    let psbt = updater
+        .update_input_with_descriptor(input_index, &descriptor(key, index))
+        .expect("failed to update")
+        .psbt();
+    let (psbt, _) =
+        psbt.into_signer().expect("valid PSBT").sign(key, &secp).expect("failed to sign");
+    Finalizer::new(psbt)
+        .expect("valid PSBT")
+        .finalize_input(input_index, &secp)
+        .expect("failed to finalize")
+}
+
+/// Creates the sender's finalized original PSBT paying [`PAYMENT`] to the receiver.
+fn original() -> Psbt {
+    let out_point = OutPoint { txid: Txid::from_byte_array([0x01; 32]), vout: 0 };
+    let utxo = TxOut {
+        value: Amount::from_sat(FUNDING),
+        script_pubkey: descriptor(&sender_key(), 0).script_pubkey(),
+    };
+    let payment = TxOut { value: Amount::from_sat(PAYMENT), script_pubkey: payee() };
+    let change = TxOut {
+        value: Amount::from_sat(CHANGE),
+        script_pubkey: descriptor(&sender_key(), 1).script_pubkey(),
+    };
+    let updater = Constructor::<Modifiable>::default()
+        .input(InputBuilder::new(&out_point).segwit_fund(utxo).build())
+        .output(OutputBuilder::new(payment).build())
+        .output(OutputBuilder::new(change).build())
+        .updater()
+        .expect("valid lock time combination");
+    sign_input(updater, &sender_key(), 0, 0)
+}
+
+fn receiver_input() -> Input {
+    let out_point = OutPoint { txid: Txid::from_byte_array([0x02; 32]), vout: 0 };
+    let utxo = TxOut {
+        value: Amount::from_sat(RECEIVER_FUNDING),
+        script_pubkey: descriptor(&receiver_key(), 1).script_pubkey(),
+    };
+    InputBuilder::new(&out_point).segwit_fund(utxo).build()
+}
+
+/// Runs the receiver side, returning the finalized proposal.
+fn proposal(original: Psbt, params: Params) -> Psbt {
+    let receiver = Receiver::new(original, params).expect("valid original");
+    let payment =
+        TxOut { value: Amount::from_sat(PAYMENT + RECEIVER_FUNDING), script_pubkey: payee() };
+    let updater = receiver
+        .clone()
+        .contribute_input(receiver_input())
+        .expect("valid input")
+        .substitute_output(&payee(), OutputBuilder::new(payment).build())
+        .expect("payee output")
+        .build()
+        .expect("valid lock time combination");
+    let psbt = sign_input(updater, &receiver_key(), 1, 1);
+    receiver.finalize_proposal(psbt).expect("receiver input finalized")
+}
+
+#[test]
+fn payjoin() {
+    let sender = Sender::new(original(), payee(), params()).expect("valid original");
+    let original_fee = original().fee().unwrap();
+
+    let proposal = proposal(sender.original_psbt(), params());
+    assert_eq!(proposal.inputs.len(), 2);
+    assert!(proposal.inputs[0].witness_utxo.is_none());
+    assert!(!proposal.inputs[0].is_finalized());
+    assert!(proposal.inputs[1].is_finalized());
+    assert!(proposal.inputs[1].bip32_derivations.is_empty());
+
+    let updater = sender.validate_proposal(proposal).expect("valid proposal");
+    let psbt = sign_input(updater, &sender_key(), 0, 0);
+    let fee = psbt.fee().expect("valid fee");
+    let tx = psbt.into_extractor().expect("finalized").extract_tx().expect("valid fee rate");
+
+    assert_eq!(tx.input.len(), 2);
+    // The receiver took its input's share of the fee from the sender's change.
+    let change = tx.output.iter().find(|o| o.script_pubkey != payee()).unwrap().value;
+    assert_eq!(CHANGE - change.to_sat(), fee.to_sat() - original_fee.to_sat());
+    assert!(change < Amount::from_sat(CHANGE));
+    assert!(change >= Amount::from_sat(CHANGE - 1_000));
+    let payment = tx.output.iter().find(|o| o.script_pubkey == payee()).unwrap().value;
+    assert_eq!(payment, Amount::from_sat(PAYMENT + RECEIVER_FUNDING));
+}
+
+#[test]
+fn sender_rejects_changed_outputs() {
+    let sender = Sender::new(original(), payee(), params()).expect("valid original");
+    let proposal = proposal(sender.original_psbt(), params());
+
+    let mut changed = proposal.clone();
+    let change = changed.outputs.iter_mut().find(|o| o.script_pubkey != payee()).unwrap();
+    change.amount = Amount::from_sat(CHANGE - 5_000);
+    match sender.validate_proposal(changed) {
+        Err(ProposalError::FeeContributionTooHigh { .. }) => {}
+        res => panic!("unexpected result: {:?}", res),
+    }
+
+    let mut removed = proposal;
+    removed.outputs.retain(|o| o.script_pubkey == payee());
+    removed.global.output_count = removed.outputs.len();
+    match sender.validate_proposal(removed) {
+        Err(ProposalError::MissingSenderOutput { output_index: 1 }) => {}
+        res => panic!("unexpected result: {:?}", res),
+    }
+}
+
+#[test]
+fn sender_rejects_changed_inputs() {
+    let sender = Sender::new(original(), payee(), params()).expect("valid original");
+    let proposal = proposal(sender.original_psbt(), params());
+
+    let mut sequence = proposal.clone();
+    sequence.inputs[0].sequence = Some(Sequence::ZERO);
+    match sender.validate_proposal(sequence) {
+        Err(ProposalError::SequenceChanged { input_index: 0 }) => {}
+        res => panic!("unexpected result: {:?}", res),
+    }
+
+    let mut unfinalized = proposal.clone();
+    unfinalized.inputs[1].final_script_witness = None;
+    match sender.validate_proposal(unfinalized) {
+        Err(ProposalError::ReceiverInputNotFinalized { input_index: 1 }) => {}
+        res => panic!("unexpected result: {:?}", res),
+    }
+
+    // The sender input is spent twice instead of spending a receiver input.
+    let mut duplicate = proposal.clone();
+    duplicate.inputs[1] = duplicate.inputs[0].clone();
+    match sender.validate_proposal(duplicate) {
+        Err(ProposalError::DuplicateInput { input_index: 1 }) => {}
+        res => panic!("unexpected result: {:?}", res),
+    }
+
+    let mut removed = proposal;
+    removed.inputs.remove(0);
+    removed.global.input_count = 1;
+    match sender.validate_proposal(removed) {
+        Err(ProposalError::MissingSenderInput) => {}
+        res => panic!("unexpected result: {:?}", res),
+    }
+}
+
+#[test]
+fn output_substitution_disabled() {
+    let params = Params { disable_output_substitution: true, ..params() };
+    let receiver = Receiver::new(original(), params).expect("valid original");
+    let other = TxOut {
+        value: Amount::from_sat(PAYMENT),
+        script_pubkey: descriptor(&receiver_key(), 2).script_pubkey(),
+    };
+    match receiver.substitute_output(&payee(), OutputBuilder::new(other).build()) {
+        Err(ContributeError::OutputSubstitutionDisabled) => {}
+        res => panic!("unexpected result: {:?}", res),
+    }
+
+    // Without output substitution the sender also rejects a reduced payment.
+    let sender = Sender::new(original(), payee(), params).expect("valid original");
+    let mut proposal = proposal(sender.original_psbt(), params);
+    proposal.outputs.iter_mut().find(|o| o.script_pubkey == payee()).unwrap().amount =
+        Amount::from_sat(PAYMENT - 1);
+    match sender.validate_proposal(proposal) {
+        Err(ProposalError::PayeeOutputChanged) => {}
+        res => panic!("unexpected result: {:?}", res),
+    }
+}
+
+#[test]
+fn min_fee_rate() {
+    let params = Params { min_fee_rate: Some(FeeRate::from_sat_per_vb_unchecked(100)), ..params() };
+    let sender = Sender::new(original(), payee(), params).expect("valid original");
+    match sender.validate_proposal(proposal(sender.original_psbt(), params)) {
+        Err(ProposalError::FeeRateBelowMinimum { .. }) => {}
+        res => panic!("unexpected result: {:?}", res),
+    }
+}
+
+#[test]
+fn receiver_input_not_finalized() {
+    let receiver = Receiver::new(original(), params()).expect("valid original");
+    let psbt = receiver
+        .clone()
+        .contribute_input(receiver_input())
+        .expect("valid input")
+        .build()
+        .expect("valid lock time combination")
+        .psbt();
+    assert_eq!(
+        receiver.finalize_proposal(psbt),
+        Err(FinalizeProposalError::InputNotFinalized { input_index: 1 })
+    );
+}
@@ -0,0 +1,113 @@
+//! Inspecting a serialized PSBT using the borrowed `PsbtRef` view.
+
+#![cfg(feature = "std")]
+
+use psbt_v2::bitcoin::hashes::Hash as _;
+use psbt_v2::bitcoin::hex::FromHex;
+use psbt_v2::bitcoin::Amount;
+use psbt_v2::v2::{FieldError, Psbt, PsbtRef, PsbtRefError};
+
+const V2_HEX: &str = "70736274ff01020402000000010401010105010201fb040200000000010e200b0ad921419c1c8719735d72dc739f9ea9e0638d1fe4c1eef0f9944084815fc8010f0400000000000103080008af2f000000000104160014c430f64c4756da310dbd1a085572ef299926272c000103088bbdeb0b0000000001041600144dd193ac964a56ac1b9e1cca8454fe2f474f851300";
+
+const V0_HEX: &str = "70736274ff01003302000000010b0ad921419c1c8719735d72dc739f9ea9e0638d1fe4c1eef0f9944084815fc80000000000ffffffff0000000000000000";
+
+fn bytes(hex: &str) -> Vec<u8> { Vec::from_hex(hex).expect("valid hex") }
+
+#[test]
+fn matches_owned_psbt() {
+    let buf = bytes(V2_HEX);
+    let psbt = Psbt::deserialize(&buf).expect("valid PSBT");
+    let view = PsbtRef::new(&buf).expect("valid PSBT");
+
+    assert_eq!(view.len(), buf.len());
+    assert_eq!(view.tx_version().expect("tx version"), psbt.global.tx_version);
+    assert_eq!(view.input_count(), psbt.inputs.len());
+    assert_eq!(view.output_count(), psbt.outputs.len());
+
+    for (input, want) in view.inputs().zip(psbt.inputs.iter()) {
+        assert_eq!(input.previous_txid().expect("txid"), want.previous_txid);
+        assert_eq!(input.spent_output_index().expect("index"), want.spent_output_index);
+        assert_eq!(input.sequence().expect("valid sequence"), want.sequence);
+        assert_eq!(input.witness_utxo().expect("valid witness utxo"), want.witness_utxo);
+    }
+    for (output, want) in view.outputs().zip(psbt.outputs.iter()) {
+        assert_eq!(output.amount().expect("amount"), want.amount);
+        assert_eq!(output.script_pubkey(), Some(want.script_pubkey.as_script()));
+    }
+
+    let total = view.outputs().map(|output| output.amount().expect("amount")).sum::<Amount>();
+    assert_eq!(total, Amount::from_sat(0x2faf0800 + 0x0bebbd8b));
+}
+
+#[test]
+fn pairs_match_raw_pairs() {
+    let buf = bytes(V2_HEX);
+    let psbt = Psbt::deserialize(&buf).expect("valid PSBT");
+    let view = PsbtRef::new(&buf).expect("valid PSBT");
+    let input = view.input(0).expect("one input");
+    assert!(view.input(1).is_none());
+
+    let pair = input.map().pairs().next().expect("at least one pair");
+    assert_eq!(pair.type_value, 0x0e);
+    assert!(pair.key.is_empty());
+    assert_eq!(pair.value, psbt.inputs[0].previous_txid.as_byte_array());
+
+    let owned = pair.to_pair();
+    assert_eq!(owned.key.type_value, pair.type_value);
+    assert_eq!(owned.value, pair.value);
+    assert_eq!(input.map().pairs().count(), 2);
+}
+
+#[test]
+fn ignores_trailing_data() {
+    let psbt = bytes(V2_HEX);
+    let mut buf = psbt.clone();
+    buf.extend_from_slice(&[0xde, 0xad, 0xbe, 0xef]);
+
+    let view = PsbtRef::new(&buf).expect("valid PSBT");
+    assert_eq!(view.len(), psbt.len());
+}
+
+#[test]
+fn truncated() {
+    let buf = bytes(V2_HEX);
+    for len in 5..buf.len() {
+        assert!(PsbtRef::new(&buf[..len]).is_err(), "truncated to {} bytes", len);
+    }
+    let err = PsbtRef::new(&buf[..buf.len() - 1]).expect_err("missing separator");
+    assert_eq!(err, PsbtRefError::UnexpectedEnd);
+}
+
+#[test]
+fn invalid_magic() {
+    let mut buf = bytes(V2_HEX);
+    buf[0] = b'x';
+    assert_eq!(PsbtRef::new(&buf).expect_err("invalid magic"), PsbtRefError::InvalidMagic);
+}
+
+#[test]
+fn rejects_v0() {
+    let buf = bytes(V0_HEX);
+    assert_eq!(PsbtRef::new(&buf).expect_err("not a v2 PSBT"), PsbtRefError::MissingVersion);
+}
+
+#[test]
+fn missing_field() {
+    // Change the key type of the first output's amount to an unknown key type.
+    let buf = bytes(&V2_HEX.replace("0103080008af2f00000000", "0120080008af2f00000000"));
+    let view = PsbtRef::new(&buf).expect("valid structure");
+
+    let err = view.output(0).expect("first output").amount().expect_err("missing amount");
+    assert!(matches!(err, FieldError::Missing { field: "PSBT_OUT_AMOUNT" }));
+    assert!(view.output(1).expect("second output").amount().is_ok());
+}
+
+#[test]
+fn invalid_field() {
+    // Truncate the value of the first output's amount to seven bytes.
+    let buf = bytes(&V2_HEX.replace("0103080008af2f00000000", "0103070008af2f000000"));
+    let view = PsbtRef::new(&buf).expect("valid structure");
+
+    let err = view.output(0).expect("first output").amount().expect_err("invalid amount");
+    assert!(matches!(err, FieldError::Deserialize { field: "PSBT_OUT_AMOUNT", .. }));
+}
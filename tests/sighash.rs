@@ -0,0 +1,113 @@
+//! Sighash message computation for PSBT v2 inputs.
+
+#![cfg(feature = "std")]
+
+use psbt_v2::bitcoin::hashes::Hash as _;
+use psbt_v2::bitcoin::key::{PublicKey, XOnlyPublicKey};
+use psbt_v2::bitcoin::secp256k1::{Secp256k1, SecretKey};
+use psbt_v2::bitcoin::sighash::SighashCache;
+use psbt_v2::bitcoin::taproot::{LeafVersion, TapLeafHash};
+use psbt_v2::bitcoin::{
+    absolute, transaction, Amount, OutPoint, ScriptBuf, Transaction, TxOut, Txid,
+};
+use psbt_v2::v2::{
+    Constructor, InputBuilder, Modifiable, OutputBuilder, Psbt, PsbtSighashMsg, Signer,
+};
+
+fn public_key() -> PublicKey {
+    let secp = Secp256k1::new();
+    let sk = SecretKey::from_slice(&[0x01; 32]).expect("valid secret key");
+    PublicKey::new(sk.public_key(&secp))
+}
+
+fn x_only() -> XOnlyPublicKey { public_key().inner.x_only_public_key().0 }
+
+/// Creates a PSBT with a single input funded by `script_pubkey`.
+fn psbt(script_pubkey: ScriptBuf) -> Psbt {
+    let utxo = TxOut { value: Amount::from_sat(100_000), script_pubkey };
+    let input = if utxo.script_pubkey.is_witness_program() {
+        let out_point = OutPoint { txid: Txid::all_zeros(), vout: 0 };
+        InputBuilder::new(&out_point).segwit_fund(utxo).build()
+    } else {
+        // Non-segwit inputs must be funded with the whole previous transaction.
+        let tx = Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![],
+            output: vec![utxo],
+        };
+        InputBuilder::new(&OutPoint { txid: tx.txid(), vout: 0 }).legacy_fund(tx).build()
+    };
+    let output = OutputBuilder::new(TxOut {
+        value: Amount::from_sat(90_000),
+        script_pubkey: ScriptBuf::new_op_return([0x01]),
+    })
+    .build();
+
+    Constructor::<Modifiable>::default()
+        .input(input)
+        .output(output)
+        .psbt()
+        .expect("valid lock time combination")
+}
+
+fn unsigned_tx(psbt: &Psbt) -> Transaction {
+    Signer::new(psbt.clone()).expect("valid lock time combination").unsigned_tx()
+}
+
+#[test]
+fn sighash_msg_legacy() {
+    let psbt = psbt(ScriptBuf::new_p2pkh(&public_key().pubkey_hash()));
+    let tx = unsigned_tx(&psbt);
+    let mut cache = SighashCache::new(&tx);
+
+    let msg = psbt.sighash_msg(0, &mut cache, None).expect("failed to compute sighash");
+    assert!(matches!(msg, PsbtSighashMsg::LegacySighash(_)));
+
+    let (ecdsa, _) = psbt.sighash_ecdsa(0, &mut cache).expect("failed to compute sighash");
+    assert_eq!(msg.to_secp_msg(), ecdsa);
+}
+
+#[test]
+fn sighash_msg_segwit_v0() {
+    let wpkh = public_key().wpubkey_hash().expect("compressed key");
+    let psbt = psbt(ScriptBuf::new_p2wpkh(&wpkh));
+    let tx = unsigned_tx(&psbt);
+    let mut cache = SighashCache::new(&tx);
+
+    let msg = psbt.sighash_msg(0, &mut cache, None).expect("failed to compute sighash");
+    assert!(matches!(msg, PsbtSighashMsg::SegwitV0Sighash(_)));
+
+    let (ecdsa, _) = psbt.sighash_ecdsa(0, &mut cache).expect("failed to compute sighash");
+    assert_eq!(msg.to_secp_msg(), ecdsa);
+}
+
+#[test]
+fn sighash_msg_taproot() {
+    let secp = Secp256k1::new();
+    let psbt = psbt(ScriptBuf::new_p2tr(&secp, x_only(), None));
+    let tx = unsigned_tx(&psbt);
+    let mut cache = SighashCache::new(&tx);
+
+    let key_path = psbt.sighash_msg(0, &mut cache, None).expect("failed to compute sighash");
+    assert!(matches!(key_path, PsbtSighashMsg::TapSighash(_)));
+    let (schnorr, _) =
+        psbt.sighash_taproot(0, &mut cache, None).expect("failed to compute sighash");
+    assert_eq!(key_path.to_secp_msg(), schnorr);
+
+    let leaf_hash = TapLeafHash::from_script(&ScriptBuf::new(), LeafVersion::TapScript);
+    let script_path =
+        psbt.sighash_msg(0, &mut cache, Some(leaf_hash)).expect("failed to compute sighash");
+    assert_ne!(script_path, key_path);
+    let (schnorr, _) =
+        psbt.sighash_taproot(0, &mut cache, Some(leaf_hash)).expect("failed to compute sighash");
+    assert_eq!(script_path.to_secp_msg(), schnorr);
+}
+
+#[test]
+fn sighash_msg_index_out_of_bounds() {
+    let psbt = psbt(ScriptBuf::new_p2pkh(&public_key().pubkey_hash()));
+    let tx = unsigned_tx(&psbt);
+
+    assert!(psbt.sighash_msg(1, &mut SighashCache::new(&tx), None).is_err());
+}
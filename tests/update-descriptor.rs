@@ -0,0 +1,162 @@
+//! Descriptor based updating of PSBT v2 inputs.
+
+#![cfg(all(feature = "std", feature = "miniscript"))]
+
+use core::str::FromStr;
+
+use psbt_v2::bitcoin::hashes::Hash as _;
+use psbt_v2::bitcoin::{Amount, OutPoint, ScriptBuf, TxOut, Txid};
+use psbt_v2::miniscript::descriptor::{DefiniteDescriptorKey, Descriptor, DescriptorPublicKey};
+use psbt_v2::v2::{
+    Constructor, InputBuilder, Modifiable, OutputBuilder, UpdateInputError, UpdateOutputError,
+    Updater,
+};
+
+const XPUB: &str = "tpubD6NzVbkrYhZ4XgiXtGrdW5XDAPFCL9h7we1vwNCpn8tGbBcgfVYjXyhWo4E1xkh56hjod1RhGjxbaTLV3X4FyWuejifB9jusQ46QzG87VKp";
+const OTHER_XPUB: &str = "tpubD6NzVbkrYhZ4WQdzxL7NmJN7b85ePo4p6RSj9QQHF7te2RR9iUeVSGgnGkoUsB9LBRosgvNbjRv9bcsJgzgBd7QKuxDm23ZewkTRzNSLEDr";
+
+fn descriptor(s: &str) -> Descriptor<DefiniteDescriptorKey> {
+    Descriptor::<DescriptorPublicKey>::from_str(s)
+        .expect("valid descriptor")
+        .at_derivation_index(0)
+        .expect("valid derivation index")
+}
+
+/// Creates an updater for a PSBT with a single input funded by `script_pubkey`.
+fn updater(script_pubkey: ScriptBuf) -> Updater {
+    updater_with_output(script_pubkey, ScriptBuf::new_op_return([0x01]))
+}
+
+/// Creates an updater for a PSBT with a single input funded by `input_spk` and a single output
+/// paying to `output_spk`.
+fn updater_with_output(input_spk: ScriptBuf, output_spk: ScriptBuf) -> Updater {
+    let out_point = OutPoint { txid: Txid::all_zeros(), vout: 0 };
+    let utxo = TxOut { value: Amount::from_sat(100_000), script_pubkey: input_spk };
+    let input = InputBuilder::new(&out_point).segwit_fund(utxo).build();
+    let output =
+        OutputBuilder::new(TxOut { value: Amount::from_sat(90_000), script_pubkey: output_spk })
+            .build();
+
+    Constructor::<Modifiable>::default()
+        .input(input)
+        .output(output)
+        .updater()
+        .expect("valid lock time combination")
+}
+
+#[test]
+fn update_input_wpkh() {
+    let desc = descriptor(&format!("wpkh([d34db33f/84'/1'/0']{}/0/*)", XPUB));
+    let psbt = updater(desc.script_pubkey())
+        .update_input_with_descriptor(0, &desc)
+        .expect("failed to update")
+        .psbt();
+
+    let input = &psbt.inputs[0];
+    assert_eq!(input.bip32_derivations.len(), 1);
+    assert!(input.witness_script.is_none());
+    assert!(input.redeem_script.is_none());
+}
+
+#[test]
+fn update_input_sh_wsh() {
+    let desc = descriptor(&format!("sh(wsh(multi(1,{}/0/*,{}/0/*)))", XPUB, OTHER_XPUB));
+    let psbt = updater(desc.script_pubkey())
+        .update_input_with_descriptor(0, &desc)
+        .expect("failed to update")
+        .psbt();
+
+    let input = &psbt.inputs[0];
+    let witness_script = input.witness_script.clone().expect("witness script");
+    assert_eq!(input.redeem_script, Some(witness_script.to_p2wsh()));
+    assert_eq!(desc.explicit_script().expect("not taproot"), witness_script);
+    assert_eq!(input.bip32_derivations.len(), 2);
+}
+
+#[test]
+fn update_input_tr() {
+    let desc = descriptor(&format!("tr({}/0/*,pk({}/0/*))", XPUB, OTHER_XPUB));
+    let psbt = updater(desc.script_pubkey())
+        .update_input_with_descriptor(0, &desc)
+        .expect("failed to update")
+        .psbt();
+
+    let input = &psbt.inputs[0];
+    let internal_key = input.tap_internal_key.expect("internal key");
+    assert!(input.tap_merkle_root.is_some());
+    assert_eq!(input.tap_scripts.len(), 1);
+    assert_eq!(input.tap_key_origins.len(), 2);
+    assert!(input.tap_key_origins[&internal_key].0.is_empty());
+}
+
+#[test]
+fn update_input_mismatched_script_pubkey() {
+    let desc = descriptor(&format!("wpkh({}/0/*)", XPUB));
+    let other = descriptor(&format!("wpkh({}/0/*)", OTHER_XPUB));
+
+    let err = updater(other.script_pubkey())
+        .update_input_with_descriptor(0, &desc)
+        .expect_err("script pubkey does not match");
+    assert_eq!(err, UpdateInputError::MismatchedScriptPubkey);
+}
+
+#[test]
+fn update_input_non_segwit_requires_non_witness_utxo() {
+    let desc = descriptor(&format!("pkh({}/0/*)", XPUB));
+
+    let err = updater(desc.script_pubkey())
+        .update_input_with_descriptor(0, &desc)
+        .expect_err("non-segwit descriptor needs full funding transaction");
+    assert_eq!(err, UpdateInputError::MissingNonWitnessUtxo);
+}
+
+#[test]
+fn update_output_sh_wpkh() {
+    let input = descriptor(&format!("wpkh({}/0/*)", XPUB));
+    let change = descriptor(&format!("sh(wpkh([d34db33f/49'/1'/0']{}/1/*))", XPUB));
+    let psbt = updater_with_output(input.script_pubkey(), change.script_pubkey())
+        .update_output_with_descriptor(0, &change)
+        .expect("failed to update")
+        .psbt();
+
+    let output = &psbt.outputs[0];
+    assert!(output.redeem_script.is_some());
+    assert!(output.witness_script.is_none());
+    assert_eq!(output.bip32_derivations.len(), 1);
+}
+
+#[test]
+fn update_output_tr() {
+    let input = descriptor(&format!("wpkh({}/0/*)", XPUB));
+    let change = descriptor(&format!("tr({}/1/*,pk({}/1/*))", XPUB, OTHER_XPUB));
+    let psbt = updater_with_output(input.script_pubkey(), change.script_pubkey())
+        .update_output_with_descriptor(0, &change)
+        .expect("failed to update")
+        .psbt();
+
+    let output = &psbt.outputs[0];
+    let internal_key = output.tap_internal_key.expect("internal key");
+    assert!(output.tap_tree.is_some());
+    assert_eq!(output.tap_key_origins.len(), 2);
+    assert!(output.tap_key_origins[&internal_key].0.is_empty());
+}
+
+#[test]
+fn update_output_mismatched_script_pubkey() {
+    let change = descriptor(&format!("wpkh({}/1/*)", XPUB));
+
+    let err = updater(change.script_pubkey())
+        .update_output_with_descriptor(0, &change)
+        .expect_err("script pubkey does not match");
+    assert_eq!(err, UpdateOutputError::MismatchedScriptPubkey);
+}
+
+#[test]
+fn update_output_index_out_of_bounds() {
+    let change = descriptor(&format!("wpkh({}/1/*)", XPUB));
+
+    let err = updater(change.script_pubkey())
+        .update_output_with_descriptor(1, &change)
+        .expect_err("there is only one output");
+    assert!(matches!(err, UpdateOutputError::IndexOutOfBounds(_)));
+}